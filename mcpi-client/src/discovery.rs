@@ -17,6 +17,7 @@ impl Error for McpDiscoveryError {}
 
 // --- Service Info (Unchanged) ---
 #[derive(Debug, Clone)]
+#[allow(dead_code)]
 pub struct McpServiceInfo { pub endpoint: String, pub version: String }
 impl McpServiceInfo { pub fn new(endpoint: String, version: String) -> Self { McpServiceInfo { endpoint, version } } }
 
@@ -0,0 +1,86 @@
+// mcpi-client/src/http_client.rs
+//
+// HTTP client used for discovery and Streamable HTTP. Requests go over TCP with
+// reqwest, or straight to a Unix domain socket when the base URL is `unix:`.
+
+use bytes::Bytes;
+use futures::{Stream, StreamExt, TryStreamExt};
+use reqwest::{header::HeaderMap, Client as ReqwestClient, Method, StatusCode};
+use serde::de::DeserializeOwned;
+use std::error::Error;
+use std::io;
+use std::pin::Pin;
+
+#[cfg(unix)]
+use crate::unix_socket::UnixClient;
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+/// Response body as a stream of chunks
+pub type BodyStream = Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send>>;
+
+#[derive(Clone)]
+pub enum HttpClient {
+    Tcp(ReqwestClient),
+    #[cfg(unix)]
+    Unix(UnixClient),
+}
+
+impl HttpClient {
+    pub async fn get(&self, url: &str, headers: HeaderMap) -> Result<HttpResponse, BoxedError> {
+        self.send(Method::GET, url, headers, None).await
+    }
+
+    pub async fn post(&self, url: &str, headers: HeaderMap, body: String) -> Result<HttpResponse, BoxedError> {
+        self.send(Method::POST, url, headers, Some(body)).await
+    }
+
+    async fn send(&self, method: Method, url: &str, headers: HeaderMap, body: Option<String>) -> Result<HttpResponse, BoxedError> {
+        match self {
+            HttpClient::Tcp(client) => {
+                let mut request = client.request(method, url).headers(headers);
+                if let Some(body) = body {
+                    request = request.body(body);
+                }
+                let response = request.send().await?;
+                Ok(HttpResponse {
+                    status: response.status(),
+                    headers: response.headers().clone(),
+                    body: Box::pin(response.bytes_stream().map_err(io::Error::other)),
+                })
+            }
+            #[cfg(unix)]
+            HttpClient::Unix(client) => client.send(method, url, &headers, body).await,
+        }
+    }
+}
+
+pub struct HttpResponse {
+    pub(crate) status: StatusCode,
+    pub(crate) headers: HeaderMap,
+    pub(crate) body: BodyStream,
+}
+
+impl HttpResponse {
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Read the whole body and parse it as JSON
+    pub async fn json<T: DeserializeOwned>(self) -> Result<T, BoxedError> {
+        let mut body = Vec::new();
+        let mut chunks = self.body;
+        while let Some(chunk) = chunks.next().await {
+            body.extend_from_slice(&chunk?);
+        }
+        Ok(serde_json::from_slice(&body)?)
+    }
+
+    pub fn bytes_stream(self) -> BodyStream {
+        self.body
+    }
+}
@@ -1,39 +1,56 @@
 // mcpi-client/src/main.rs
-use bytes::Bytes;
 use clap::{Parser, Subcommand};
-use futures::{SinkExt, StreamExt, TryStreamExt}; // Added TryStreamExt
+use futures::{SinkExt, StreamExt};
 use mcpi_common::{
-    BlobResourceContents, CallToolResult, CapabilityDescription, ContentItem, DiscoveryResponse,
+    CallToolResult, ContentItem, DiscoveryResponse,
     InitializeParams, InitializeResult, ListResourcesResult, ListToolsResult, MCPRequest,
-    MCPResponse, Provider, Referral, ResourceContentUnion, TextResourceContents,
-    LATEST_MCP_VERSION, MCPI_VERSION,
+    MCPResponse, ResourceContentUnion,
+    LATEST_MCP_VERSION,
 };
 use rand::Rng;
 use reqwest::{
     header::{HeaderMap, HeaderName, HeaderValue, ACCEPT, CONTENT_TYPE},
-    Client as ReqwestClient, Error as ReqwestError,
+    Client as ReqwestClient,
 };
 use serde_json::{json, Value};
 use std::error::Error;
-use std::sync::{Arc, RwLock}; // Use std::sync::RwLock
 use tokio::io::AsyncBufReadExt;
-use tokio_tungstenite::{connect_async, tungstenite::protocol::Message};
+use std::path::PathBuf;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::{connect_async_tls_with_config, tungstenite::protocol::Message, WebSocketStream};
 use tokio_util::io::StreamReader;
 use tracing::warn; // Use tracing::warn
 
 mod discovery;
+mod http_client;
+mod tls;
+#[cfg(unix)]
+mod unix_socket;
+
+use http_client::{HttpClient, HttpResponse};
+use tls::CaBundle;
 
 static MCP_SESSION_ID_HEADER: HeaderName = HeaderName::from_static("mcp-session-id");
+static LAST_EVENT_ID_HEADER: HeaderName = HeaderName::from_static("last-event-id");
+const SSE_DEFAULT_RETRY_MS: u64 = 1000;
+const SSE_MAX_RECONNECT_ATTEMPTS: u32 = 5;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[arg(short, long)]
     domain: Option<String>,
+    /// Server base URL: http(s)://host:port or unix:/path/to/socket
     #[arg(short = 'u', long)]
     base_url: Option<String>,
     #[arg(short, long)]
     plugin: Option<String>,
+    /// PEM file of CA certificates to trust for HTTPS/WSS, e.g. a self-signed server certificate
+    #[arg(long)]
+    ca_cert: Option<PathBuf>,
+    /// Host header sent over a unix: base URL, which selects the server's tenant
+    #[arg(long, default_value = "localhost")]
+    host: String,
 }
 
 #[derive(Subcommand)]
@@ -56,6 +73,7 @@ async fn main() -> Result<(), BoxedError> {
     let mut protocol = Protocol::McpHttp;
     let mut discovery_url = String::from("http://localhost:3001/mcpi/discover");
     let mut service_base_url = String::from("http://localhost:3001");
+    let mut unix_socket: Option<PathBuf> = None;
 
     // Determine protocol and base URL
     if let Some(domain) = &cli.domain {
@@ -86,6 +104,12 @@ async fn main() -> Result<(), BoxedError> {
             protocol = Protocol::McpHttp;
             println!("Using provided base URL: {}", base_url_arg);
             service_base_url = base_url_arg.trim_end_matches('/').to_string();
+            #[cfg(unix)]
+            if let Some(socket) = unix_socket::socket_path(&service_base_url) {
+                println!("  Connecting over Unix socket {} as host {}", socket.display(), cli.host);
+                service_base_url = format!("http://{}", cli.host);
+                unix_socket = Some(socket);
+            }
             discovery_url = format!("{}/mcpi/discover", service_base_url);
             println!("  Protocol: MCP (HTTP)");
             println!("  Derived Discovery URL: {}", discovery_url);
@@ -95,8 +119,16 @@ async fn main() -> Result<(), BoxedError> {
         }
     }
 
+    let ca_bundle = cli.ca_cert.as_deref().map(CaBundle::load).transpose()?;
+    let http_client = match (&unix_socket, &ca_bundle) {
+        #[cfg(unix)]
+        (Some(socket), _) => HttpClient::Unix(unix_socket::UnixClient::new(socket.clone())),
+        (_, Some(bundle)) => HttpClient::Tcp(bundle.http_client()?),
+        _ => HttpClient::Tcp(ReqwestClient::new()),
+    };
+
     println!("\nDiscovering service capabilities via HTTP discovery endpoint...");
-    let discovery_resp = discover_service_http(&discovery_url).await?;
+    let discovery_resp = discover_service_http(&http_client, &discovery_url).await?;
     println!(
         "Provider: {} ({})",
         discovery_resp.provider.name, discovery_resp.provider.domain
@@ -120,12 +152,27 @@ async fn main() -> Result<(), BoxedError> {
                 service_base_url.replace("http://", "ws://") + "/mcpi"
             };
             println!("\nConnecting via WebSocket (MCPI) to {}...", ws_url);
-            run_mcpi_websocket_client(ws_url, cli.plugin, discovery_resp).await?;
+            match &unix_socket {
+                #[cfg(unix)]
+                Some(socket) => {
+                    let ws_stream = unix_socket::connect_websocket(socket, &ws_url)
+                        .await
+                        .map_err(|e| format!("WS connection failed: {}", e))?;
+                    run_mcpi_websocket_client(ws_stream, cli.plugin, discovery_resp).await?;
+                }
+                _ => {
+                    let connector = ca_bundle.as_ref().map(CaBundle::ws_connector).transpose()?;
+                    let (ws_stream, _) = connect_async_tls_with_config(&ws_url, None, false, connector)
+                        .await
+                        .map_err(|e| format!("WS connection failed: {}", e))?;
+                    run_mcpi_websocket_client(ws_stream, cli.plugin, discovery_resp).await?;
+                }
+            }
         }
         Protocol::McpHttp => {
             let mcp_url = format!("{}/mcp", service_base_url);
             println!("\nConnecting via Streamable HTTP (MCP) to {}...", mcp_url);
-            run_mcp_http_client(mcp_url, cli.plugin, discovery_resp).await?;
+            run_mcp_http_client(http_client, mcp_url, cli.plugin, discovery_resp).await?;
         }
     }
     Ok(())
@@ -133,32 +180,17 @@ async fn main() -> Result<(), BoxedError> {
 
 // --- Streamable HTTP Client Implementation (MCP) ---
 async fn run_mcp_http_client(
+    http_client: HttpClient,
     mcp_url: String,
     specific_plugin: Option<String>,
     _discovery_resp: DiscoveryResponse,
 ) -> Result<(), BoxedError> {
-    let http_client = ReqwestClient::new();
     let mut session_id: Option<String> = None;
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
 
     println!("\nEstablishing SSE connection via GET {}...", mcp_url);
-    let get_response = http_client
-        .get(&mcp_url)
-        .header(ACCEPT, "text/event-stream")
-        .send()
-        .await?;
-
-    if !get_response.status().is_success() {
-        return Err(format!("GET /mcp failed status: {}", get_response.status()).into());
-    }
-    if let Some(ct) = get_response.headers().get(CONTENT_TYPE) {
-        if !ct.to_str()?.starts_with("text/event-stream") {
-            return Err(format!("Expected text/event-stream, got: {:?}", ct).into());
-        }
-    } else {
-        return Err("Missing Content-Type on GET /mcp response".into());
-    }
+    let get_response = connect_sse(&http_client, &mcp_url, None, None).await?;
 
     // FIX E0507: Pass static HeaderName by reference to .get()
     if let Some(sid_value) = get_response.headers().get(&MCP_SESSION_ID_HEADER) {
@@ -172,83 +204,12 @@ async fn run_mcp_http_client(
     }
     println!("SSE stream connected.");
 
-    let body_stream = get_response
-        .bytes_stream()
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e));
-    let stream_reader = StreamReader::new(body_stream);
-    let mut lines = stream_reader.lines();
-
-    let last_event_id = Arc::new(RwLock::new(None::<String>));
-    let last_event_id_clone = last_event_id.clone();
-
-    tokio::spawn(async move {
-        println!("SSE Listener Task Started.");
-        let mut current_event_type = String::new();
-        let mut current_data = String::new();
-
-        while let Ok(Some(line)) = lines.next_line().await {
-            if line.is_empty() {
-                if !current_data.is_empty() {
-                    let event_type = if current_event_type.is_empty() {
-                        "message".to_string()
-                    } else {
-                        current_event_type.clone()
-                    };
-                    let data = current_data.trim_end_matches('\n');
-                    let id = { last_event_id_clone.read().unwrap().clone() };
-
-                    println!("\n--- SSE Event Received ---");
-                    println!("Event: {}", event_type);
-                    if let Some(id_val) = &id {
-                        println!("ID: {}", id_val);
-                    }
-                    println!("Data: {}", data);
-                    match serde_json::from_str::<Value>(data) {
-                        Ok(json_data) => {
-                            println!(
-                                "Parsed Data: {}",
-                                serde_json::to_string_pretty(&json_data).unwrap_or_default()
-                            );
-                            if event_type == "message" {
-                                if let Ok(mcp_notif) = serde_json::from_value::<Value>(json_data) {
-                                    if mcp_notif.get("method").and_then(|m| m.as_str())
-                                        == Some("notifications/progress")
-                                    {
-                                        println!(
-                                            "  -> Progress Update: {:?}",
-                                            mcp_notif.get("params")
-                                        );
-                                    } else {
-                                        println!(
-                                            "  -> Other MCP Notification/Request: {:?}",
-                                            mcp_notif
-                                        );
-                                    }
-                                }
-                            }
-                        }
-                        Err(e) => {
-                            warn!("SSE data was not valid JSON: {}", e);
-                        }
-                    }
-                    println!("--- End SSE Event ---");
-                }
-                current_data.clear();
-                current_event_type.clear();
-            } else if let Some(data) = line.strip_prefix("data:") {
-                current_data.push_str(data.trim_start());
-                current_data.push('\n');
-            } else if let Some(event) = line.strip_prefix("event:") {
-                current_event_type = event.trim().to_string();
-            } else if let Some(id) = line.strip_prefix("id:") {
-                let id_str = id.trim().to_string();
-                if !id_str.is_empty() {
-                    *last_event_id_clone.write().unwrap() = Some(id_str);
-                }
-            }
-        }
-        println!("SSE Listener Task Ended.");
-    });
+    tokio::spawn(run_sse_listener(
+        http_client.clone(),
+        mcp_url.clone(),
+        session_id.clone(),
+        get_response,
+    ));
 
     println!("\nSending initialize request via POST {}...", mcp_url);
     let init_params = InitializeParams {
@@ -267,10 +228,7 @@ async fn run_mcp_http_client(
     };
     let init_req_str = serde_json::to_string(&init_request)?;
     let init_resp = http_client
-        .post(&mcp_url)
-        .headers(headers.clone())
-        .body(init_req_str)
-        .send()
+        .post(&mcp_url, headers.clone(), init_req_str)
         .await?;
     if !init_resp.status().is_success() {
         return Err(format!("Initialize POST failed status: {}", init_resp.status()).into());
@@ -301,10 +259,7 @@ async fn run_mcp_http_client(
     };
     let list_res_str = serde_json::to_string(&list_res_req)?;
     let list_res_resp = http_client
-        .post(&mcp_url)
-        .headers(headers.clone())
-        .body(list_res_str)
-        .send()
+        .post(&mcp_url, headers.clone(), list_res_str)
         .await?
         .json::<MCPResponse>()
         .await?;
@@ -348,10 +303,7 @@ async fn run_mcp_http_client(
     };
     let list_tools_str = serde_json::to_string(&list_tools_req)?;
     let list_tools_resp = http_client
-        .post(&mcp_url)
-        .headers(headers.clone())
-        .body(list_tools_str)
-        .send()
+        .post(&mcp_url, headers.clone(), list_tools_str)
         .await?
         .json::<MCPResponse>()
         .await?;
@@ -411,10 +363,7 @@ async fn run_mcp_http_client(
     let batch_req_data = json!([{ "jsonrpc": "2.0", "id": 10, "method": "ping", "params": null }, { "jsonrpc": "2.0", "id": 11, "method": "resources/list", "params": null }]);
     let batch_req_str = serde_json::to_string(&batch_req_data)?;
     let batch_resp = http_client
-        .post(&mcp_url)
-        .headers(headers.clone())
-        .body(batch_req_str)
-        .send()
+        .post(&mcp_url, headers.clone(), batch_req_str)
         .await?
         .json::<Vec<MCPResponse>>()
         .await?;
@@ -482,10 +431,7 @@ async fn run_mcp_http_client(
     };
     let ping_req_str = serde_json::to_string(&ping_req)?;
     let ping_resp = http_client
-        .post(&mcp_url)
-        .headers(headers)
-        .body(ping_req_str)
-        .send()
+        .post(&mcp_url, headers, ping_req_str)
         .await?
         .json::<MCPResponse>()
         .await?;
@@ -503,15 +449,163 @@ async fn run_mcp_http_client(
     Ok(())
 }
 
+// Open the session's SSE stream, resuming after `last_event_id` if given
+async fn connect_sse(
+    http_client: &HttpClient,
+    mcp_url: &str,
+    session_id: Option<&str>,
+    last_event_id: Option<&str>,
+) -> Result<HttpResponse, BoxedError> {
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT, HeaderValue::from_static("text/event-stream"));
+    if let Some(sid) = session_id {
+        headers.insert(MCP_SESSION_ID_HEADER.clone(), HeaderValue::from_str(sid)?);
+    }
+    if let Some(id) = last_event_id {
+        headers.insert(LAST_EVENT_ID_HEADER.clone(), HeaderValue::from_str(id)?);
+    }
+    let response = http_client.get(mcp_url, headers).await?;
+
+    if !response.status().is_success() {
+        return Err(format!("GET /mcp failed status: {}", response.status()).into());
+    }
+    if let Some(ct) = response.headers().get(CONTENT_TYPE) {
+        if !ct.to_str()?.starts_with("text/event-stream") {
+            return Err(format!("Expected text/event-stream, got: {:?}", ct).into());
+        }
+    } else {
+        return Err("Missing Content-Type on GET /mcp response".into());
+    }
+    Ok(response)
+}
+
+// Read the SSE stream, reconnecting with Last-Event-ID whenever it drops so the
+// server can replay anything sent in between
+async fn run_sse_listener(
+    http_client: HttpClient,
+    mcp_url: String,
+    session_id: Option<String>,
+    mut response: HttpResponse,
+) {
+    println!("SSE Listener Task Started.");
+    let mut last_event_id: Option<String> = None;
+    let mut retry_ms = SSE_DEFAULT_RETRY_MS;
+
+    'listen: loop {
+        read_sse_events(response, &mut last_event_id, &mut retry_ms).await;
+
+        // Without a session there is nothing to resume
+        let Some(sid) = session_id.as_deref() else { break };
+        for attempt in 1..=SSE_MAX_RECONNECT_ATTEMPTS {
+            let delay = retry_ms * u64::from(attempt);
+            println!(
+                "SSE stream closed, reconnecting in {}ms (attempt {}/{}, last event ID: {})",
+                delay,
+                attempt,
+                SSE_MAX_RECONNECT_ATTEMPTS,
+                last_event_id.as_deref().unwrap_or("none")
+            );
+            tokio::time::sleep(tokio::time::Duration::from_millis(delay)).await;
+            match connect_sse(&http_client, &mcp_url, Some(sid), last_event_id.as_deref()).await {
+                Ok(resumed) => {
+                    println!("SSE stream reconnected.");
+                    response = resumed;
+                    continue 'listen;
+                }
+                Err(e) => warn!("SSE reconnect failed: {}", e),
+            }
+        }
+        println!("Giving up on SSE stream after {} attempts.", SSE_MAX_RECONNECT_ATTEMPTS);
+        break;
+    }
+    println!("SSE Listener Task Ended.");
+}
+
+// Print events from one SSE connection until it closes, tracking the last event
+// ID and any retry interval the server asks for
+async fn read_sse_events(
+    response: HttpResponse,
+    last_event_id: &mut Option<String>,
+    retry_ms: &mut u64,
+) {
+    let stream_reader = StreamReader::new(response.bytes_stream());
+    let mut lines = stream_reader.lines();
+
+    let mut current_event_type = String::new();
+    let mut current_data = String::new();
+    let mut current_id: Option<String> = None;
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.is_empty() {
+            if !current_data.is_empty() {
+                let event_type = if current_event_type.is_empty() {
+                    "message".to_string()
+                } else {
+                    current_event_type.clone()
+                };
+                let data = current_data.trim_end_matches('\n');
+
+                println!("\n--- SSE Event Received ---");
+                println!("Event: {}", event_type);
+                if let Some(id_val) = &current_id {
+                    println!("ID: {}", id_val);
+                }
+                println!("Data: {}", data);
+                match serde_json::from_str::<Value>(data) {
+                    Ok(json_data) => {
+                        println!(
+                            "Parsed Data: {}",
+                            serde_json::to_string_pretty(&json_data).unwrap_or_default()
+                        );
+                        if event_type == "message" {
+                            if json_data.get("method").and_then(|m| m.as_str())
+                                == Some("notifications/progress")
+                            {
+                                println!("  -> Progress Update: {:?}", json_data.get("params"));
+                            } else {
+                                println!("  -> Other MCP Notification/Request: {:?}", json_data);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        warn!("SSE data was not valid JSON: {}", e);
+                    }
+                }
+                println!("--- End SSE Event ---");
+            }
+            // The last event ID only advances once an event is fully received
+            if let Some(id) = current_id.take() {
+                *last_event_id = Some(id);
+            }
+            current_data.clear();
+            current_event_type.clear();
+        } else if let Some(data) = line.strip_prefix("data:") {
+            current_data.push_str(data.trim_start());
+            current_data.push('\n');
+        } else if let Some(event) = line.strip_prefix("event:") {
+            current_event_type = event.trim().to_string();
+        } else if let Some(id) = line.strip_prefix("id:") {
+            let id_str = id.trim().to_string();
+            if !id_str.is_empty() {
+                current_id = Some(id_str);
+            }
+        } else if let Some(retry) = line.strip_prefix("retry:") {
+            if let Ok(ms) = retry.trim().parse::<u64>() {
+                *retry_ms = ms;
+            }
+        }
+    }
+}
+
 // --- WebSocket Client Implementation (MCPI) ---
-async fn run_mcpi_websocket_client(
-    ws_url: String,
+async fn run_mcpi_websocket_client<T>(
+    ws_stream: WebSocketStream<T>,
     specific_plugin: Option<String>,
     _discovery_resp: DiscoveryResponse,
-) -> Result<(), BoxedError> {
-    let (ws_stream, _) = connect_async(&ws_url)
-        .await
-        .map_err(|e| format!("WS connection failed: {}", e))?;
+) -> Result<(), BoxedError>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
     println!("WebSocket connection established.");
     let (mut write, mut read) = ws_stream.split();
     let init_request = MCPRequest {
@@ -740,14 +834,12 @@ async fn run_mcpi_websocket_client(
 }
 
 // --- Helper Functions ---
-async fn discover_service_http(url: &str) -> Result<DiscoveryResponse, BoxedError> {
-    let client = reqwest::Client::new();
-    Ok(client
-        .get(url)
-        .send()
+async fn discover_service_http(client: &HttpClient, url: &str) -> Result<DiscoveryResponse, BoxedError> {
+    client
+        .get(url, HeaderMap::new())
         .await?
         .json::<DiscoveryResponse>()
-        .await?)
+        .await
 }
 fn generate_test_arguments(_tool_name: &str, operation: &str, schema: &Value) -> Value {
     let mut args = json!({"operation": operation});
@@ -781,6 +873,7 @@ fn generate_test_arguments(_tool_name: &str, operation: &str, schema: &Value) ->
     }
     args
 }
+#[allow(dead_code)]
 async fn get_completions<S, R>(
     write: &mut S,
     read: &mut R,
@@ -927,7 +1020,7 @@ where
     Ok(())
 }
 async fn call_tool_http(
-    client: &ReqwestClient,
+    client: &HttpClient,
     mcp_url: &str,
     session_id: Option<&str>,
     name: &str,
@@ -935,16 +1028,18 @@ async fn call_tool_http(
     arguments: Value,
 ) -> Result<(), BoxedError> {
     println!("\nTesting {} with {} operation (HTTP)", name, operation);
+    let request_id = format!(
+        "{}-{}-{}",
+        name,
+        operation,
+        rand::thread_rng().gen::<u16>()
+    );
     let req = MCPRequest {
         jsonrpc: "2.0".to_string(),
-        id: json!(format!(
-            "{}-{}-{}",
-            name,
-            operation,
-            rand::thread_rng().gen::<u16>()
-        )),
+        id: json!(request_id),
         method: "tools/call".to_string(),
-        params: Some(json!({"name":name,"arguments":arguments})),
+        // Ask for progress so the server answers with an SSE stream
+        params: Some(json!({"name":name,"arguments":arguments,"_meta":{"progressToken":request_id}})),
     };
     println!(
         "Request Params: {}",
@@ -953,15 +1048,14 @@ async fn call_tool_http(
     let req_str = serde_json::to_string(&req)?;
     let mut headers = HeaderMap::new();
     headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    headers.insert(
+        ACCEPT,
+        HeaderValue::from_static("application/json, text/event-stream"),
+    );
     if let Some(sid) = session_id {
         headers.insert(MCP_SESSION_ID_HEADER.clone(), HeaderValue::from_str(sid)?);
     }
-    let response = client
-        .post(mcp_url)
-        .headers(headers)
-        .body(req_str)
-        .send()
-        .await?;
+    let response = client.post(mcp_url, headers, req_str).await?;
     if !response.status().is_success() {
         return Err(format!("Tool call POST failed status: {}", response.status()).into());
     }
@@ -969,57 +1063,36 @@ async fn call_tool_http(
         .headers()
         .get(CONTENT_TYPE)
         .and_then(|v| v.to_str().ok())
-        .unwrap_or("");
+        .unwrap_or("")
+        .to_string();
     if content_type.starts_with("application/json") {
         let resp_body = response.json::<MCPResponse>().await?;
-        match resp_body {
-            MCPResponse { error: Some(e), .. } => {
-                println!("  Tool call error: {} ({})", e.message, e.code)
+        print_tool_call_response(resp_body);
+    } else if content_type.starts_with("text/event-stream") {
+        // The server streams related notifications, then the final response, then closes
+        println!("  Received SSE stream response from POST.");
+        let mut lines = StreamReader::new(response.bytes_stream()).lines();
+        let mut data = String::new();
+        while let Some(line) = lines.next_line().await? {
+            if let Some(d) = line.strip_prefix("data:") {
+                data.push_str(d.trim_start());
+                continue;
+            }
+            if !line.is_empty() || data.is_empty() {
+                continue;
+            }
+            let message: Value = serde_json::from_str(&data)?;
+            data.clear();
+            if let Some(method) = message.get("method").and_then(|m| m.as_str()) {
+                println!(
+                    "  -> {}: {}",
+                    method,
+                    message.get("params").cloned().unwrap_or_default()
+                );
+            } else {
+                print_tool_call_response(serde_json::from_value(message)?);
             }
-            MCPResponse {
-                result: Some(r), ..
-            } => match serde_json::from_value::<CallToolResult>(r.clone()) {
-                Ok(tr) => {
-                    println!("  Result{}", if tr.is_error { " (ERROR)" } else { "" });
-                    for c in tr.content {
-                        match c {
-                            ContentItem::Text { text, .. } => {
-                                if let Ok(j) = serde_json::from_str::<Value>(&text) {
-                                    println!(
-                                        "  {}",
-                                        serde_json::to_string_pretty(&j).unwrap_or(text)
-                                    );
-                                } else {
-                                    println!("  {}", text);
-                                }
-                            }
-                            ContentItem::Audio {
-                                data, mime_type, ..
-                            } => println!("  Audio: {}b, {}", data.len(), mime_type),
-                            ContentItem::Image {
-                                data, mime_type, ..
-                            } => println!("  Image: {}b, {}", data.len(), mime_type),
-                            ContentItem::Resource { resource, .. } => match resource {
-                                ResourceContentUnion::Text(tc) => {
-                                    println!("  Resource (Text): {}", tc.uri)
-                                }
-                                ResourceContentUnion::Blob(bc) => {
-                                    println!("  Resource (Blob): {}", bc.uri)
-                                }
-                            },
-                        }
-                    }
-                }
-                Err(e) => println!(
-                    "  Err parsing ToolCallResult: {}\nRaw: {}",
-                    e,
-                    serde_json::to_string_pretty(&r).unwrap_or_default()
-                ),
-            },
-            _ => println!("  Invalid tool call response format"),
         }
-    } else if content_type.starts_with("text/event-stream") {
-        println!("  Received SSE stream response from POST (Handling not implemented).");
     } else {
         println!(
             "  Received unexpected Content-Type from POST: {}",
@@ -1028,3 +1101,51 @@ async fn call_tool_http(
     }
     Ok(())
 }
+fn print_tool_call_response(resp_body: MCPResponse) {
+    match resp_body {
+        MCPResponse { error: Some(e), .. } => {
+            println!("  Tool call error: {} ({})", e.message, e.code)
+        }
+        MCPResponse {
+            result: Some(r), ..
+        } => match serde_json::from_value::<CallToolResult>(r.clone()) {
+            Ok(tr) => {
+                println!("  Result{}", if tr.is_error { " (ERROR)" } else { "" });
+                for c in tr.content {
+                    match c {
+                        ContentItem::Text { text, .. } => {
+                            if let Ok(j) = serde_json::from_str::<Value>(&text) {
+                                println!(
+                                    "  {}",
+                                    serde_json::to_string_pretty(&j).unwrap_or(text)
+                                );
+                            } else {
+                                println!("  {}", text);
+                            }
+                        }
+                        ContentItem::Audio {
+                            data, mime_type, ..
+                        } => println!("  Audio: {}b, {}", data.len(), mime_type),
+                        ContentItem::Image {
+                            data, mime_type, ..
+                        } => println!("  Image: {}b, {}", data.len(), mime_type),
+                        ContentItem::Resource { resource, .. } => match resource {
+                            ResourceContentUnion::Text(tc) => {
+                                println!("  Resource (Text): {}", tc.uri)
+                            }
+                            ResourceContentUnion::Blob(bc) => {
+                                println!("  Resource (Blob): {}", bc.uri)
+                            }
+                        },
+                    }
+                }
+            }
+            Err(e) => println!(
+                "  Err parsing ToolCallResult: {}\nRaw: {}",
+                e,
+                serde_json::to_string_pretty(&r).unwrap_or_default()
+            ),
+        },
+        _ => println!("  Invalid tool call response format"),
+    }
+}
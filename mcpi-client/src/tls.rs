@@ -0,0 +1,74 @@
+// mcpi-client/src/tls.rs
+//
+// Custom CA bundle support, so the client can talk to servers whose certificate
+// is not signed by a system-trusted CA (e.g. a self-signed certificate in local testing).
+
+use reqwest::Client as ReqwestClient;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+use tokio_tungstenite::Connector;
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+const PEM_CERT_END: &str = "-----END CERTIFICATE-----";
+
+/// Certificates to trust in addition to the system roots, as PEM blocks
+#[derive(Debug, Clone, Default)]
+pub struct CaBundle {
+    certs: Vec<String>,
+}
+
+impl CaBundle {
+    /// Read a PEM file containing one or more certificates
+    pub fn load(path: &Path) -> Result<Self, BoxedError> {
+        let pem = fs::read_to_string(path).map_err(|e| format!("Cannot read CA bundle {}: {}", path.display(), e))?;
+        let bundle = Self::from_pem(&pem);
+        if bundle.certs.is_empty() {
+            return Err(format!("No certificates found in CA bundle {}", path.display()).into());
+        }
+        Ok(bundle)
+    }
+
+    fn from_pem(pem: &str) -> Self {
+        let certs = pem
+            .split_inclusive(PEM_CERT_END)
+            .filter(|block| block.contains(PEM_CERT_END))
+            .map(|block| block.trim().to_string())
+            .collect();
+        CaBundle { certs }
+    }
+
+    /// HTTP client that trusts the bundle
+    pub fn http_client(&self) -> Result<ReqwestClient, BoxedError> {
+        let mut builder = ReqwestClient::builder();
+        for cert in &self.certs {
+            builder = builder.add_root_certificate(reqwest::Certificate::from_pem(cert.as_bytes())?);
+        }
+        Ok(builder.build()?)
+    }
+
+    /// WebSocket TLS connector that trusts the bundle
+    pub fn ws_connector(&self) -> Result<Connector, BoxedError> {
+        let mut builder = native_tls::TlsConnector::builder();
+        for cert in &self.certs {
+            builder.add_root_certificate(native_tls::Certificate::from_pem(cert.as_bytes())?);
+        }
+        Ok(Connector::NativeTls(builder.build()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_pem_bundle() {
+        let pem = "-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+                   -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+        let bundle = CaBundle::from_pem(pem);
+        assert_eq!(bundle.certs.len(), 2);
+        assert!(bundle.certs[1].contains("BBB"));
+        assert!(CaBundle::from_pem("not a certificate").certs.is_empty());
+    }
+}
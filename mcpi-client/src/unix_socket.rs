@@ -0,0 +1,102 @@
+// mcpi-client/src/unix_socket.rs
+//
+// Support for `unix:/path/to/mcpi.sock` base URLs. Every HTTP request and the
+// WebSocket connection open their own connection to the socket, so nothing is
+// exposed on a TCP port and the socket's permissions keep applying. Requests
+// use `http://<host>` URLs; the host only goes into the `Host` header, which
+// the server uses to pick a tenant.
+
+use crate::http_client::HttpResponse;
+use bytes::Bytes;
+use futures::TryStreamExt;
+use http_body_util::{BodyExt, Full};
+use hyper::client::conn::http1;
+use hyper_util::rt::TokioIo;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use reqwest::{Method, StatusCode};
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use tokio::net::UnixStream;
+use tokio_tungstenite::{client_async, WebSocketStream};
+use tracing::warn;
+use url::Url;
+
+type BoxedError = Box<dyn Error + Send + Sync>;
+
+pub const UNIX_URL_PREFIX: &str = "unix:";
+
+/// Socket path of a `unix:` base URL, or None for any other URL
+pub fn socket_path(base_url: &str) -> Option<PathBuf> {
+    base_url
+        .strip_prefix(UNIX_URL_PREFIX)
+        .map(|path| PathBuf::from(path.trim_start_matches("//")))
+}
+
+/// HTTP/1.1 client that sends each request over a new connection to the socket
+#[derive(Debug, Clone)]
+pub struct UnixClient {
+    socket: PathBuf,
+}
+
+impl UnixClient {
+    pub fn new(socket: PathBuf) -> Self {
+        UnixClient { socket }
+    }
+
+    pub async fn send(&self, method: Method, url: &str, headers: &HeaderMap, body: Option<String>) -> Result<HttpResponse, BoxedError> {
+        let url = Url::parse(url)?;
+        let stream = connect(&self.socket).await?;
+        let (mut sender, connection) = http1::handshake(TokioIo::new(stream)).await?;
+        tokio::spawn(async move {
+            if let Err(e) = connection.await {
+                warn!("Unix socket connection error: {}", e);
+            }
+        });
+
+        let mut request = hyper::Request::builder()
+            .method(method.as_str())
+            .uri(&url[url::Position::BeforePath..])
+            .header(hyper::header::HOST, &url[url::Position::BeforeHost..url::Position::AfterPort]);
+        for (name, value) in headers {
+            request = request.header(name.as_str(), value.as_bytes());
+        }
+        let request = request.body(Full::new(Bytes::from(body.unwrap_or_default())))?;
+        let (parts, body) = sender.send_request(request).await?.into_parts();
+
+        // reqwest and hyper use different versions of the `http` types
+        let mut response_headers = HeaderMap::new();
+        for (name, value) in &parts.headers {
+            response_headers.append(HeaderName::from_bytes(name.as_str().as_bytes())?, HeaderValue::from_bytes(value.as_bytes())?);
+        }
+        Ok(HttpResponse {
+            status: StatusCode::from_u16(parts.status.as_u16())?,
+            headers: response_headers,
+            body: Box::pin(body.into_data_stream().map_err(std::io::Error::other)),
+        })
+    }
+}
+
+/// Open a WebSocket connection over the socket; `ws_url` supplies the path and `Host`
+pub async fn connect_websocket(socket: &Path, ws_url: &str) -> Result<WebSocketStream<UnixStream>, BoxedError> {
+    let stream = connect(socket).await?;
+    let (ws_stream, _) = client_async(ws_url, stream).await?;
+    Ok(ws_stream)
+}
+
+async fn connect(socket: &Path) -> Result<UnixStream, BoxedError> {
+    UnixStream::connect(socket)
+        .await
+        .map_err(|e| format!("Cannot connect to {}: {}", socket.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_socket_path() {
+        assert_eq!(socket_path("unix:/run/mcpi.sock"), Some(PathBuf::from("/run/mcpi.sock")));
+        assert_eq!(socket_path("unix:///run/mcpi.sock"), Some(PathBuf::from("/run/mcpi.sock")));
+        assert_eq!(socket_path("http://localhost:3001"), None);
+    }
+}
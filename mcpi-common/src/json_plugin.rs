@@ -157,4 +157,20 @@ impl<T: JsonDataCapable + McpPlugin + Send + Sync> McpPlugin for JsonDataPlugin<
     fn get_capabilities(&self) -> Vec<String> {
         self.provider.get_capabilities()
     }
+
+    fn custom_methods(&self) -> Vec<String> {
+        self.provider.custom_methods()
+    }
+
+    fn handle_method(&self, method: &str, params: &Value) -> PluginResult {
+        self.provider.handle_method(method, params)
+    }
+
+    // Same checks as load_data, without its logging on every probe
+    fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let data_path = self.provider.get_data_path();
+        let data = fs::read_to_string(data_path).map_err(|e| format!("Cannot read data file {}: {}", data_path, e))?;
+        serde_json::from_str::<Value>(&data).map_err(|e| format!("Data file {} is not valid JSON: {}", data_path, e))?;
+        Ok(())
+    }
 }
\ No newline at end of file
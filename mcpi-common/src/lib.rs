@@ -17,6 +17,8 @@ pub use plugin::PluginType;
 // --- Protocol Constants ---
 // From TS example, assumed associated with the schema
 pub const LATEST_MCP_VERSION: &str = "2025-03-26";
+// Protocol versions the server can negotiate during `initialize` (newest first)
+pub const SUPPORTED_MCP_VERSIONS: &[&str] = &["2025-03-26", "2024-11-05"];
 // Version for your custom WebSocket protocol (keep separate if structure differs)
 pub const MCPI_VERSION: &str = "0.1.0"; // Example - Use your actual MCPI version
 
@@ -52,6 +54,19 @@ pub struct MCPError {
     pub data: Option<Value>,
 }
 
+// Standard JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i32 = -32700;
+pub const INVALID_REQUEST: i32 = -32600;
+pub const METHOD_NOT_FOUND: i32 = -32601;
+pub const INVALID_PARAMS: i32 = -32602;
+pub const INTERNAL_ERROR: i32 = -32603;
+
+impl MCPError {
+    pub fn new(code: i32, message: impl Into<String>) -> Self {
+        MCPError { code, message: message.into(), data: None }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
 #[serde(rename_all = "camelCase")]
 pub enum Role {
@@ -135,6 +150,12 @@ pub struct BlobResourceContents {
     pub blob: String, // Base64
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadResourceRequestParams {
+    pub uri: String,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct ReadResourceResult {
@@ -181,6 +202,13 @@ pub struct Tool {
     pub annotations: Option<ToolAnnotations>,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CallToolRequestParams {
+    pub name: String,
+    pub arguments: Value,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CallToolResult {
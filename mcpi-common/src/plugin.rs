@@ -61,11 +61,38 @@ pub trait McpPlugin: Send + Sync {
         None // Default: No specific annotations.
     }
 
+    /// Custom JSON-RPC methods this plugin handles, namespaced by the plugin (e.g. `mcpi/hello`).
+    /// They are not registered up front: the server looks each call up among the plugins
+    /// currently loaded, so methods follow the plugins across a reload. A name must contain
+    /// a `/`, and it never shadows a built-in method of the same name, which keeps handling
+    /// the call. If several plugins declare a method, the one whose name sorts first gets it.
+    fn custom_methods(&self) -> Vec<String> {
+        Vec::new() // Default: No custom methods.
+    }
+
+    /// Handle a call to one of the methods returned by `custom_methods`.
+    fn handle_method(&self, method: &str, params: &Value) -> Result<Value, Box<dyn Error + Send + Sync>> {
+        let _ = params;
+        Err(format!("Plugin '{}' does not handle method '{}'", self.name(), method).into())
+    }
+
     /// Provide completion suggestions for a given method and parameter.
     fn get_completions(&self, param_name: &str, partial_value: &Value, context: &Value) -> Vec<Value> {
         let _ = (param_name, partial_value, context); // Avoid unused warnings
         Vec::new() // Default: No suggestions.
     }
+
+    /// Check that the plugin can serve requests, e.g. that its data files are
+    /// readable. Called by the readiness probe; an error marks the plugin degraded.
+    fn health_check(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(()) // Default: Always healthy.
+    }
+
+    /// Release resources (connections, files, background work) when the server
+    /// shuts down. Called once, after every transport has stopped.
+    fn shutdown(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        Ok(()) // Default: Nothing to release.
+    }
 }
 
 /// Simplified result type for plugin operations (used by `execute`)
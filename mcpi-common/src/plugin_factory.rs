@@ -9,6 +9,7 @@ struct BasicJsonData {
     description: String,
     category: String,
     operations: Vec<String>,
+    #[allow(dead_code)]
     data_file: String,
     data_path: String,
 }
@@ -82,7 +83,7 @@ impl McpPlugin for BasicJsonData {
         })
     }
     
-    fn execute(&self, operation: &str, params: &serde_json::Value) -> crate::PluginResult {
+    fn execute(&self, _operation: &str, _params: &serde_json::Value) -> crate::PluginResult {
         // This will be handled by JsonDataPlugin
         Err("This method is handled by JsonDataPlugin".into())
     }
@@ -1,11 +1,16 @@
 // mcpi-server/src/admin.rs
 
-use axum::{extract::State, response::Html, Json}; // Ensure Html is imported
+use axum::{extract::{Path, Query, State}, http::StatusCode, response::Html, Json}; // Ensure Html is imported
 use serde_json::{json, Value};
 use std::sync::{atomic::Ordering, Arc};
 use std::time::Instant;
+use tracing::info;
 
-use crate::AppState; // Import shared AppState
+use crate::audit::AuditQuery;
+use crate::{reload, AppState}; // Import shared AppState
+
+/// Close reason sent to WebSocket clients terminated from the admin API
+const TERMINATED_REASON: &str = "Terminated by administrator";
 
 // Handler for GET /admin
 pub async fn serve_admin_html() -> Html<&'static str> {
@@ -22,22 +27,34 @@ pub async fn get_stats(State(state): State<Arc<AppState>>) -> Json<Value> {
     let uptime = Instant::now().duration_since(state.startup_time).as_secs();
 
     // Access stats from AppState
-    let active_ws_connections = state.active_ws_connections.load(Ordering::SeqCst);
+    let active_ws_connections = state.ws_connections.len();
     let request_count = state.request_count.load(Ordering::SeqCst);
-    // Use read().await because http_sessions is behind a RwLock
-    let http_sessions_count = state.http_sessions.read().await.len();
+    let http_sessions_count = state.http_sessions.len().await;
 
     Json(json!({
+        "tenant": state.tenant,
         "uptime_seconds": uptime,
         "active_websocket_connections": active_ws_connections,
         "active_http_sessions": http_sessions_count,
         "total_requests_processed": request_count,
+        "outbound": state.outbound.stats(),
+        "limits": state.message_handler.limits().stats(),
+        "reload": state.reloader.stats(),
     }))
 }
 
+// Handler for POST /api/admin/reload
+pub async fn reload_config(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    match reload::reload(&state).await {
+        Ok(summary) => (StatusCode::OK, Json(json!({ "reloaded": true, "summary": summary }))),
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, Json(json!({ "reloaded": false, "error": e }))),
+    }
+}
+
 // Handler for GET /api/admin/plugins
 pub async fn get_plugins(State(state): State<Arc<AppState>>) -> Json<Value> {
-    let plugins_info: Vec<Value> = state
+    let snapshot = state.snapshot.get();
+    let plugins_info: Vec<Value> = snapshot
         .registry
         .get_all_plugins()
         .iter()
@@ -52,5 +69,97 @@ pub async fn get_plugins(State(state): State<Arc<AppState>>) -> Json<Value> {
         })
         .collect();
 
-    Json(json!({ "plugins": plugins_info }))
-}
\ No newline at end of file
+    Json(json!({
+        "plugins": plugins_info,
+        "methods": state.message_handler.router().methods(&snapshot.registry),
+    }))
+}
+// Handler for GET /api/admin/sessions
+pub async fn get_sessions(State(state): State<Arc<AppState>>) -> Json<Value> {
+    let mut sessions = state.http_sessions.list().await;
+    sessions.extend(state.ws_connections.list());
+    let sessions: Vec<Value> = sessions
+        .iter()
+        .map(|session| {
+            json!({
+                "id": session.id,
+                "transport": session.transport,
+                "client_info": state.message_handler.client_info(&session.id),
+                "protocol_version": state.message_handler.negotiated_version(&session.id),
+                "connected_at": session.connected_at.to_rfc3339(),
+                "last_activity": session.last_activity.to_rfc3339(),
+                "requests": session.requests,
+                "streaming": session.streaming,
+            })
+        })
+        .collect();
+
+    Json(json!({ "tenant": state.tenant, "sessions": sessions }))
+}
+
+// Handler for DELETE /api/admin/sessions/:id
+pub async fn terminate_session(State(state): State<Arc<AppState>>, Path(session_id): Path<String>) -> (StatusCode, Json<Value>) {
+    // A WebSocket connection cleans up after itself once the close frame is sent
+    let terminated = state.ws_connections.close(&session_id, TERMINATED_REASON) || {
+        let removed = state.http_sessions.remove(&session_id).await;
+        if removed {
+            state.message_handler.forget_client(&session_id);
+        }
+        removed
+    };
+    if terminated {
+        info!("Session {} terminated by administrator", session_id);
+        (StatusCode::OK, Json(json!({ "terminated": true })))
+    } else {
+        (StatusCode::NOT_FOUND, Json(json!({ "terminated": false, "error": "Session not found" })))
+    }
+}
+
+// Handler for POST /api/admin/sessions/:id/notify, e.g.
+// `{ "method": "notifications/message", "params": { "level": "info", "data": "hello" } }`
+pub async fn notify_session(
+    State(state): State<Arc<AppState>>,
+    Path(session_id): Path<String>,
+    Json(body): Json<Value>,
+) -> (StatusCode, Json<Value>) {
+    let Some(method) = body.get("method").and_then(|m| m.as_str()) else {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "sent": false, "error": "method must be a string" })));
+    };
+    let mut notification = json!({ "jsonrpc": "2.0", "method": method });
+    if let Some(params) = body.get("params") {
+        notification["params"] = params.clone();
+    }
+    let message = notification.to_string();
+
+    let sent = if state.ws_connections.send(&session_id, message.clone()).await {
+        true
+    } else if let Some(events) = state.http_sessions.events(&session_id).await {
+        events.publish(message).await;
+        true
+    } else {
+        false
+    };
+    if sent {
+        info!("Sent {} to session {} from the admin API", method, session_id);
+        (StatusCode::OK, Json(json!({ "sent": true })))
+    } else {
+        (StatusCode::NOT_FOUND, Json(json!({ "sent": false, "error": "Session not found" })))
+    }
+}
+
+// Handler for GET /api/admin/audit, e.g. `?tool=store_customer&outcome=error&since=2026-01-01T00:00:00Z&limit=50`
+pub async fn query_audit(State(state): State<Arc<AppState>>, Query(mut query): Query<AuditQuery>) -> (StatusCode, Json<Value>) {
+    let Some(audit) = state.audit.clone() else {
+        return (StatusCode::NOT_FOUND, Json(json!({ "error": "Auditing is not enabled" })));
+    };
+    if let Err(e) = query.validate() {
+        return (StatusCode::BAD_REQUEST, Json(json!({ "error": e })));
+    }
+    // Tenants only see their own records
+    query.tenant = Some(state.tenant.clone());
+    match tokio::task::spawn_blocking(move || audit.query(&query)).await {
+        Ok(Ok(records)) => (StatusCode::OK, Json(json!({ "tenant": state.tenant, "records": records }))),
+        Ok(Err(e)) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": format!("Cannot read audit log: {}", e) }))),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, Json(json!({ "error": e.to_string() }))),
+    }
+}
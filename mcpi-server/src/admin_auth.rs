@@ -0,0 +1,322 @@
+// mcpi-server/src/admin_auth.rs
+//
+// Access control for the admin page, admin API and metrics. The `admin` block
+// picks one mechanism: a static token, HTTP Basic with argon2 password hashes,
+// or client certificates (mTLS). The admin routes can also be moved off the
+// public port onto a listener of their own, bound to localhost by default.
+//
+// argon2 is slow and memory-hard on purpose, so Basic passwords are verified on
+// the blocking pool, a few at a time, and credentials that verified are
+// remembered by digest for a short while. Unknown user names are checked against
+// a dummy hash, so they take as long as known ones.
+
+use crate::config::check_keys;
+use crate::tls::TlsConfig;
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::Engine;
+use rand::RngCore;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::Semaphore;
+use tracing::warn;
+
+/// Realm announced to browsers in the Basic challenge
+const REALM: &str = "MCPI Admin";
+
+/// How long verified Basic credentials are accepted without running argon2 again
+const VERIFIED_CREDENTIALS_TTL: Duration = Duration::from_secs(60);
+
+/// argon2 verifications running at once; further admin requests wait their turn
+const MAX_CONCURRENT_VERIFICATIONS: usize = 2;
+
+/// How requests to the admin routes are authenticated
+#[derive(Debug)]
+pub enum AdminAuth {
+    /// No authentication; the default, for servers bound to localhost
+    None,
+    /// `Authorization: Bearer <token>`, or Basic with the token as the password
+    /// so the admin page works in a browser
+    Token(String),
+    /// HTTP Basic, checked against argon2 PHC hashes by user name
+    Basic(BasicUsers),
+    /// Client certificates signed by this CA, checked during the TLS handshake
+    /// of the admin listener
+    ClientCert(PathBuf),
+}
+
+/// A listener that serves only the admin routes
+#[derive(Debug, Clone)]
+pub struct AdminListener {
+    pub addr: SocketAddr,
+    pub tls: Option<TlsConfig>,
+}
+
+/// Settings from the optional `admin` block of the server config
+#[derive(Debug, Clone)]
+pub struct AdminConfig {
+    pub auth: Arc<AdminAuth>,
+    /// Serve the admin routes here instead of on the public listeners
+    pub listener: Option<AdminListener>,
+}
+
+impl AdminConfig {
+    /// Read the `admin` block, e.g.
+    /// `{ "auth": { "mode": "basic", "users": { "ops": "$argon2id$v=19$..." } },
+    ///    "listen": { "bind_address": "127.0.0.1", "port": 3002 } }`.
+    /// Other modes are `{ "mode": "token", "token": "..." }` and
+    /// `{ "mode": "mtls", "client_ca": "clients.pem" }`, which needs `listen.tls`.
+    pub fn from_config(config: Option<&Value>, data_dir: &Path) -> Result<Self, String> {
+        check_keys(config, "admin", &["auth", "listen"])?;
+        let auth = AdminAuth::from_config(config.and_then(|c| c.get("auth")))?;
+        let mut listener = match config.and_then(|c| c.get("listen")) {
+            Some(listen) => Some(AdminListener::from_config(listen, data_dir)?),
+            None => None,
+        };
+        if let AdminAuth::ClientCert(client_ca) = &auth {
+            let tls = listener
+                .as_mut()
+                .and_then(|listener| listener.tls.as_mut())
+                .ok_or("admin.auth mode \"mtls\" needs admin.listen with its own tls block")?;
+            tls.client_ca = Some(client_ca.clone());
+        }
+        Ok(AdminConfig { auth: Arc::new(auth), listener })
+    }
+}
+
+impl AdminAuth {
+    fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        let Some(config) = config else { return Ok(AdminAuth::None) };
+        check_keys(Some(config), "admin.auth", &["mode", "token", "users", "client_ca"])?;
+        let string = |key: &str| match config.get(key) {
+            Some(Value::String(value)) if !value.is_empty() => Ok(value.clone()),
+            Some(other) => Err(format!("admin.auth.{} must be a non-empty string, got {}", key, other)),
+            None => Err(format!("admin.auth.{} is required", key)),
+        };
+        match config.get("mode").and_then(|m| m.as_str()) {
+            Some("none") => Ok(AdminAuth::None),
+            Some("token") => Ok(AdminAuth::Token(string("token")?)),
+            Some("basic") => {
+                let users = config
+                    .get("users")
+                    .and_then(|u| u.as_object())
+                    .filter(|u| !u.is_empty())
+                    .ok_or("admin.auth.users must map at least one user name to a password hash")?;
+                let mut hashes = HashMap::new();
+                for (user, hash) in users {
+                    let hash = hash.as_str().ok_or_else(|| format!("admin.auth.users.{} must be a password hash string", user))?;
+                    PasswordHash::new(hash).map_err(|e| format!("admin.auth.users.{} is not a valid password hash ({}); create one with --hash-password", user, e))?;
+                    hashes.insert(user.clone(), hash.to_string());
+                }
+                Ok(AdminAuth::Basic(BasicUsers::new(hashes)?))
+            }
+            Some("mtls") => Ok(AdminAuth::ClientCert(PathBuf::from(string("client_ca")?))),
+            _ => Err(format!("admin.auth.mode must be \"none\", \"token\", \"basic\" or \"mtls\", got {}", config.get("mode").unwrap_or(&Value::Null))),
+        }
+    }
+
+    pub fn is_none(&self) -> bool {
+        matches!(self, AdminAuth::None)
+    }
+
+    async fn authorize(&self, headers: &HeaderMap) -> bool {
+        match self {
+            // Clients without a valid certificate never get past the handshake
+            AdminAuth::None | AdminAuth::ClientCert(_) => true,
+            AdminAuth::Token(token) => match credentials(headers) {
+                Some(Credentials::Bearer(given)) | Some(Credentials::Basic(_, given)) => constant_time_eq(given.as_bytes(), token.as_bytes()),
+                None => false,
+            },
+            AdminAuth::Basic(users) => match credentials(headers) {
+                Some(Credentials::Basic(user, password)) => users.verify(&user, password).await,
+                _ => false,
+            },
+        }
+    }
+}
+
+impl AdminListener {
+    fn from_config(config: &Value, data_dir: &Path) -> Result<Self, String> {
+        check_keys(Some(config), "admin.listen", &["bind_address", "port", "tls"])?;
+        let bind_address = match config.get("bind_address") {
+            Some(Value::String(address)) => address.parse().map_err(|e| format!("Invalid admin.listen.bind_address '{}': {}", address, e))?,
+            Some(other) => return Err(format!("admin.listen.bind_address must be a string, got {}", other)),
+            None => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        };
+        let port = config
+            .get("port")
+            .and_then(|p| p.as_u64())
+            .and_then(|p| u16::try_from(p).ok())
+            .ok_or_else(|| format!("admin.listen.port must be a port number, got {}", config.get("port").unwrap_or(&Value::Null)))?;
+        let tls = TlsConfig::from_config(config.get("tls"), data_dir).map_err(|e| format!("admin.listen.{}", e))?;
+        Ok(AdminListener { addr: SocketAddr::new(bind_address, port), tls })
+    }
+}
+
+/// Password hashes of the Basic users, and the state that keeps verifying them cheap
+#[derive(Debug)]
+pub struct BasicUsers {
+    hashes: HashMap<String, String>,
+    // Hash that passwords of unknown users are verified against
+    dummy_hash: String,
+    verifying: Semaphore,
+    // Digests of credentials that passed verification, with when they did
+    verified: Mutex<HashMap<[u8; 32], Instant>>,
+}
+
+impl BasicUsers {
+    fn new(hashes: HashMap<String, String>) -> Result<Self, String> {
+        let mut unguessable = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut unguessable);
+        Ok(BasicUsers {
+            hashes,
+            dummy_hash: hash_password(&base64::engine::general_purpose::STANDARD.encode(unguessable))?,
+            verifying: Semaphore::new(MAX_CONCURRENT_VERIFICATIONS),
+            verified: Mutex::new(HashMap::new()),
+        })
+    }
+
+    async fn verify(&self, user: &str, password: String) -> bool {
+        let digest = credentials_digest(user, &password);
+        if self.is_verified(&digest) {
+            return true;
+        }
+        let (hash, known) = match self.hashes.get(user) {
+            Some(hash) => (hash.clone(), true),
+            None => (self.dummy_hash.clone(), false),
+        };
+        // The semaphore is never closed
+        let Ok(_permit) = self.verifying.acquire().await else { return false };
+        let valid = tokio::task::spawn_blocking(move || verify_password(&password, &hash)).await.unwrap_or(false) && known;
+        if valid {
+            self.remember(digest);
+        }
+        valid
+    }
+
+    fn is_verified(&self, digest: &[u8; 32]) -> bool {
+        self.verified.lock().unwrap().get(digest).is_some_and(|at| at.elapsed() < VERIFIED_CREDENTIALS_TTL)
+    }
+
+    fn remember(&self, digest: [u8; 32]) {
+        let mut verified = self.verified.lock().unwrap();
+        verified.retain(|_, at| at.elapsed() < VERIFIED_CREDENTIALS_TTL);
+        verified.insert(digest, Instant::now());
+    }
+}
+
+fn credentials_digest(user: &str, password: &str) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(user.as_bytes());
+    hasher.update([0]);
+    hasher.update(password.as_bytes());
+    hasher.finalize().into()
+}
+
+enum Credentials {
+    Bearer(String),
+    Basic(String, String),
+}
+
+fn credentials(headers: &HeaderMap) -> Option<Credentials> {
+    let value = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let (scheme, rest) = value.split_once(' ')?;
+    if scheme.eq_ignore_ascii_case("bearer") {
+        Some(Credentials::Bearer(rest.trim().to_string()))
+    } else if scheme.eq_ignore_ascii_case("basic") {
+        let decoded = base64::engine::general_purpose::STANDARD.decode(rest.trim()).ok()?;
+        let (user, password) = String::from_utf8(decoded).ok()?.split_once(':').map(|(u, p)| (u.to_string(), p.to_string()))?;
+        Some(Credentials::Basic(user, password))
+    } else {
+        None
+    }
+}
+
+fn verify_password(password: &str, hash: &str) -> bool {
+    PasswordHash::new(hash).is_ok_and(|hash| Argon2::default().verify_password(password.as_bytes(), &hash).is_ok())
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Hash a password for `admin.auth.users`
+pub fn hash_password(password: &str) -> Result<String, String> {
+    let mut salt = [0u8; 16];
+    rand::thread_rng().fill_bytes(&mut salt);
+    let salt = SaltString::encode_b64(&salt).map_err(|e| e.to_string())?;
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| e.to_string())
+}
+
+/// Reject admin requests that do not carry valid credentials
+pub async fn require_admin(State(auth): State<Arc<AdminAuth>>, request: Request<Body>, next: Next) -> Response {
+    if auth.authorize(request.headers()).await {
+        return next.run(request).await;
+    }
+    warn!("Rejected unauthenticated admin request for {}", request.uri().path());
+    let mut response = (StatusCode::UNAUTHORIZED, Json(json!({ "error": "Authentication required" }))).into_response();
+    let challenges = match auth.as_ref() {
+        AdminAuth::Token(_) => vec![format!("Bearer realm=\"{}\"", REALM), format!("Basic realm=\"{}\"", REALM)],
+        _ => vec![format!("Basic realm=\"{}\"", REALM)],
+    };
+    for challenge in challenges {
+        if let Ok(value) = HeaderValue::from_str(&challenge) {
+            response.headers_mut().append(header::WWW_AUTHENTICATE, value);
+        }
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(authorization: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, HeaderValue::from_str(authorization).unwrap());
+        headers
+    }
+
+    fn basic(user: &str, password: &str) -> HeaderMap {
+        headers(&format!("Basic {}", base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password))))
+    }
+
+    #[tokio::test]
+    async fn test_admin_credentials() {
+        let token = AdminAuth::from_config(Some(&json!({ "mode": "token", "token": "s3cret" }))).unwrap();
+        assert!(token.authorize(&headers("Bearer s3cret")).await);
+        assert!(token.authorize(&basic("anyone", "s3cret")).await);
+        assert!(!token.authorize(&headers("Bearer wrong")).await);
+        assert!(!token.authorize(&HeaderMap::new()).await);
+
+        let hash = hash_password("hunter2").unwrap();
+        let users = AdminAuth::from_config(Some(&json!({ "mode": "basic", "users": { "ops": hash } }))).unwrap();
+        assert!(users.authorize(&basic("ops", "hunter2")).await);
+        assert!(!users.authorize(&basic("ops", "hunter3")).await);
+        assert!(!users.authorize(&basic("root", "hunter2")).await);
+        // A verified password is remembered, a wrong one is not
+        let AdminAuth::Basic(basic_users) = &users else { unreachable!() };
+        assert!(basic_users.is_verified(&credentials_digest("ops", "hunter2")));
+        assert!(!basic_users.is_verified(&credentials_digest("ops", "hunter3")));
+
+        assert!(AdminAuth::from_config(Some(&json!({ "mode": "basic", "users": { "ops": "plaintext" } }))).is_err());
+        // mTLS is enforced by the admin listener, which must then have TLS
+        assert!(AdminConfig::from_config(Some(&json!({ "auth": { "mode": "mtls", "client_ca": "ca.pem" } })), Path::new("data")).is_err());
+    }
+}
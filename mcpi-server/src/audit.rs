@@ -0,0 +1,441 @@
+// mcpi-server/src/audit.rs
+//
+// Append-only audit log of tool calls and resource reads, one JSON record per
+// line. Each record says who called what and how it ended: the session and
+// client, the tool and operation or resource, a digest of the redacted arguments
+// with the redacted copy itself, the outcome and the duration. The file is
+// rotated by size and age, and `GET /api/admin/audit` searches the current and
+// rotated files.
+
+use crate::config::check_keys;
+use crate::interceptor::{redact, Interceptor, InterceptorFuture, SessionContext, DEFAULT_REDACTED_KEYS};
+use chrono::{DateTime, Utc};
+use mcpi_common::{MCPError, MCPRequest, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, METHOD_NOT_FOUND, PARSE_ERROR};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{error, info};
+
+/// Methods that get an audit record
+const AUDITED_METHODS: &[&str] = &["tools/call", "resources/read"];
+
+const DEFAULT_PATH: &str = "audit/audit.jsonl";
+const DEFAULT_MAX_BYTES: u64 = 10 * 1024 * 1024;
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+const DEFAULT_KEEP: usize = 10;
+
+/// Records returned by a query unless it asks for fewer
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// Settings from the optional `audit` block of the server config
+#[derive(Debug, Clone)]
+pub struct AuditConfig {
+    pub path: PathBuf,
+    /// Rotate once the file would grow past this size
+    pub max_bytes: u64,
+    /// Rotate once the file is this old
+    pub max_age: Duration,
+    /// Rotated files kept; older ones are deleted
+    pub keep: usize,
+    /// Argument keys whose values are replaced in the recorded copy
+    pub redacted_keys: Vec<String>,
+    /// Record a redacted copy of the arguments, not just their digest
+    pub record_arguments: bool,
+}
+
+impl AuditConfig {
+    /// Read the `audit` block, e.g. `{ "path": "audit/audit.jsonl", "max_bytes": 10485760,
+    /// "max_age_secs": 86400, "keep": 10, "redact": ["password", "card_number"], "record_arguments": true }`.
+    /// Returns None when auditing is not configured or `enabled` is false.
+    pub fn from_config(config: Option<&Value>) -> Result<Option<Self>, String> {
+        let Some(config) = config else { return Ok(None) };
+        check_keys(Some(config), "audit", &["enabled", "path", "max_bytes", "max_age_secs", "keep", "redact", "record_arguments"])?;
+        if config.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+            return Ok(None);
+        }
+        let number = |key: &str, default: u64| match config.get(key) {
+            Some(value) => value.as_u64().filter(|n| *n > 0).ok_or_else(|| format!("audit.{} must be a positive number, got {}", key, value)),
+            None => Ok(default),
+        };
+        let path = match config.get("path") {
+            Some(Value::String(path)) => PathBuf::from(path),
+            Some(other) => return Err(format!("audit.path must be a string, got {}", other)),
+            None => PathBuf::from(DEFAULT_PATH),
+        };
+        let redacted_keys = match config.get("redact") {
+            Some(Value::Array(keys)) => keys
+                .iter()
+                .map(|k| k.as_str().map(str::to_string).ok_or_else(|| format!("audit.redact entries must be strings, got {}", k)))
+                .collect::<Result<_, _>>()?,
+            Some(other) => return Err(format!("audit.redact must be an array of keys, got {}", other)),
+            None => DEFAULT_REDACTED_KEYS.iter().map(|k| k.to_string()).collect(),
+        };
+        let record_arguments = match config.get("record_arguments") {
+            Some(value) => value.as_bool().ok_or_else(|| format!("audit.record_arguments must be a boolean, got {}", value))?,
+            None => true,
+        };
+        Ok(Some(AuditConfig {
+            path,
+            max_bytes: number("max_bytes", DEFAULT_MAX_BYTES)?,
+            max_age: Duration::from_secs(number("max_age_secs", DEFAULT_MAX_AGE.as_secs())?),
+            keep: number("keep", DEFAULT_KEEP as u64)? as usize,
+            redacted_keys,
+            record_arguments,
+        }))
+    }
+}
+
+struct OpenFile {
+    file: File,
+    size: u64,
+    opened_at: DateTime<Utc>,
+}
+
+/// The audit file shared by every tenant. Records are written on the blocking
+/// pool and each request waits for its record, so none is lost if the process exits.
+pub struct AuditLog {
+    config: AuditConfig,
+    current: Mutex<Option<OpenFile>>,
+}
+
+impl AuditLog {
+    pub fn new(config: AuditConfig) -> Self {
+        AuditLog { config, current: Mutex::new(None) }
+    }
+
+    /// Open the file now, so a bad path is reported at startup
+    pub fn open(&self) -> io::Result<()> {
+        let mut current = self.current.lock().unwrap();
+        *current = Some(self.open_file()?);
+        Ok(())
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.config.path
+    }
+
+    fn open_file(&self) -> io::Result<OpenFile> {
+        if let Some(parent) = self.config.path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&self.config.path)?;
+        let metadata = file.metadata()?;
+        // An existing file keeps aging from when it was created
+        let opened_at = metadata.created().map(DateTime::<Utc>::from).unwrap_or_else(|_| Utc::now());
+        Ok(OpenFile { file, size: metadata.len(), opened_at })
+    }
+
+    /// Append one record, rotating the file first if it is full or too old
+    pub fn append(&self, record: &Value) -> io::Result<()> {
+        let mut line = record.to_string();
+        line.push('\n');
+        let mut current = self.current.lock().unwrap();
+        if let Some(open) = current.as_ref() {
+            let age = (Utc::now() - open.opened_at).to_std().unwrap_or_default();
+            let full = open.size > 0 && open.size + line.len() as u64 > self.config.max_bytes;
+            if full || age >= self.config.max_age {
+                *current = None;
+                self.rotate()?;
+            }
+        }
+        if current.is_none() {
+            *current = Some(self.open_file()?);
+        }
+        let open = current.as_mut().unwrap();
+        open.file.write_all(line.as_bytes())?;
+        open.size += line.len() as u64;
+        Ok(())
+    }
+
+    // Move the current file aside under a timestamped name and drop the oldest
+    // rotated files beyond `keep`
+    fn rotate(&self) -> io::Result<()> {
+        if !self.config.path.exists() {
+            return Ok(());
+        }
+        let stamp = Utc::now().format("%Y%m%dT%H%M%S%.3fZ").to_string();
+        // Never overwrite a file rotated within the same millisecond; `_` sorts after `.`
+        let mut rotated = self.rotated_name(&stamp);
+        let mut sequence = 1;
+        while rotated.exists() {
+            rotated = self.rotated_name(&format!("{}_{}", stamp, sequence));
+            sequence += 1;
+        }
+        fs::rename(&self.config.path, &rotated)?;
+        info!("Rotated audit log to {}", rotated.display());
+
+        let rotated_files = self.rotated_files();
+        for old in rotated_files.iter().skip(self.config.keep) {
+            if let Err(e) = fs::remove_file(old) {
+                error!("Cannot remove old audit log {}: {}", old.display(), e);
+            }
+        }
+        Ok(())
+    }
+
+    // audit.jsonl becomes audit-<stamp>.jsonl next to it
+    fn rotated_name(&self, stamp: &str) -> PathBuf {
+        let (stem, extension) = self.name_parts();
+        let name = match extension {
+            Some(extension) => format!("{}-{}.{}", stem, stamp, extension),
+            None => format!("{}-{}", stem, stamp),
+        };
+        self.config.path.with_file_name(name)
+    }
+
+    fn name_parts(&self) -> (String, Option<String>) {
+        let stem = self.config.path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_default();
+        let extension = self.config.path.extension().map(|e| e.to_string_lossy().into_owned());
+        (stem, extension)
+    }
+
+    // Rotated files, newest first; the timestamps sort by name
+    fn rotated_files(&self) -> Vec<PathBuf> {
+        let (stem, extension) = self.name_parts();
+        let prefix = format!("{}-", stem);
+        let suffix = extension.map(|e| format!(".{}", e)).unwrap_or_default();
+        let dir = match self.config.path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let mut files: Vec<PathBuf> = fs::read_dir(&dir)
+            .map(|entries| {
+                entries
+                    .flatten()
+                    .map(|entry| entry.path())
+                    .filter(|path| {
+                        let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+                        name.starts_with(&prefix) && name.ends_with(&suffix)
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        files.sort();
+        files.reverse();
+        files
+    }
+
+    /// Records matching `query`, newest first
+    pub fn query(&self, query: &AuditQuery) -> io::Result<Vec<Value>> {
+        let limit = query.limit.unwrap_or(DEFAULT_QUERY_LIMIT).min(MAX_QUERY_LIMIT);
+        let mut files = vec![self.config.path.clone()];
+        files.extend(self.rotated_files());
+
+        let mut matches = Vec::new();
+        for path in files {
+            let file = match File::open(&path) {
+                Ok(file) => file,
+                Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+                Err(e) => return Err(e),
+            };
+            let lines: Vec<String> = BufReader::new(file).lines().collect::<io::Result<_>>()?;
+            for line in lines.iter().rev() {
+                let Ok(record) = serde_json::from_str::<Value>(line) else { continue };
+                if query.matches(&record) {
+                    matches.push(record);
+                    if matches.len() == limit {
+                        return Ok(matches);
+                    }
+                }
+            }
+        }
+        Ok(matches)
+    }
+}
+
+/// Filters of `GET /api/admin/audit`; every given field must match
+#[derive(Debug, Default, Deserialize)]
+pub struct AuditQuery {
+    #[serde(skip)]
+    pub tenant: Option<String>,
+    pub session: Option<String>,
+    /// Client name from `clientInfo`
+    pub client: Option<String>,
+    pub method: Option<String>,
+    pub tool: Option<String>,
+    pub operation: Option<String>,
+    pub outcome: Option<String>,
+    pub error_class: Option<String>,
+    /// RFC 3339 timestamps bounding the records' times
+    pub since: Option<String>,
+    pub until: Option<String>,
+    pub limit: Option<usize>,
+}
+
+impl AuditQuery {
+    fn matches(&self, record: &Value) -> bool {
+        let field = |expected: &Option<String>, actual: Option<&Value>| {
+            expected.as_ref().is_none_or(|expected| actual.and_then(|v| v.as_str()) == Some(expected.as_str()))
+        };
+        let timestamp = || record.get("timestamp").and_then(|t| t.as_str()).and_then(|t| DateTime::parse_from_rfc3339(t).ok());
+        let after_since = self.since.as_ref().is_none_or(|since| {
+            let since = DateTime::parse_from_rfc3339(since).ok();
+            timestamp().zip(since).is_some_and(|(t, since)| t >= since)
+        });
+        let before_until = self.until.as_ref().is_none_or(|until| {
+            let until = DateTime::parse_from_rfc3339(until).ok();
+            timestamp().zip(until).is_some_and(|(t, until)| t <= until)
+        });
+        field(&self.tenant, record.get("tenant"))
+            && field(&self.session, record.get("session"))
+            && field(&self.client, record.pointer("/client/name"))
+            && field(&self.method, record.get("method"))
+            && field(&self.tool, record.get("tool"))
+            && field(&self.operation, record.get("operation"))
+            && field(&self.outcome, record.get("outcome"))
+            && field(&self.error_class, record.get("error_class"))
+            && after_since
+            && before_until
+    }
+
+    /// Check the timestamps before searching
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, value) in [("since", &self.since), ("until", &self.until)] {
+            if let Some(value) = value {
+                DateTime::parse_from_rfc3339(value).map_err(|e| format!("{} must be an RFC 3339 timestamp: {}", name, e))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes an audit record for every audited request of one tenant
+pub struct AuditInterceptor {
+    log: Arc<AuditLog>,
+    tenant: String,
+}
+
+impl AuditInterceptor {
+    pub fn new(log: Arc<AuditLog>, tenant: &str) -> Self {
+        AuditInterceptor { log, tenant: tenant.to_string() }
+    }
+
+    fn record(&self, ctx: &SessionContext, request: &MCPRequest, outcome: &Result<Value, MCPError>) -> Value {
+        let params = request.params.as_ref();
+        let mut record = json!({
+            "timestamp": Utc::now().to_rfc3339(),
+            "tenant": self.tenant,
+            "session": ctx.client_id,
+            "client": ctx.client_info.as_ref().map(|info| json!({ "name": info.get("name"), "version": info.get("version") })),
+            "protocol_version": ctx.protocol_version,
+            "method": request.method,
+        });
+        let arguments = if request.method == "tools/call" {
+            let arguments = params.and_then(|p| p.get("arguments")).cloned().unwrap_or(Value::Null);
+            record["tool"] = params.and_then(|p| p.get("name")).cloned().unwrap_or(Value::Null);
+            record["operation"] = json!(arguments.get("operation").and_then(|o| o.as_str()).unwrap_or("DEFAULT"));
+            arguments
+        } else {
+            record["resource"] = params.and_then(|p| p.get("uri")).cloned().unwrap_or(Value::Null);
+            params.cloned().unwrap_or(Value::Null)
+        };
+        // Hashing the redacted copy keeps low-entropy secrets from being guessed
+        // from the digest. serde_json objects keep their keys sorted, so equal
+        // arguments hash equally.
+        let arguments = redact(&arguments, &self.log.config.redacted_keys);
+        record["arguments_sha256"] = json!(format!("{:x}", Sha256::digest(arguments.to_string().as_bytes())));
+        if self.log.config.record_arguments {
+            record["arguments"] = arguments;
+        }
+
+        let (result, error_class, error) = match outcome {
+            Ok(result) if result.get("isError").and_then(|e| e.as_bool()) == Some(true) => {
+                let message = result.pointer("/content/0/text").and_then(|t| t.as_str()).map(str::to_string);
+                ("error", Some("tool_error"), message)
+            }
+            Ok(_) => ("ok", None, None),
+            Err(e) => ("error", Some(error_class(e.code)), Some(e.message.clone())),
+        };
+        record["outcome"] = json!(result);
+        record["error_class"] = json!(error_class);
+        record["error"] = json!(error);
+        record["duration_ms"] = json!((ctx.received_at.elapsed().as_secs_f64() * 1e6).round() / 1e3);
+        record
+    }
+}
+
+// A stable name for a JSON-RPC error code
+fn error_class(code: i32) -> &'static str {
+    match code {
+        PARSE_ERROR => "parse_error",
+        INVALID_REQUEST => "invalid_request",
+        METHOD_NOT_FOUND => "method_not_found",
+        INVALID_PARAMS => "invalid_params",
+        INTERNAL_ERROR => "internal_error",
+        _ => "server_error",
+    }
+}
+
+impl Interceptor for AuditInterceptor {
+    fn name(&self) -> &str {
+        "audit"
+    }
+
+    fn after<'a>(&'a self, ctx: &'a SessionContext, request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> InterceptorFuture<'a, ()> {
+        Box::pin(async move {
+            if !AUDITED_METHODS.contains(&request.method.as_str()) {
+                return;
+            }
+            let record = self.record(ctx, request, outcome);
+            let log = self.log.clone();
+            let written = tokio::task::spawn_blocking(move || log.append(&record)).await.unwrap_or_else(|e| Err(io::Error::other(e)));
+            if let Err(e) = written {
+                error!("Cannot write audit record to {}: {}", self.log.path().display(), e);
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[tokio::test]
+    async fn test_audit_records_rotate_and_query() {
+        let dir = std::env::temp_dir().join(format!("mcpi-audit-{}", uuid::Uuid::new_v4()));
+        let config = AuditConfig::from_config(Some(&json!({ "path": dir.join("audit.jsonl"), "max_bytes": 600, "keep": 2 })))
+            .unwrap()
+            .unwrap();
+        let log = Arc::new(AuditLog::new(config));
+        let audit = AuditInterceptor::new(log.clone(), "shop");
+        let ctx = SessionContext {
+            client_id: "session-1".to_string(),
+            protocol_version: Some("2025-03-26".to_string()),
+            client_info: Some(json!({ "name": "test-client", "version": "1.0" })),
+            is_notification: false,
+            in_batch: false,
+            received_at: Instant::now(),
+        };
+        for i in 0..6 {
+            let request: MCPRequest = serde_json::from_value(json!({
+                "jsonrpc": "2.0", "id": i, "method": "tools/call",
+                "params": { "name": "store_customer", "arguments": { "operation": "get", "password": "hunter2" } }
+            }))
+            .unwrap();
+            let mut outcome = if i % 2 == 0 { Ok(json!({ "content": [] })) } else { Err(MCPError::new(INVALID_PARAMS, "Invalid params")) };
+            audit.after(&ctx, &request, &mut outcome).await;
+        }
+
+        // Every record is a few hundred bytes, so the file rotated and only two rotated files remain
+        assert_eq!(log.rotated_files().len(), 2);
+        let errors = log.query(&AuditQuery { outcome: Some("error".to_string()), ..AuditQuery::default() }).unwrap();
+        assert!(!errors.is_empty());
+        let newest = &errors[0];
+        assert_eq!(newest["error_class"], "invalid_params");
+        assert_eq!(newest["client"]["name"], "test-client");
+        assert_eq!(newest["arguments"]["password"], "[REDACTED]");
+        // The digest covers the redacted arguments, never the secret itself
+        let redacted = json!({ "operation": "get", "password": "[REDACTED]" });
+        assert_eq!(newest["arguments_sha256"], format!("{:x}", Sha256::digest(redacted.to_string().as_bytes())));
+        assert!(log.query(&AuditQuery { tool: Some("weather_forecast".to_string()), ..AuditQuery::default() }).unwrap().is_empty());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
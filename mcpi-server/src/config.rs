@@ -0,0 +1,430 @@
+// mcpi-server/src/config.rs
+//
+// Layered server configuration. From lowest to highest priority, settings come
+// from `<data_dir>/server/data.json`, the config file (TOML or JSON), `MCPI_*`
+// environment variables and command-line flags. The layers are merged into one
+// JSON tree, and each subsystem parses its own block of it with `from_config`.
+
+use crate::admin_auth::AdminConfig;
+use crate::audit::AuditConfig;
+use crate::limits::LimitsConfig;
+use crate::outbound::OutboundConfig;
+use crate::plugin_registry::PluginsConfig;
+use crate::reload::ReloadConfig;
+use crate::security::HttpConfig;
+use crate::sessions::SessionConfig;
+use crate::telemetry::{Telemetry, TelemetryConfig};
+use crate::tenants::TenantConfig;
+use crate::shutdown::ShutdownConfig;
+use crate::tls::TlsConfig;
+use crate::transport::TransportsConfig;
+use clap::Parser;
+use serde_json::{Map, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::EnvFilter;
+
+const DEFAULT_DATA_DIR: &str = "data";
+const DEFAULT_PORT: u16 = 3001;
+const ENV_PREFIX: &str = "MCPI_";
+
+/// Top-level blocks the server understands; anything else is reported as a typo
+const SECTIONS: &[&str] = &[
+    "provider", "referrals", "capabilities", "data_dir", "http", "tls", "unix_socket", "transports", "shutdown", "outbound", "limits",
+    "sessions", "plugins", "logging", "reload", "tenants", "admin", "audit", "telemetry",
+];
+
+/// Blocks a tenant's own data file may contain; everything else is shared with the root
+const TENANT_SECTIONS: &[&str] = &["provider", "referrals", "capabilities", "plugins"];
+
+/// Command-line flags. Each one overrides the matching config file setting.
+#[derive(Parser, Debug, Clone, Default)]
+#[command(version, about = "MCPI server")]
+pub struct Cli {
+    /// Config file (.toml or .json); defaults to <data-dir>/server/config.toml or config.json
+    #[arg(short, long, env = "MCPI_CONFIG")]
+    pub config: Option<PathBuf>,
+    /// Directory with the server and plugin data
+    #[arg(long, env = "MCPI_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    /// Address to bind the TCP listener to
+    #[arg(long)]
+    pub bind: Option<String>,
+    /// Port of the TCP listener
+    #[arg(short, long)]
+    pub port: Option<u16>,
+    /// Serve only stdio, whatever the config enables
+    #[arg(long)]
+    pub stdio: bool,
+    /// Transports to enable, e.g. streamable_http,websocket; the others are disabled
+    #[arg(long, value_delimiter = ',')]
+    pub transports: Option<Vec<String>>,
+    /// Plugins to load; the others are skipped
+    #[arg(long, value_delimiter = ',')]
+    pub plugins: Option<Vec<String>>,
+    /// Log output format: text or json
+    #[arg(long)]
+    pub log_format: Option<String>,
+    /// Log level or filter directives, e.g. debug or info,mcpi_server=trace
+    #[arg(long)]
+    pub log_level: Option<String>,
+    /// Override any setting by its dotted path, e.g. --set limits.max_json_depth=32
+    #[arg(long = "set", value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+    /// Read a password from stdin, print its hash for admin.auth.users and exit
+    #[arg(long)]
+    pub hash_password: bool,
+}
+
+/// The merged configuration tree
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub data_dir: PathBuf,
+    /// The config file that was read, if any
+    pub file: Option<PathBuf>,
+    tree: Value,
+}
+
+impl ServerConfig {
+    /// Load every layer, reading overrides from the process environment
+    pub fn load(cli: &Cli) -> Result<Self, String> {
+        Self::load_with_env(cli, std::env::vars())
+    }
+
+    pub fn load_with_env(cli: &Cli, env: impl IntoIterator<Item = (String, String)>) -> Result<Self, String> {
+        // The data directory given on the command line decides where the default config file is
+        let bootstrap_dir = cli.data_dir.clone().unwrap_or_else(|| PathBuf::from(DEFAULT_DATA_DIR));
+        let file = match &cli.config {
+            Some(path) if path.exists() => Some(path.clone()),
+            Some(path) => return Err(format!("Config file not found: {}", path.display())),
+            None => ["config.toml", "config.json"].iter().map(|name| bootstrap_dir.join("server").join(name)).find(|p| p.exists()),
+        };
+
+        let mut overrides = match &file {
+            Some(path) => read_file(path)?,
+            None => Value::Object(Map::new()),
+        };
+        merge(&mut overrides, env_layer(env)?);
+        merge(&mut overrides, cli_layer(cli)?);
+
+        let data_dir = match overrides.get("data_dir") {
+            Some(Value::String(dir)) => PathBuf::from(dir),
+            Some(other) => return Err(format!("data_dir must be a string, got {}", other)),
+            None => bootstrap_dir,
+        };
+        if !data_dir.is_dir() {
+            return Err(format!("Data directory not found: {}", data_dir.display()));
+        }
+        let data_file = data_dir.join("server").join("data.json");
+        if !data_file.exists() {
+            return Err(format!("Server data file missing: {}", data_file.display()));
+        }
+        let mut tree = read_file(&data_file)?;
+        merge(&mut tree, overrides);
+
+        if let Some(unknown) = tree.as_object().and_then(|t| t.keys().find(|k| !SECTIONS.contains(&k.as_str()))) {
+            return Err(format!("Unknown config section '{}'; expected one of: {}", unknown, SECTIONS.join(", ")));
+        }
+        Ok(ServerConfig { data_dir, file, tree })
+    }
+
+    /// Load a tenant's data file, with the `plugins` block of its entry taking precedence
+    pub fn load_tenant(tenant: &TenantConfig) -> Result<Self, String> {
+        let data_file = tenant.data_dir.join("server").join("data.json");
+        if !data_file.exists() {
+            return Err(format!("Data file of tenant '{}' missing: {}", tenant.name, data_file.display()));
+        }
+        let mut tree = read_file(&data_file)?;
+        if let Some(plugins) = &tenant.plugins {
+            merge(&mut tree, serde_json::json!({ "plugins": plugins }));
+        }
+        if let Some(unknown) = tree.as_object().and_then(|t| t.keys().find(|k| !TENANT_SECTIONS.contains(&k.as_str()))) {
+            return Err(format!(
+                "Unknown section '{}' in {}; a tenant may only set: {}",
+                unknown,
+                data_file.display(),
+                TENANT_SECTIONS.join(", ")
+            ));
+        }
+        Ok(ServerConfig { data_dir: tenant.data_dir.clone(), file: None, tree })
+    }
+
+    /// A top-level block of the merged tree
+    pub fn get(&self, section: &str) -> Option<&Value> {
+        self.tree.get(section)
+    }
+
+    /// Parse and check every block, so mistakes are reported before anything starts
+    pub fn settings(&self, stdio_only: bool) -> Result<Settings, String> {
+        Ok(Settings {
+            transports: if stdio_only { TransportsConfig::stdio_only() } else { TransportsConfig::from_config(self.get("transports"))? },
+            http: HttpConfig::from_config(self.get("http"), DEFAULT_PORT)?,
+            tls: TlsConfig::from_config(self.get("tls"), &self.data_dir)?,
+            #[cfg(unix)]
+            unix_socket: crate::transport::unix_socket::UnixSocketConfig::from_config(self.get("unix_socket"))?,
+            shutdown: ShutdownConfig::from_config(self.get("shutdown"))?,
+            outbound: OutboundConfig::from_config(self.get("outbound"))?,
+            sessions: SessionConfig::from_config(self.get("sessions"))?,
+            limits: LimitsConfig::from_config(self.get("limits"))?,
+            plugins: PluginsConfig::from_config(self.get("plugins"))?,
+            logging: LoggingConfig::from_config(self.get("logging"))?,
+            reload: ReloadConfig::from_config(self.get("reload"))?,
+            tenants: TenantConfig::list_from_config(self.get("tenants"))?,
+            admin: AdminConfig::from_config(self.get("admin"), &self.data_dir)?,
+            audit: AuditConfig::from_config(self.get("audit"))?,
+            telemetry: TelemetryConfig::from_config(self.get("telemetry"))?,
+        })
+    }
+}
+
+/// Every block of the config, parsed
+pub struct Settings {
+    pub transports: TransportsConfig,
+    pub http: HttpConfig,
+    pub tls: Option<TlsConfig>,
+    #[cfg(unix)]
+    pub unix_socket: Option<crate::transport::unix_socket::UnixSocketConfig>,
+    pub shutdown: ShutdownConfig,
+    pub outbound: OutboundConfig,
+    pub sessions: SessionConfig,
+    pub limits: LimitsConfig,
+    pub plugins: PluginsConfig,
+    pub logging: LoggingConfig,
+    pub reload: ReloadConfig,
+    pub tenants: Vec<TenantConfig>,
+    pub admin: AdminConfig,
+    pub audit: Option<AuditConfig>,
+    pub telemetry: Option<TelemetryConfig>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+/// Settings from the optional `logging` block of the server config
+#[derive(Debug, Clone)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    /// Filter directives; `RUST_LOG` is used when this is not configured
+    pub level: String,
+}
+
+impl LoggingConfig {
+    /// Read the `logging` block, e.g. `{ "format": "json", "level": "info,mcpi_server=debug" }`
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "logging", &["format", "level"])?;
+        let format = match config.and_then(|c| c.get("format")) {
+            Some(value) => match value.as_str() {
+                Some("text") => LogFormat::Text,
+                Some("json") => LogFormat::Json,
+                _ => return Err(format!("logging.format must be \"text\" or \"json\", got {}", value)),
+            },
+            None => LogFormat::Text,
+        };
+        let level = match config.and_then(|c| c.get("level")) {
+            Some(Value::String(level)) => level.clone(),
+            Some(other) => return Err(format!("logging.level must be a string, got {}", other)),
+            None => std::env::var("RUST_LOG").unwrap_or_else(|_| "info".to_string()),
+        };
+        EnvFilter::try_new(&level).map_err(|e| format!("Invalid logging.level '{}': {}", level, e))?;
+        Ok(LoggingConfig { format, level })
+    }
+
+    /// Install the global subscriber, exporting spans if telemetry is on. With
+    /// stdio enabled stdout carries the JSON-RPC stream, so logs go to stderr.
+    pub fn init_tracing(&self, to_stderr: bool, telemetry: Option<&Telemetry>) {
+        let registry = tracing_subscriber::registry()
+            .with(EnvFilter::new(&self.level))
+            .with(telemetry.map(|telemetry| telemetry.layer()));
+        let fmt = tracing_subscriber::fmt::layer();
+        match (self.format, to_stderr) {
+            (LogFormat::Json, true) => registry.with(fmt.json().with_writer(std::io::stderr)).init(),
+            (LogFormat::Json, false) => registry.with(fmt.json()).init(),
+            (LogFormat::Text, true) => registry.with(fmt.with_writer(std::io::stderr)).init(),
+            (LogFormat::Text, false) => registry.with(fmt).init(),
+        }
+    }
+}
+
+// Parse a config file by its extension; an empty file is an empty config
+fn read_file(path: &Path) -> Result<Value, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("Cannot read {}: {}", path.display(), e))?;
+    if text.trim().is_empty() {
+        return Ok(Value::Object(Map::new()));
+    }
+    let value: Value = if path.extension().is_some_and(|ext| ext == "toml") {
+        toml::from_str(&text).map_err(|e| format!("Invalid TOML in {}: {}", path.display(), e))?
+    } else {
+        serde_json::from_str(&text).map_err(|e| format!("Invalid JSON in {}: {}", path.display(), e))?
+    };
+    if !value.is_object() {
+        return Err(format!("{} must contain a table of settings", path.display()));
+    }
+    Ok(value)
+}
+
+// MCPI_LIMITS__MAX_JSON_DEPTH=32 sets limits.max_json_depth. Values are read as
+// JSON where they parse, otherwise as strings. A name without `__` is only read if
+// it names a whole section, so unrelated MCPI_* variables are left alone.
+fn env_layer(env: impl IntoIterator<Item = (String, String)>) -> Result<Value, String> {
+    let mut layer = Value::Object(Map::new());
+    for (name, raw) in env {
+        let Some(key) = name.strip_prefix(ENV_PREFIX) else { continue };
+        // Read by clap as the --config and --data-dir flags
+        if key == "CONFIG" || key == "DATA_DIR" {
+            continue;
+        }
+        let path = key.to_lowercase().replace("__", ".");
+        if !key.contains("__") && !SECTIONS.contains(&path.as_str()) {
+            continue;
+        }
+        set_path(&mut layer, &path, parse_value(&raw)).map_err(|e| format!("{}: {}", name, e))?;
+    }
+    Ok(layer)
+}
+
+fn cli_layer(cli: &Cli) -> Result<Value, String> {
+    let mut layer = Value::Object(Map::new());
+    let mut set = |path: &str, value: Value| set_path(&mut layer, path, value);
+    if let Some(dir) = &cli.data_dir {
+        set("data_dir", Value::String(dir.to_string_lossy().into_owned()))?;
+    }
+    if let Some(bind) = &cli.bind {
+        set("http.bind_address", Value::String(bind.clone()))?;
+    }
+    if let Some(port) = cli.port {
+        set("http.port", Value::from(port))?;
+    }
+    if let Some(enabled) = &cli.transports {
+        const TRANSPORTS: &[&str] = &["streamable_http", "legacy_sse", "websocket", "stdio"];
+        if let Some(unknown) = enabled.iter().find(|t| !TRANSPORTS.contains(&t.as_str())) {
+            return Err(format!("Unknown transport '{}' in --transports; expected: {}", unknown, TRANSPORTS.join(", ")));
+        }
+        for transport in TRANSPORTS {
+            set(&format!("transports.{}", transport), Value::Bool(enabled.iter().any(|t| t == transport)))?;
+        }
+    }
+    if let Some(plugins) = &cli.plugins {
+        set("plugins.enabled", Value::from(plugins.clone()))?;
+    }
+    if let Some(format) = &cli.log_format {
+        set("logging.format", Value::String(format.clone()))?;
+    }
+    if let Some(level) = &cli.log_level {
+        set("logging.level", Value::String(level.clone()))?;
+    }
+    for assignment in &cli.overrides {
+        let (path, raw) = assignment.split_once('=').ok_or_else(|| format!("--set expects KEY=VALUE, got '{}'", assignment))?;
+        set(path.trim(), parse_value(raw))?;
+    }
+    Ok(layer)
+}
+
+fn parse_value(raw: &str) -> Value {
+    serde_json::from_str(raw).unwrap_or_else(|_| Value::String(raw.to_string()))
+}
+
+// Set a dotted path, creating tables along the way
+fn set_path(tree: &mut Value, path: &str, value: Value) -> Result<(), String> {
+    if path.is_empty() || path.split('.').any(str::is_empty) {
+        return Err(format!("Invalid setting path '{}'", path));
+    }
+    let mut node = tree;
+    let mut keys = path.split('.').peekable();
+    while let Some(key) = keys.next() {
+        if !node.is_object() {
+            *node = Value::Object(Map::new());
+        }
+        let table = node.as_object_mut().expect("just made a table");
+        if keys.peek().is_none() {
+            table.insert(key.to_string(), value);
+            return Ok(());
+        }
+        node = table.entry(key.to_string()).or_insert_with(|| Value::Object(Map::new()));
+    }
+    Ok(())
+}
+
+// Merge `overlay` into `base`: tables merge key by key, anything else replaces
+fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Object(base), Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Reject keys of a block that its reader does not use, so a misspelled setting
+/// is reported instead of silently keeping its default. `block` is the block's
+/// dotted path, as used in error messages.
+pub fn check_keys(config: Option<&Value>, block: &str, known: &[&str]) -> Result<(), String> {
+    let Some(Value::Object(config)) = config else { return Ok(()) };
+    match config.keys().find(|key| !known.contains(&key.as_str())) {
+        Some(unknown) => Err(format!("{}.{} is not a known setting; expected one of: {}", block, unknown, known.join(", "))),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let dir = std::env::temp_dir().join(format!("mcpi-config-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("server")).unwrap();
+        fs::write(dir.join("server").join("data.json"), r#"{ "provider": { "name": "Test" }, "http": { "port": 4000 } }"#).unwrap();
+        fs::write(
+            dir.join("server").join("config.toml"),
+            "[http]\nport = 4001\nbind_address = \"127.0.0.1\"\n[limits]\nmax_json_depth = 10\nmax_batch_len = 5\n",
+        )
+        .unwrap();
+
+        let cli = Cli {
+            data_dir: Some(dir.clone()),
+            port: Some(4003),
+            overrides: vec!["limits.max_batch_len=7".to_string()],
+            ..Cli::default()
+        };
+        let env = vec![
+            ("MCPI_HTTP__PORT".to_string(), "4002".to_string()),
+            ("MCPI_LIMITS__MAX_JSON_DEPTH".to_string(), "20".to_string()),
+        ];
+        let config = ServerConfig::load_with_env(&cli, env).unwrap();
+        assert_eq!(config.get("provider").unwrap()["name"], "Test");
+        let settings = config.settings(false).unwrap();
+        assert_eq!(settings.http.port, 4003);
+        assert_eq!(settings.http.bind_address.to_string(), "127.0.0.1");
+        assert_eq!(settings.limits.max_json_depth, 20);
+        assert_eq!(settings.limits.max_batch_len, 7);
+
+        // Unrelated variables are ignored, but a misspelled section is still an error
+        let unrelated = vec![("MCPI_FOO".to_string(), "bar".to_string())];
+        assert!(ServerConfig::load_with_env(&cli, unrelated).is_ok());
+        let typo = vec![("MCPI_LIMTS__MAX_BATCH_LEN".to_string(), "1".to_string())];
+        let err = ServerConfig::load_with_env(&cli, typo).unwrap_err();
+        assert!(err.contains("Unknown config section 'limts'"));
+
+        // So is a misspelled key inside a known section, from any layer
+        let typo = vec![("MCPI_LIMITS__MAX_BATCH_LNE".to_string(), "1".to_string())];
+        let err = ServerConfig::load_with_env(&cli, typo).unwrap().settings(false).err().unwrap();
+        assert!(err.starts_with("limits.max_batch_lne is not a known setting"));
+        let cli = Cli { overrides: vec!["http.cors.mpc=[]".to_string()], ..cli };
+        let err = ServerConfig::load_with_env(&cli, vec![]).unwrap().settings(false).err().unwrap();
+        assert!(err.starts_with("http.cors.mpc is not a known setting"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,72 @@
+// mcpi-server/src/health.rs
+//
+// Liveness and readiness probes. `/healthz` answers as long as the server is
+// serving requests. `/readyz` runs every plugin's health check and lists the
+// degraded plugins without failing; only a server that is shutting down is
+// reported not ready, so load balancers stop sending it new clients.
+
+use crate::AppState;
+use axum::{extract::State, http::StatusCode, Json};
+use serde_json::{json, Map, Value};
+use std::sync::Arc;
+
+// Handler for GET /healthz
+pub async fn liveness() -> Json<Value> {
+    Json(json!({ "status": "ok" }))
+}
+
+// Handler for GET /readyz
+pub async fn readiness(State(state): State<Arc<AppState>>) -> (StatusCode, Json<Value>) {
+    // Health checks may read files, so keep them off the async workers
+    let registry = state.snapshot.get().registry.clone();
+    let checks = tokio::task::spawn_blocking(move || registry.check_health()).await.unwrap_or_default();
+    let (status, mut report) = readiness_report(state.drain.is_draining(), &checks);
+    report["tenant"] = json!(state.tenant);
+    (status, Json(report))
+}
+
+fn readiness_report(draining: bool, checks: &[(String, Result<(), String>)]) -> (StatusCode, Value) {
+    let mut plugins = Map::new();
+    let mut degraded = Vec::new();
+    for (name, result) in checks {
+        let health = match result {
+            Ok(()) => json!({ "status": "ok" }),
+            Err(e) => {
+                degraded.push(name.clone());
+                json!({ "status": "degraded", "error": e })
+            }
+        };
+        plugins.insert(name.clone(), health);
+    }
+    let (status, summary) = if draining {
+        (StatusCode::SERVICE_UNAVAILABLE, "draining")
+    } else if degraded.is_empty() {
+        (StatusCode::OK, "ready")
+    } else {
+        (StatusCode::OK, "degraded")
+    };
+    (status, json!({ "status": summary, "plugins": plugins, "degraded": degraded }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_readiness_report() {
+        let checks = vec![
+            ("hello".to_string(), Ok(())),
+            ("store_customer".to_string(), Err("Cannot read data file data/store/customers/data.json".to_string())),
+        ];
+        let (status, report) = readiness_report(false, &checks);
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(report["status"], "degraded");
+        assert_eq!(report["degraded"], json!(["store_customer"]));
+        assert_eq!(report["plugins"]["hello"]["status"], "ok");
+
+        let (status, report) = readiness_report(false, &checks[..1]);
+        assert_eq!((status, report["status"].as_str()), (StatusCode::OK, Some("ready")));
+        let (status, report) = readiness_report(true, &checks[..1]);
+        assert_eq!((status, report["status"].as_str()), (StatusCode::SERVICE_UNAVAILABLE, Some("draining")));
+    }
+}
@@ -0,0 +1,140 @@
+// mcpi-server/src/interceptor.rs
+//
+// Interceptors wrap request dispatch for cross-cutting concerns (auth, logging,
+// metrics, request rewriting). They run in registration order before dispatch
+// and in reverse order afterwards, for every request and notification no matter
+// which transport delivered it or whether it arrived inside a batch.
+
+use mcpi_common::{MCPError, MCPRequest};
+use serde_json::Value;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Instant;
+use tracing::debug;
+
+/// Boxed future returned by interceptor hooks
+pub type InterceptorFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
+
+/// Session information visible to interceptors
+#[derive(Debug, Clone)]
+pub struct SessionContext {
+    pub client_id: String,
+    /// Protocol version negotiated during `initialize`, if any
+    pub protocol_version: Option<String>,
+    /// `clientInfo` sent with `initialize`, if any
+    pub client_info: Option<Value>,
+    /// Whether the request is a notification (no response will be sent)
+    pub is_notification: bool,
+    /// Whether the request arrived as part of a JSON-RPC batch
+    pub in_batch: bool,
+    pub received_at: Instant,
+}
+
+pub trait Interceptor: Send + Sync {
+    /// Name used in logs
+    fn name(&self) -> &str;
+
+    /// Called before dispatch. May rewrite the request; returning an error
+    /// short-circuits the chain and becomes the request's response.
+    fn before<'a>(&'a self, ctx: &'a SessionContext, request: &'a mut MCPRequest) -> InterceptorFuture<'a, Result<(), MCPError>> {
+        let _ = (ctx, request);
+        Box::pin(async { Ok(()) })
+    }
+
+    /// Called after dispatch (or after a short-circuit) with the outcome, which
+    /// may be inspected or replaced.
+    fn after<'a>(&'a self, ctx: &'a SessionContext, request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> InterceptorFuture<'a, ()> {
+        let _ = (ctx, request, outcome);
+        Box::pin(async {})
+    }
+}
+
+/// Keys whose values are replaced before params are written to the log
+pub const DEFAULT_REDACTED_KEYS: &[&str] = &["password", "token", "secret", "api_key", "apiKey", "authorization"];
+
+/// Logs each request with sensitive params redacted, and its outcome and duration
+pub struct RequestLogInterceptor {
+    redacted_keys: Vec<String>,
+}
+
+impl RequestLogInterceptor {
+    pub fn new() -> Self {
+        RequestLogInterceptor {
+            redacted_keys: DEFAULT_REDACTED_KEYS.iter().map(|k| k.to_string()).collect(),
+        }
+    }
+}
+
+impl Default for RequestLogInterceptor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interceptor for RequestLogInterceptor {
+    fn name(&self) -> &str {
+        "request-log"
+    }
+
+    fn before<'a>(&'a self, ctx: &'a SessionContext, request: &'a mut MCPRequest) -> InterceptorFuture<'a, Result<(), MCPError>> {
+        Box::pin(async move {
+            let params = request.params.as_ref().map(|p| redact(p, &self.redacted_keys));
+            debug!(
+                client = %ctx.client_id,
+                method = %request.method,
+                protocol_version = ?ctx.protocol_version,
+                notification = ctx.is_notification,
+                in_batch = ctx.in_batch,
+                params = ?params,
+                "Request received"
+            );
+            Ok(())
+        })
+    }
+
+    fn after<'a>(&'a self, ctx: &'a SessionContext, request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> InterceptorFuture<'a, ()> {
+        Box::pin(async move {
+            let elapsed_ms = ctx.received_at.elapsed().as_millis();
+            match outcome {
+                Ok(_) => debug!(client = %ctx.client_id, method = %request.method, elapsed_ms, "Request completed"),
+                Err(e) => debug!(client = %ctx.client_id, method = %request.method, elapsed_ms, code = e.code, "Request failed"),
+            }
+        })
+    }
+}
+
+/// Return a copy of `value` with the values of any `keys` (at any depth) replaced
+pub fn redact(value: &Value, keys: &[String]) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| {
+                    let v = if keys.iter().any(|key| key.eq_ignore_ascii_case(k)) {
+                        Value::String("[REDACTED]".to_string())
+                    } else {
+                        redact(v, keys)
+                    };
+                    (k.clone(), v)
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(items.iter().map(|v| redact(v, keys)).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_redact_nested_keys() {
+        let keys = vec!["password".to_string(), "token".to_string()];
+        let params = json!({ "name": "login", "arguments": { "Password": "hunter2", "items": [{ "token": "abc" }] } });
+        let redacted = redact(&params, &keys);
+        assert_eq!(redacted["name"], "login");
+        assert_eq!(redacted["arguments"]["Password"], "[REDACTED]");
+        assert_eq!(redacted["arguments"]["items"][0]["token"], "[REDACTED]");
+    }
+}
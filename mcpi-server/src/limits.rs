@@ -0,0 +1,216 @@
+// mcpi-server/src/limits.rs
+//
+// Limits on incoming messages, so a single client cannot make the server buffer
+// or parse arbitrarily large input: message size (HTTP body or WebSocket frame),
+// JSON nesting depth, batch length and the length of strings in tool arguments.
+// Size and depth are checked on the raw text before it is parsed.
+
+use crate::config::check_keys;
+use axum::{body::Body, extract::{Request, State}, http::StatusCode, middleware::Next, response::Response};
+use mcpi_common::MCPRequest;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Settings from the optional `limits` block of the server config
+#[derive(Debug, Clone)]
+pub struct LimitsConfig {
+    /// Largest HTTP body, WebSocket message or stdio line
+    pub max_message_bytes: usize,
+    pub max_json_depth: usize,
+    pub max_batch_len: usize,
+    /// Longest string anywhere in `tools/call` arguments, in bytes
+    pub max_string_bytes: usize,
+    /// Batch items processed at the same time
+    pub batch_concurrency: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            max_message_bytes: 4 * 1024 * 1024,
+            max_json_depth: 64,
+            max_batch_len: 100,
+            max_string_bytes: 1024 * 1024,
+            batch_concurrency: 8,
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// Read the `limits` block, e.g.
+    /// `{ "max_message_bytes": 4194304, "max_json_depth": 64, "max_batch_len": 100, "max_string_bytes": 1048576, "batch_concurrency": 8 }`.
+    /// Missing keys keep their defaults.
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "limits", &["max_message_bytes", "max_json_depth", "max_batch_len", "max_string_bytes", "batch_concurrency"])?;
+        let mut limits = LimitsConfig::default();
+        for (key, field) in [
+            ("max_message_bytes", &mut limits.max_message_bytes),
+            ("max_json_depth", &mut limits.max_json_depth),
+            ("max_batch_len", &mut limits.max_batch_len),
+            ("max_string_bytes", &mut limits.max_string_bytes),
+            ("batch_concurrency", &mut limits.batch_concurrency),
+        ] {
+            if let Some(value) = config.and_then(|c| c.get(key)) {
+                *field = value
+                    .as_u64()
+                    .filter(|v| *v > 0)
+                    .map(|v| v as usize)
+                    .ok_or_else(|| format!("limits.{} must be a positive number, got {}", key, value))?;
+            }
+        }
+        Ok(limits)
+    }
+}
+
+#[derive(Default)]
+struct Rejections {
+    message_too_large: AtomicU64,
+    too_deep: AtomicU64,
+    batch_too_long: AtomicU64,
+    string_too_long: AtomicU64,
+}
+
+/// The configured limits and how often each was hit
+pub struct Limits {
+    config: LimitsConfig,
+    rejected: Rejections,
+}
+
+impl Limits {
+    pub fn new(config: LimitsConfig) -> Self {
+        Limits { config, rejected: Rejections::default() }
+    }
+
+    pub fn config(&self) -> &LimitsConfig {
+        &self.config
+    }
+
+    /// Check a raw message before it is parsed
+    pub fn check_message(&self, message: &str) -> Result<(), String> {
+        if message.len() > self.config.max_message_bytes {
+            self.rejected.message_too_large.fetch_add(1, Ordering::Relaxed);
+            return Err(format!("Message of {} bytes exceeds the limit of {}", message.len(), self.config.max_message_bytes));
+        }
+        if exceeds_depth(message, self.config.max_json_depth) {
+            self.rejected.too_deep.fetch_add(1, Ordering::Relaxed);
+            return Err(format!("JSON nesting exceeds the limit of {} levels", self.config.max_json_depth));
+        }
+        Ok(())
+    }
+
+    pub fn check_batch(&self, len: usize) -> Result<(), String> {
+        if len > self.config.max_batch_len {
+            self.rejected.batch_too_long.fetch_add(1, Ordering::Relaxed);
+            return Err(format!("Batch of {} messages exceeds the limit of {}", len, self.config.max_batch_len));
+        }
+        Ok(())
+    }
+
+    /// Check the strings in a `tools/call` request's arguments
+    pub fn check_tool_arguments(&self, request: &MCPRequest) -> Result<(), String> {
+        let arguments = request.params.as_ref().and_then(|p| p.get("arguments"));
+        match arguments.and_then(longest_string) {
+            Some(len) if len > self.config.max_string_bytes => {
+                self.rejected.string_too_long.fetch_add(1, Ordering::Relaxed);
+                Err(format!("Argument string of {} bytes exceeds the limit of {}", len, self.config.max_string_bytes))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Limits and rejection counts for the admin stats
+    pub fn stats(&self) -> Value {
+        json!({
+            "max_message_bytes": self.config.max_message_bytes,
+            "max_json_depth": self.config.max_json_depth,
+            "max_batch_len": self.config.max_batch_len,
+            "max_string_bytes": self.config.max_string_bytes,
+            "batch_concurrency": self.config.batch_concurrency,
+            "rejected": {
+                "message_too_large": self.rejected.message_too_large.load(Ordering::Relaxed),
+                "too_deep": self.rejected.too_deep.load(Ordering::Relaxed),
+                "batch_too_long": self.rejected.batch_too_long.load(Ordering::Relaxed),
+                "string_too_long": self.rejected.string_too_long.load(Ordering::Relaxed),
+            },
+        })
+    }
+}
+
+/// Count the `413 Payload Too Large` responses that the body limit layer produces
+/// before a handler ever sees the request
+pub async fn count_oversized_bodies(State(limits): State<Arc<Limits>>, request: Request<Body>, next: Next) -> Response {
+    let response = next.run(request).await;
+    if response.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        limits.rejected.message_too_large.fetch_add(1, Ordering::Relaxed);
+    }
+    response
+}
+
+// Scan the raw text for brackets outside of strings, so deeply nested input is
+// rejected without building anything
+fn exceeds_depth(text: &str, max_depth: usize) -> bool {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for byte in text.bytes() {
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match byte {
+            b'"' => in_string = true,
+            b'[' | b'{' => {
+                depth += 1;
+                if depth > max_depth {
+                    return true;
+                }
+            }
+            b']' | b'}' => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+    }
+    false
+}
+
+fn longest_string(value: &Value) -> Option<usize> {
+    match value {
+        Value::String(s) => Some(s.len()),
+        Value::Array(items) => items.iter().filter_map(longest_string).max(),
+        Value::Object(map) => map.iter().filter_map(|(k, v)| longest_string(v).map(|l| l.max(k.len()))).max(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits::new(LimitsConfig { max_message_bytes: 64, max_json_depth: 3, max_batch_len: 2, max_string_bytes: 4, batch_concurrency: 1 });
+        assert!(limits.check_message(r#"{"a":[{"b":1}]}"#).is_ok());
+        // Brackets inside strings do not count
+        assert!(limits.check_message(r#"{"a":"[[[[[["}"#).is_ok());
+        assert!(limits.check_message(r#"{"a":[{"b":[1]}]}"#).is_err());
+        assert!(limits.check_message(&format!(r#"{{"a":"{}"}}"#, "x".repeat(64))).is_err());
+        assert!(limits.check_batch(3).is_err());
+
+        let request: MCPRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": { "name": "t", "arguments": { "q": ["ok", "too long"] } }
+        }))
+        .unwrap();
+        assert!(limits.check_tool_arguments(&request).is_err());
+
+        let stats = limits.stats();
+        assert_eq!(stats["rejected"]["message_too_large"], 1);
+        assert_eq!(stats["rejected"]["too_deep"], 1);
+        assert_eq!(stats["rejected"]["string_too_long"], 1);
+    }
+}
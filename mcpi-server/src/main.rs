@@ -2,239 +2,284 @@
 
 // --- Standard Imports ---
 use axum::{
-    extract::{ws::{WebSocket, WebSocketUpgrade, Message}, State},
-    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
-    routing::{get, post, delete},
-    Router, Json,
-    http::{StatusCode, HeaderMap, header::{HeaderName, HeaderValue}},
+    extract::State,
+    Json,
 };
-use mcpi_common::{
-    CapabilityDescription, DiscoveryResponse, MCPRequest, Resource, Tool,
-    ServerCapabilities, MCPI_VERSION, ContentItem, ResourcesCapability, ToolsCapability,
-    Provider, Referral, InitializeResult, CallToolResult, ReadResourceResult,
-    ListResourcesResult, ListToolsResult, CompleteResult, EmptyResult,
-    PromptsCapability, LoggingCapability, CompletionsCapability,
-    ResourceContentUnion, TextResourceContents,
-    LATEST_MCP_VERSION,
-};
-use serde_json::{json, Value};
+use clap::Parser;
+use mcpi_common::{CapabilityDescription, DiscoveryResponse, Referral};
 use std::{
-    collections::HashMap,
-    net::SocketAddr,
-    path::Path,
     sync::{atomic::{AtomicUsize, Ordering}, Arc},
-    time::{Instant, Duration},
-    fs,
+    time::Instant,
     error::Error,
-    convert::Infallible,
-};
-use tokio::net::TcpListener;
-use tokio::sync::{broadcast, RwLock};
-// Removed tower imports
-use tower_http::{
-    cors::CorsLayer,
-    trace::TraceLayer,
 };
-use tracing::{error, info, warn};
-use url::Url;
-use rand::Rng;
-use futures::Stream;
-use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
-use tokio_stream::StreamExt;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
 
 
 // --- Local Modules ---
 mod admin;
+mod admin_auth;
+mod audit;
+mod config;
+mod health;
+mod interceptor;
+mod limits;
 mod message_handler;
+mod metrics;
+mod methods;
+mod outbound;
 mod plugin_registry;
 mod plugins;
+mod reload;
+mod router;
+mod security;
+mod sessions;
+mod shutdown;
+mod telemetry;
+mod tenants;
+mod tls;
 mod traits;
+mod transport;
 
+use audit::{AuditInterceptor, AuditLog};
+use config::{Cli, ServerConfig};
+use limits::LimitsConfig;
+use interceptor::RequestLogInterceptor;
 use message_handler::McpMessageHandler;
-use plugin_registry::PluginRegistry;
-use crate::traits::MessageHandler;
+use metrics::{Metrics, TenantMetrics};
+use outbound::OutboundQueues;
+use plugin_registry::{PluginRegistry, PluginsConfig};
+use reload::{ConfigSource, Provider, Reloadable, Reloader, Snapshot};
+use sessions::{SessionConfig, SessionStore};
+use shutdown::Drain;
+use transport::{stdio::StdioTransport, tcp::TcpTransport, websocket::WsConnections, TransportManager};
 
 
 // --- Constants ---
-const DATA_PATH: &str = "data";
-const CONFIG_FILE_PATH: &str = "data/server/data.json";
-const SERVER_PORT: u16 = 3001;
-static MCP_SESSION_ID: HeaderName = HeaderName::from_static("mcp-session-id");
+/// Server-wide notifications buffered for slow subscribers
+const NOTIFICATION_CAPACITY: usize = 32;
+/// Name of the tenant served by the root configuration
+const ROOT_TENANT: &str = "default";
 
 
 // --- Shared Application State ---
+// One per tenant; the outbound queues and in-flight tracking are shared by all of them
 pub struct AppState {
-    registry: Arc<PluginRegistry>,
-    provider_info: Arc<Value>,
-    referrals: Arc<Value>,
+    tenant: String,
+    /// Path the tenant's routes are mounted under, empty if none
+    path_prefix: String,
+    /// Provider info, referrals and plugins, replaced together on reload
+    snapshot: Arc<Reloadable<Snapshot>>,
+    reloader: Arc<Reloader>,
     message_handler: Arc<McpMessageHandler>,
-    http_sessions: Arc<RwLock<HashMap<String, HttpSessionInfo>>>,
-    active_ws_connections: AtomicUsize,
+    http_sessions: Arc<SessionStore>,
+    ws_connections: Arc<WsConnections>,
+    /// Notifications for every client, for transports without a connection registry (stdio)
+    notifications: broadcast::Sender<String>,
+    outbound: Arc<OutboundQueues>,
+    drain: Arc<Drain>,
+    metrics: Arc<Metrics>,
+    audit: Option<Arc<AuditLog>>,
     request_count: AtomicUsize,
     startup_time: Instant,
 }
 
-// --- Session Info for Streamable HTTP ---
-struct HttpSessionInfo {
-    sender: broadcast::Sender<Event>,
+// Settings every tenant is built with
+struct TenantDefaults {
+    outbound: Arc<OutboundQueues>,
+    drain: Arc<Drain>,
+    limits: LimitsConfig,
+    sessions: SessionConfig,
+    metrics: Arc<Metrics>,
+    audit: Option<Arc<AuditLog>>,
 }
 
 // --- Main Function ---
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
-    tracing_subscriber::fmt::init();
-    validate_paths()?;
-    let config = load_config()?;
-
-    let provider_info = Arc::new(config.get("provider").cloned().unwrap_or_else(|| json!({})));
-    let referrals = Arc::new(config.get("referrals").cloned().unwrap_or_else(|| json!([])));
-
-    let registry = Arc::new(PluginRegistry::new());
-    registry.register_all_plugins(DATA_PATH, (*referrals).clone())?;
-    info!("Registered {} plugins", registry.get_all_plugins().len());
-
-    // Ensure McpMessageHandler::new takes Arc<PluginRegistry> and Arc<Value>
-    let message_handler = Arc::new(McpMessageHandler::new(
-        registry.clone(),
-        provider_info.clone(),
-    ));
-
-    let app_state = Arc::new(AppState {
-        registry,
-        provider_info,
-        referrals,
-        message_handler,
-        http_sessions: Arc::new(RwLock::new(HashMap::new())),
-        active_ws_connections: AtomicUsize::new(0),
-        request_count: AtomicUsize::new(0),
-        startup_time: Instant::now(),
-    });
-
-    let cors = CorsLayer::permissive();
-
-    let app_router = Router::new()
-        .route("/mcp", get(handle_streamable_get).post(handle_streamable_post).delete(handle_streamable_delete))
-        .route("/mcpi", get(ws_handler))
-        .route("/mcpi/discover", get(discovery_handler))
-        .route("/admin", get(admin::serve_admin_html))
-        .route("/api/admin/stats", get(admin::get_stats))
-        .route("/api/admin/plugins", get(admin::get_plugins))
-        .layer(TraceLayer::new_for_http())
-        .layer(cors)
-        .with_state(app_state.clone());
-
-    let addr = SocketAddr::from(([0, 0, 0, 0], SERVER_PORT));
-    info!("Starting unified server (MCP/MCPI/Admin) on {}", addr);
-    let listener = TcpListener::bind(addr).await?;
-    info!("Server listening on {}", addr);
-    axum::serve(listener, app_router.into_make_service())
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
-    info!("Server shut down successfully");
-    Ok(())
-}
+    // Every setting is validated before anything starts
+    let cli = Cli::parse();
+    if cli.hash_password {
+        let mut password = String::new();
+        std::io::stdin().read_line(&mut password)?;
+        println!("{}", admin_auth::hash_password(password.trim_end_matches(['\r', '\n']))?);
+        return Ok(());
+    }
+    let config = ServerConfig::load(&cli)?;
+    let settings = config.settings(cli.stdio)?;
+    let transports = settings.transports;
+    let telemetry = match &settings.telemetry {
+        Some(telemetry_config) => Some(telemetry_config.build()?),
+        None => None,
+    };
+    settings.logging.init_tracing(transports.stdio, telemetry.as_ref());
+    match &config.file {
+        Some(file) => info!("Loaded config from {}", file.display()),
+        None => info!("No config file found, using defaults"),
+    }
+    if let Some(telemetry) = &telemetry {
+        info!("Exporting trace spans to {}", telemetry.description());
+    }
 
-// --- Graceful Shutdown Signal Handler ---
-async fn shutdown_signal() {
-    tokio::signal::ctrl_c().await.expect("Failed to install Ctrl+C handler");
-    info!("Shutdown signal received...");
-}
+    let http_config = settings.http;
+    let admin_config = settings.admin;
+    let admin_bind = admin_config.listener.as_ref().map_or(http_config.bind_address, |listener| listener.addr.ip());
+    if admin_config.auth.is_none() && !admin_bind.is_loopback() {
+        warn!("Admin routes and metrics are served without authentication on {}; configure admin.auth", admin_bind);
+    }
+    let shutdown_config = settings.shutdown;
+    let outbound_config = settings.outbound;
+    info!("Outbound queues hold {} messages per client ({} when full)", outbound_config.queue_capacity, outbound_config.policy.as_str());
+    let session_config = settings.sessions;
+    info!("HTTP sessions expire after {:?} idle or {:?} total", session_config.idle_timeout, session_config.absolute_timeout);
+    let audit = match settings.audit {
+        Some(audit_config) => {
+            let audit = AuditLog::new(audit_config);
+            audit.open().map_err(|e| format!("Cannot open audit log {}: {}", audit.path().display(), e))?;
+            info!("Auditing tool calls and resource reads to {}", audit.path().display());
+            Some(Arc::new(audit))
+        }
+        None => None,
+    };
+    let defaults = TenantDefaults {
+        outbound: Arc::new(OutboundQueues::new(outbound_config)),
+        drain: Arc::new(Drain::new()),
+        limits: settings.limits,
+        sessions: session_config,
+        metrics: Arc::new(Metrics::new()),
+        audit,
+    };
+
+    let root = build_state(ROOT_TENANT, "", &config, settings.plugins, ConfigSource::Root(cli.clone()), &defaults)?;
+    let mut tenants = vec![root.clone()];
+    let mut watched = vec![(root.clone(), config.data_dir.clone(), config.file.clone())];
+    let mut tenant_routers = Vec::new();
+    let mut admin_routers = Vec::new();
+    for tenant in settings.tenants {
+        let tenant_config = ServerConfig::load_tenant(&tenant)?;
+        let plugins = PluginsConfig::from_config(tenant_config.get("plugins"))?;
+        let prefix = tenant.path_prefix.clone().unwrap_or_default();
+        let state = build_state(&tenant.name, &prefix, &tenant_config, plugins, ConfigSource::Tenant(tenant.clone()), &defaults)?;
+        info!("Tenant {} serves hosts {:?} under '{}' from {}", tenant.name, tenant.hosts, prefix, tenant.data_dir.display());
+        watched.push((state.clone(), tenant.data_dir.clone(), None));
+        if admin_config.listener.is_some() {
+            admin_routers.push((tenant.clone(), transport::http::admin_router(state.clone(), &http_config, &admin_config)));
+        }
+        tenant_routers.push((tenant, transport::http::router(state.clone(), &transports, &http_config, &admin_config)));
+        tenants.push(state);
+    }
 
+    for state in &tenants {
+        tokio::spawn(reap_expired_sessions(state.clone()));
+    }
+    tokio::spawn(reload::reload_on_sighup(tenants.clone()));
+    if settings.reload.watch {
+        for (state, data_dir, file) in watched {
+            info!("Watching {} for changes every {:?}", data_dir.display(), settings.reload.poll_interval);
+            tokio::spawn(reload::watch_for_changes(state, data_dir, file, settings.reload.poll_interval));
+        }
+    }
 
-// --- Streamable HTTP Handlers ---
-async fn handle_streamable_get(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-) -> Result<(HeaderMap, Sse<impl Stream<Item = Result<Event, Infallible>>>), StatusCode> {
-    state.request_count.fetch_add(1, Ordering::SeqCst);
-    let provided_session_id = headers.get(&MCP_SESSION_ID).and_then(|hv| hv.to_str().ok());
-
-    let session_id: String;
-    let sender: broadcast::Sender<Event>;
-    let receiver: broadcast::Receiver<Event>;
-    let mut response_headers = HeaderMap::new();
-
-    response_headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
-    response_headers.insert(axum::http::header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
-    response_headers.insert(axum::http::header::CONNECTION, HeaderValue::from_static("keep-alive"));
-
-    let mut sessions = state.http_sessions.write().await;
-
-    match provided_session_id {
-        Some(id) if sessions.contains_key(id) => {
-            session_id = id.to_string();
-            info!("SSE stream re-establishing session: {}", session_id);
-            sender = sessions.get(&session_id).unwrap().sender.clone();
-            receiver = sender.subscribe();
-            response_headers.insert(MCP_SESSION_ID.clone(), HeaderValue::from_str(&session_id).unwrap());
+    // The TCP and Unix socket listeners serve the same router
+    let root_router = transport::http::router(root.clone(), &transports, &http_config, &admin_config);
+    let app_router = transport::http::tenant_router(root_router, tenant_routers);
+    let mut manager = TransportManager::new();
+    if !cli.stdio {
+        if http_config.tcp_enabled {
+            manager.register_transport(Box::new(TcpTransport::new(http_config.socket_addr(), settings.tls, app_router.clone())));
+        } else {
+            info!("TCP listener disabled");
         }
-        _ => {
-            session_id = format!("mcp-session-{}", rand::thread_rng().gen::<u32>());
-            info!("SSE stream creating new session: {}", session_id);
-            let (new_sender, new_receiver) = broadcast::channel(32);
-            sessions.insert(session_id.clone(), HttpSessionInfo { sender: new_sender.clone() });
-            sender = new_sender;
-            receiver = new_receiver;
-            response_headers.insert(MCP_SESSION_ID.clone(), HeaderValue::from_str(&session_id).unwrap());
+        #[cfg(unix)]
+        if let Some(uds) = settings.unix_socket {
+            manager.register_transport(Box::new(transport::unix_socket::UnixSocketTransport::new(uds, app_router)));
         }
     }
-    drop(sessions);
-
-    // Convert the receiver into a stream, handling potential lagged errors
-    let stream = BroadcastStream::new(receiver)
-        // FIX: Add `move` keyword here
-        .filter_map(move |res| { // Capture session_id by value
-            match res {
-                Ok(event) => Some(Ok(event)),
-                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
-                    // session_id is moved into the closure here
-                    warn!("SSE stream for session {} lagged, skipped {} messages", session_id, skipped);
-                    None
-                }
-            }
-        });
-
-    let sse = Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("keep-alive"));
-
-    Ok((response_headers, sse))
-}
-
-async fn handle_streamable_post( State(state): State<Arc<AppState>>, headers: HeaderMap, body: String ) -> impl IntoResponse {
-    state.request_count.fetch_add(1, Ordering::SeqCst);
-    let session_id = headers.get(&MCP_SESSION_ID).and_then(|v| v.to_str().ok()).map(str::to_string);
-    let client_id = session_id.clone().unwrap_or_else(|| format!("http-{}", rand::thread_rng().gen::<u32>()));
+    // stdio has no host or path, so it always serves the root configuration
+    if transports.stdio {
+        manager.register_transport(Box::new(StdioTransport::new(root.message_handler.clone(), root.notifications.subscribe())));
+    }
+    if manager.is_empty() {
+        return Err("No transport enabled: TCP is disabled (http.tcp = false), no unix_socket is configured and stdio is off".into());
+    }
+    if let (Some(listener), false) = (&admin_config.listener, cli.stdio) {
+        info!("Admin routes and metrics are served on {} only", listener.addr);
+        let admin_router = transport::http::tenant_router(transport::http::admin_router(root.clone(), &http_config, &admin_config), admin_routers);
+        manager.register_transport(Box::new(TcpTransport::new(listener.addr, listener.tls.clone(), admin_router)));
+    }
 
-    if let Some(ref id_str) = session_id { if !state.http_sessions.read().await.contains_key(id_str) { warn!("POST /mcp for non-existent session: {}", id_str); } else { info!("POST /mcp for session: {}", id_str); } }
-    else { info!("POST /mcp without session ID (client_id: {})", client_id); }
+    manager.start_all().await?;
+    let draining = tenants.clone();
+    let drain = defaults.drain.clone();
+    let result = manager
+        .run(shutdown::shutdown_signal(), async move { shutdown::drain(&drain, &draining, &shutdown_config).await })
+        .await;
+    for state in &tenants {
+        state.snapshot.get().registry.shutdown_plugins();
+    }
+    if let Some(telemetry) = &telemetry {
+        telemetry.shutdown();
+    }
+    result?;
+    info!("Server shut down successfully");
+    Ok(())
+}
 
-    // TODO: POST -> SSE upgrade logic
-    if let Some(response_body) = state.message_handler.handle_message(body, client_id).await { (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/json"))], response_body).into_response() }
-    else { (StatusCode::NO_CONTENT, "").into_response() }
+// --- Tenant State ---
+fn build_state(
+    tenant: &str,
+    path_prefix: &str,
+    config: &ServerConfig,
+    plugins: PluginsConfig,
+    source: ConfigSource,
+    defaults: &TenantDefaults,
+) -> Result<Arc<AppState>, Box<dyn Error + Send + Sync>> {
+    let provider = Provider::from_config(config);
+    let registry = Arc::new(PluginRegistry::new().with_config(plugins));
+    registry.register_all_plugins(&config.data_dir.to_string_lossy(), (*provider.referrals).clone())?;
+    info!("Registered {} plugins for {}", registry.get_all_plugins().len(), tenant);
+    let snapshot = Arc::new(Reloadable::new(Snapshot { provider, registry }));
+
+    let mut message_handler = McpMessageHandler::new(snapshot.clone())
+        .with_limits(defaults.limits.clone())
+        .with_drain(defaults.drain.clone())
+        .with_interceptor(Arc::new(RequestLogInterceptor::new()))
+        .with_metrics(TenantMetrics::new(defaults.metrics.clone(), tenant));
+    if let Some(audit) = &defaults.audit {
+        message_handler = message_handler.with_interceptor(Arc::new(AuditInterceptor::new(audit.clone(), tenant)));
+    }
+    let message_handler = Arc::new(message_handler);
+    let state = Arc::new(AppState {
+        tenant: tenant.to_string(),
+        path_prefix: path_prefix.to_string(),
+        snapshot,
+        reloader: Arc::new(Reloader::new(source)),
+        message_handler,
+        http_sessions: Arc::new(SessionStore::new(defaults.sessions.clone(), defaults.outbound.clone())),
+        ws_connections: Arc::new(WsConnections::new(defaults.outbound.clone())),
+        notifications: broadcast::channel(NOTIFICATION_CAPACITY).0,
+        outbound: defaults.outbound.clone(),
+        drain: defaults.drain.clone(),
+        metrics: defaults.metrics.clone(),
+        audit: defaults.audit.clone(),
+        request_count: AtomicUsize::new(0),
+        startup_time: Instant::now(),
+    });
+    defaults.metrics.track(&state);
+    Ok(state)
 }
 
-async fn handle_streamable_delete( State(state): State<Arc<AppState>>, headers: HeaderMap ) -> impl IntoResponse {
-    state.request_count.fetch_add(1, Ordering::SeqCst);
-    if let Some(session_id) = headers.get(&MCP_SESSION_ID).and_then(|v| v.to_str().ok()) {
-        if state.http_sessions.write().await.remove(session_id).is_some() { info!("Session {} terminated via DELETE /mcp", session_id); (StatusCode::OK, "Session terminated").into_response() }
-        else { warn!("DELETE /mcp for non-existent session: {}", session_id); (StatusCode::NOT_FOUND, "Session not found").into_response() }
-    } else { warn!("DELETE /mcp missing mcp-session-id"); (StatusCode::BAD_REQUEST, "mcp-session-id header required").into_response() }
+// --- Session Reaper ---
+async fn reap_expired_sessions(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(state.http_sessions.config().reap_interval);
+    loop {
+        interval.tick().await;
+        for session_id in state.http_sessions.reap().await {
+            state.message_handler.forget_client(&session_id);
+        }
+    }
 }
 
-// --- WebSocket Handlers ---
-async fn ws_handler( ws: WebSocketUpgrade, State(state): State<Arc<AppState>>, _headers: HeaderMap ) -> Response { let client_id = format!("ws-{}", rand::thread_rng().gen::<u32>()); info!("WebSocket upgrade request (/mcpi) from client: {}", client_id); ws.on_upgrade(move |socket| handle_socket(socket, state, client_id)) }
-async fn handle_socket(mut socket: WebSocket, state: Arc<AppState>, client_id: String) { info!("WebSocket client connected: {}", client_id); state.active_ws_connections.fetch_add(1, Ordering::SeqCst); loop { tokio::select! { msg_result = socket.recv() => { match msg_result { Some(Ok(msg)) => { if !process_ws_message(msg, &mut socket, &state, &client_id).await { break; } } Some(Err(e)) => { warn!("WS recv error from {}: {}", client_id, e); break; } None => { info!("WS client {} disconnected (recv None)", client_id); break; } } } } } info!("WebSocket client disconnected: {}", client_id); state.active_ws_connections.fetch_sub(1, Ordering::SeqCst); }
-async fn process_ws_message( msg: Message, socket: &mut WebSocket, state: &Arc<AppState>, client_id: &str, ) -> bool { match msg { Message::Text(text) => { info!("Received text from WS {}: {}", client_id, text.chars().take(100).collect::<String>()); if let Some(response) = state.message_handler.handle_message(text, client_id.to_string()).await { if socket.send(Message::Text(response)).await.is_err() { return false; } } } Message::Binary(_) => warn!("Unexpected binary msg from WS {}", client_id), Message::Ping(data) => if socket.send(Message::Pong(data)).await.is_err() { return false; }, Message::Pong(_) => info!("Received Pong from WS {}", client_id), Message::Close(_) => { info!("WS client {} sent close frame", client_id); return false; } } true }
-
-// --- Other Handlers (Discovery, MCP Processing Logic) ---
-async fn discovery_handler(State(state): State<Arc<AppState>>) -> Json<DiscoveryResponse> { state.request_count.fetch_add(1, Ordering::SeqCst); info!("Handling /mcpi/discover request"); let provider = Provider { name: state.provider_info.get("name").and_then(|n|n.as_str()).unwrap_or("").to_string(), domain: state.provider_info.get("domain").and_then(|d|d.as_str()).unwrap_or("").to_string(), description: state.provider_info.get("description").and_then(|d|d.as_str()).unwrap_or("").to_string(), branding: None }; let referrals = if let Some(refs) = state.referrals.as_array() { refs.iter().filter_map(|r| Some(Referral{name: r.get("name")?.as_str()?.to_string(), domain: r.get("domain")?.as_str()?.to_string(), relationship: r.get("relationship")?.as_str()?.to_string() })).collect() } else { vec![] }; let caps = state.registry.get_all_plugins().iter().map(|p| CapabilityDescription{name: p.name().to_string(), description: p.description().to_string(), category: p.category().to_string(), operations: p.supported_operations()}).collect(); Json(DiscoveryResponse { provider, mode: "active".to_string(), capabilities: caps, referrals }) }
-pub async fn process_mcp_message( message: &str, registry: &Arc<PluginRegistry>, provider_info: &Arc<Value>, ) -> Option<String> { match serde_json::from_str::<MCPRequest>(message) { Ok(req) => { let span=tracing::info_span!("process_mcp_req",id=%req.id,method=%req.method); let _e=span.enter(); info!("Processing"); match req.method.as_str() { "initialize" => Some(handle_initialize(&req, registry, provider_info)), "resources/list" => Some(handle_list_resources(&req, registry, provider_info)), "resources/read" => Some(handle_read_resource(&req, registry)), "tools/list" => Some(handle_list_tools(&req, registry)), "tools/call" => Some(handle_call_tool(&req, registry)), "completions" => Some(handle_completions(&req, registry)), "ping" => Some(handle_ping(&req)), _ => { warn!("Method not found: {}", req.method); Some(create_error_response(req.id, -32601, format!("Method not found: {}", req.method))) } } } Err(e) => { error!("Parse error: {}", e); Some(create_error_response(Value::Null, -32700, format!("Parse error: {}", e))) } } }
-fn handle_initialize(_request: &MCPRequest, registry: &Arc<PluginRegistry>, provider_info: &Arc<Value>) -> String { let caps=ServerCapabilities{ resources:Some(ResourcesCapability{list_changed:true,subscribe:true}), tools:Some(ToolsCapability{list_changed:true}), prompts:None, logging:None, completions: None, experimental: None }; let name=provider_info.get("name").and_then(|v|v.as_str()).unwrap_or("").to_string(); let desc=provider_info.get("description").and_then(|v|v.as_str()).unwrap_or("").to_string(); let _names=registry.get_all_plugins().iter().map(|p|p.name()).collect::<Vec<_>>(); let result = InitializeResult { server_info: mcpi_common::Implementation { name, version: MCPI_VERSION.to_string() }, protocol_version: LATEST_MCP_VERSION.to_string(), capabilities: caps, instructions: Some(format!("Provider: {}", desc)), _meta: None, }; json!({"jsonrpc":"2.0","id":_request.id, "result": result }).to_string() }
-fn handle_list_resources(_request: &MCPRequest, registry: &Arc<PluginRegistry>, provider_info: &Arc<Value>) -> String { let domain=provider_info.get("domain").and_then(|d|d.as_str()).unwrap_or("example.com"); let resources=registry.get_all_plugins().iter().flat_map(|p|p.get_resources().into_iter().map(|(n,s,d)|Resource{ name:n, description:d, uri:format!("mcpi://{}/resources/{}/{}",domain,p.name(),s), mime_type:Some("application/json".into()), annotations: None })).collect::<Vec<_>>(); let result = ListResourcesResult { resources, next_cursor: None, _meta: None, }; json!({"jsonrpc":"2.0","id":_request.id, "result": result }).to_string() }
-fn handle_read_resource(request: &MCPRequest, registry: &Arc<PluginRegistry>) -> String { if let Some(u)=request.params.as_ref().and_then(|p|p.get("uri")?.as_str()){if let Ok(uri)=Url::parse(u){if uri.scheme()=="mcpi"{let path:Vec<&str>=uri.path_segments().map(|i|i.collect()).unwrap_or_default();if path.len()>=3&&path[0]=="resources"{let(p_name,r_suffix)=(path[1],path[2..].join("/"));if let Some(p)=registry.get_plugin(p_name){match p.read_resource(&r_suffix){Ok(content_item)=>{let resource_content=match content_item{ContentItem::Text{text,..}=>{ResourceContentUnion::Text(TextResourceContents{uri:u.to_string(),mime_type:Some("text/plain".to_string()),text,})},_=>{warn!("Cannot format ContentItem {:?} as ResourceContentUnion",content_item);return create_error_response(request.id.clone(),101,"Internal error".to_string());}}; let result=ReadResourceResult{contents:vec![resource_content], _meta: None}; return json!({"jsonrpc":"2.0","id":request.id,"result":result}).to_string();},Err(e)=>{warn!("Read err: {}",e);return create_error_response(request.id.clone(),100,format!("Read err: {}",e));}}}else{warn!("Plugin not found: {}",p_name);}}else{warn!("Invalid path: {}",uri.path());}}else{warn!("Invalid scheme: {}",uri.scheme());}}else{warn!("Invalid URI: {}",u);}}else{warn!("Missing URI");} create_error_response(request.id.clone(),-32602,"Invalid params".into()) }
-fn handle_list_tools(_request: &MCPRequest, registry: &Arc<PluginRegistry>) -> String { let tools=registry.get_all_plugins().iter().map(|p|Tool{name:p.name().into(),description:Some(p.description().into()),input_schema:p.input_schema(),annotations:p.get_tool_annotations()}).collect::<Vec<_>>(); let result = ListToolsResult { tools, next_cursor: None, _meta: None, }; json!({"jsonrpc":"2.0","id":_request.id, "result": result }).to_string() }
-fn handle_call_tool(request: &MCPRequest, registry: &Arc<PluginRegistry>) -> String { if let Some(p)=request.params.as_ref().and_then(|p|p.as_object()){if let(Some(name),Some(args))=(p.get("name").and_then(|n|n.as_str()),p.get("arguments")){let op=args.get("operation").and_then(|o|o.as_str()).unwrap_or("DEFAULT");match registry.execute_plugin(name,op,args){Ok(res)=>{let c=match res{Value::String(s)=>vec![ContentItem::Text{text:s,annotations:None}],Value::Null=>vec![],_=>vec![ContentItem::Text{text:serde_json::to_string_pretty(&res).unwrap_or_else(|_|res.to_string()),annotations:None}]};let result=CallToolResult{content:c,is_error:false,_meta:None};return json!({"jsonrpc":"2.0","id":request.id,"result":result}).to_string();},Err(e)=>{let ec=vec![ContentItem::Text{text:format!("Exec err: {}",e),annotations:None}];let result=CallToolResult{content:ec,is_error:true,_meta:None};return json!({"jsonrpc":"2.0","id":request.id,"result":result}).to_string();}}}} create_error_response(request.id.clone(),-32602,"Invalid params".into()) }
-fn handle_completions(_request: &MCPRequest, registry: &Arc<PluginRegistry>) -> String { let params:Result<mcpi_common::CompleteRequestParams,_>=_request.params.clone().map_or_else(||Err("Missing params".to_string()),|p|serde_json::from_value(p).map_err(|e|e.to_string()));match params{Ok(comp_params)=>{let param_name=&comp_params.argument.name;let partial_value=&comp_params.argument.value;let tool_name_context=comp_params.context.as_ref().and_then(|ctx|ctx.get("name")).and_then(|v|v.as_str());if param_name=="name"&&tool_name_context.is_none(){let tool_names:Vec<String>=registry.get_all_plugins().iter().map(|p|p.name().to_string()).filter(|n|n.starts_with(partial_value)).collect();let result=CompleteResult{completion:mcpi_common::CompleteResultCompletion{values:tool_names,total:None,has_more:None,},_meta:None,};return json!({"jsonrpc":"2.0","id":_request.id,"result":result}).to_string();}else if let Some(tool_name)=tool_name_context{if let Some(plugin)=registry.get_plugin(tool_name){let partial_value_json=Value::String(partial_value.clone());let context_json=serde_json::to_value(&comp_params.context).unwrap_or(Value::Null);let sugg_values=plugin.get_completions(param_name,&partial_value_json,&context_json);let string_suggestions=sugg_values.into_iter().filter_map(|v|v.as_str().map(String::from)).collect();let result=CompleteResult{completion:mcpi_common::CompleteResultCompletion{values:string_suggestions,total:None,has_more:None,},_meta:None,};return json!({"jsonrpc":"2.0","id":_request.id,"result":result}).to_string();}}let result=CompleteResult{completion:mcpi_common::CompleteResultCompletion{values:vec![],total:None,has_more:None},_meta:None};json!({"jsonrpc":"2.0","id":_request.id,"result":result}).to_string()}Err(e)=>{warn!("Invalid params for completions: {}",e);create_error_response(_request.id.clone(),-32602,format!("Invalid params: {}",e))}}}
-fn handle_ping(_request: &MCPRequest) -> String { let result = EmptyResult { _meta: None }; json!({"jsonrpc":"2.0","id":_request.id,"result":result}).to_string() }
-fn create_error_response(id: Value, code: i32, message: String) -> String { json!({"jsonrpc":"2.0","id":id,"error":{"code":code,"message":message}}).to_string() }
-fn validate_paths() -> Result<(), Box<dyn Error + Send + Sync>> { let c=Path::new(CONFIG_FILE_PATH); let d=Path::new(DATA_PATH); if !c.exists(){return Err(format!("Config file missing: {}",CONFIG_FILE_PATH).into());} if !d.exists(){return Err(format!("Data dir missing: {}",DATA_PATH).into());} Ok(()) }
-fn load_config() -> Result<Value, Box<dyn Error + Send + Sync>> { let d=fs::read_to_string(CONFIG_FILE_PATH)?; serde_json::from_str(&d).map_err(|e|e.into()) }
\ No newline at end of file
+
+// --- Other Handlers (Discovery) ---
+async fn discovery_handler(State(state): State<Arc<AppState>>) -> Json<DiscoveryResponse> { state.request_count.fetch_add(1, Ordering::SeqCst); info!("Handling /mcpi/discover request"); let snapshot = state.snapshot.get(); let provider_data = &snapshot.provider; let provider = mcpi_common::Provider { name: provider_data.info.get("name").and_then(|n|n.as_str()).unwrap_or("").to_string(), domain: provider_data.info.get("domain").and_then(|d|d.as_str()).unwrap_or("").to_string(), description: provider_data.info.get("description").and_then(|d|d.as_str()).unwrap_or("").to_string(), branding: None }; let referrals = if let Some(refs) = provider_data.referrals.as_array() { refs.iter().filter_map(|r| Some(Referral{name: r.get("name")?.as_str()?.to_string(), domain: r.get("domain")?.as_str()?.to_string(), relationship: r.get("relationship")?.as_str()?.to_string() })).collect() } else { vec![] }; let caps = snapshot.registry.get_all_plugins().iter().map(|p| CapabilityDescription{name: p.name().to_string(), description: p.description().to_string(), category: p.category().to_string(), operations: p.supported_operations()}).collect(); Json(DiscoveryResponse { provider, mode: "active".to_string(), capabilities: caps, referrals }) }
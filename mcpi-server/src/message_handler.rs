@@ -1,116 +1,351 @@
 // mcpi-server/src/message_handler.rs
 
 use crate::traits::MessageHandler;
-use crate::plugin_registry::PluginRegistry; // Import PluginRegistry
-use mcpi_common::MCPRequest;
+use crate::reload::{Reloadable, Snapshot};
+use crate::interceptor::{Interceptor, SessionContext};
+use crate::limits::{Limits, LimitsConfig};
+use crate::methods;
+use crate::metrics::TenantMetrics;
+use crate::router::{EventSender, MethodRouter, RequestContext};
+use crate::shutdown::Drain;
+use futures::stream::{self, StreamExt};
+use mcpi_common::{
+    MCPError, MCPRequest, MCPResponse, INTERNAL_ERROR, INVALID_PARAMS, INVALID_REQUEST, LATEST_MCP_VERSION, PARSE_ERROR,
+};
 use serde_json::{json, Value}; // Value needed for provider_info
+use std::collections::HashMap;
 use std::future::Future;
 use std::ops::Deref;
 use std::pin::Pin;
-use std::sync::Arc;
-use tracing::{error, info};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+use tracing::{error, info, warn};
+
+/// Protocol versions in which JSON-RPC batching is part of the spec.
+/// Batching was introduced in 2025-03-26 and is not available to clients
+/// that negotiated an older version.
+const BATCHING_PROTOCOL_VERSIONS: &[&str] = &["2025-03-26"];
+
+// A client's `initialize` handshake
+struct InitializedClient {
+    protocol_version: String,
+    client_info: Option<Value>,
+}
 
 pub struct McpMessageHandler {
-    // Store only the parts needed
-    registry: Arc<PluginRegistry>,
-    provider_info: Arc<Value>, // Add provider_info state
+    // Provider info, referrals and plugins, replaced together on reload
+    snapshot: Arc<Reloadable<Snapshot>>,
+    router: MethodRouter,
+    interceptors: Vec<Arc<dyn Interceptor>>,
+    // What each client sent and negotiated during `initialize`
+    initialized: RwLock<HashMap<String, InitializedClient>>,
+    // Requests in flight, which shutdown waits for
+    drain: Arc<Drain>,
+    limits: Arc<Limits>,
+    metrics: Option<TenantMetrics>,
 }
 
 impl McpMessageHandler {
-    // Expect Arc<PluginRegistry> and Arc<Value>
-    pub fn new(registry: Arc<PluginRegistry>, provider_info: Arc<Value>) -> Self {
-        Self { registry, provider_info }
+    // Built-in MCP methods are registered here; plugin-provided methods are resolved per call
+    pub fn new(snapshot: Arc<Reloadable<Snapshot>>) -> Self {
+        let mut router = MethodRouter::new();
+        methods::register_builtin_methods(&mut router);
+
+        Self {
+            snapshot,
+            router,
+            interceptors: Vec::new(),
+            initialized: RwLock::new(HashMap::new()),
+            drain: Arc::new(Drain::new()),
+            limits: Arc::new(Limits::new(LimitsConfig::default())),
+            metrics: None,
+        }
     }
 
-    // Helper function to process a batch of messages
-    async fn process_batch(&self, messages: Vec<Value>, client_id: &str) -> Option<String> {
-        info!("Processing batch of {} messages from client {}", messages.len(), client_id);
-        let mut responses = Vec::new();
-        // Clone the necessary state Arc(s) for async usage
-        let registry = self.registry.clone(); // Use original variable name
-        let provider_info = self.provider_info.clone(); // Clone provider_info
-
-        for message in messages {
-            let message_id = message.get("id").cloned().unwrap_or(Value::Null);
-
-            if message_id.is_null() { // Notification
-                if let Ok(request) = serde_json::from_value::<MCPRequest>(message.clone()) {
-                   match serde_json::to_string(&request) {
-                       Ok(request_str) => {
-                           // Pass needed state Arcs
-                           let _ = crate::process_mcp_message(&request_str, &registry, &provider_info).await; // Pass both
-                       },
-                       Err(e) => error!("Failed to re-serialize notification: {}", e),
-                   }
-                } else { error!("Failed to parse potential notification: {:?}", message); }
-                continue;
+    /// Share in-flight request tracking with other handlers, e.g. of other tenants
+    pub fn with_drain(mut self, drain: Arc<Drain>) -> Self {
+        self.drain = drain;
+        self
+    }
+
+    /// Replace the default limits on incoming messages
+    pub fn with_limits(mut self, config: LimitsConfig) -> Self {
+        self.limits = Arc::new(Limits::new(config));
+        self
+    }
+
+    /// Record request, plugin call and message size metrics
+    pub fn with_metrics(mut self, metrics: TenantMetrics) -> Self {
+        self.metrics = Some(metrics.clone());
+        self.with_interceptor(Arc::new(metrics))
+    }
+
+    /// Append an interceptor to the chain that wraps every dispatched request
+    pub fn with_interceptor(mut self, interceptor: Arc<dyn Interceptor>) -> Self {
+        info!("Registered interceptor: {}", interceptor.name());
+        self.interceptors.push(interceptor);
+        self
+    }
+
+    /// Access the method router
+    pub fn router(&self) -> &MethodRouter {
+        &self.router
+    }
+
+    /// Access the method router to register, override or extend methods
+    #[allow(dead_code)] // Extension point for embedders
+    pub fn router_mut(&mut self) -> &mut MethodRouter {
+        &mut self.router
+    }
+
+    /// Get the protocol version negotiated by a client, if it has initialized
+    pub fn negotiated_version(&self, client_id: &str) -> Option<String> {
+        self.initialized.read().unwrap().get(client_id).map(|client| client.protocol_version.clone())
+    }
+
+    /// Get the `clientInfo` a client sent with `initialize`, if any
+    pub fn client_info(&self, client_id: &str) -> Option<Value> {
+        self.initialized.read().unwrap().get(client_id).and_then(|client| client.client_info.clone())
+    }
+
+    /// Limits on incoming messages and how often they were hit
+    pub fn limits(&self) -> &Arc<Limits> {
+        &self.limits
+    }
+
+    /// Drop per-client state once a connection or session has ended
+    pub fn forget_client(&self, client_id: &str) {
+        self.initialized.write().unwrap().remove(client_id);
+    }
+
+    fn batching_allowed(&self, client_id: &str) -> Result<(), String> {
+        let version = self.negotiated_version(client_id).unwrap_or_else(|| LATEST_MCP_VERSION.to_string());
+        if BATCHING_PROTOCOL_VERSIONS.contains(&version.as_str()) {
+            Ok(())
+        } else {
+            Err(version)
+        }
+    }
+
+    // Process one JSON-RPC message (a single request or one batch item).
+    // Returns None for notifications, which never get a response.
+    async fn process_item(&self, mut message: Value, client_id: &str, in_batch: bool, events: Option<&EventSender>) -> Option<String> {
+        let Some(object) = message.as_object_mut() else {
+            return Some(error_response(Value::Null, INVALID_REQUEST, "Invalid Request: expected a JSON object"));
+        };
+
+        let has_id = object.contains_key("id");
+        // `MCPRequest` requires an id, which a notification leaves out
+        let message_id = object.entry("id").or_insert(Value::Null).clone();
+
+        // Malformed messages get an error even without an id; only a valid
+        // request can be a notification
+        let request = match serde_json::from_value::<MCPRequest>(message) {
+            Ok(request) => request,
+            Err(e) => {
+                warn!("Invalid request from {}: {}", client_id, e);
+                return Some(error_response(message_id, INVALID_REQUEST, &format!("Invalid Request: {}", e)));
+            }
+        };
+        if request.jsonrpc != "2.0" {
+            return Some(error_response(message_id, INVALID_REQUEST, "Invalid Request: jsonrpc must be \"2.0\""));
+        }
+
+        // Per JSON-RPC 2.0 a notification is a request without an "id" member
+        if !has_id {
+            info!("Received notification '{}' from client {}", request.method, client_id);
+            // Notifications are processed for their side effects only
+            if let Err(e) = self.dispatch(request, client_id, true, in_batch, events).await {
+                warn!("Notification from {} failed: {}", client_id, e.message);
             }
+            return None;
+        }
 
-            // Request
-            if let Ok(request) = serde_json::from_value::<MCPRequest>(message.clone()) {
-                match serde_json::to_string(&request) {
-                    Ok(request_str) => {
-                        // Pass needed state Arcs
-                        if let Some(response_str) = crate::process_mcp_message(&request_str, &registry, &provider_info).await { // Pass both
-                            match serde_json::from_str::<Value>(&response_str) {
-                                Ok(response_json) => responses.push(response_json),
-                                Err(e) => error!("Failed to parse response string: {}", e),
-                            }
-                        } else {
-                            info!("process_mcp_message returned None for request ID: {}", message_id);
-                             responses.push(json!({ "jsonrpc": "2.0", "id": message_id, "error": { "code": -32603, "message": "Internal server error" } }));
-                        }
-                    },
-                    Err(e) => {
-                        error!("Failed to serialize valid MCPRequest: {}", e);
-                         responses.push(json!({ "jsonrpc": "2.0", "id": message_id, "error": { "code": -32603, "message": "Internal server error" } }));
-                    }
-                }
-            } else { // Parse error for request
-                responses.push(json!({ "jsonrpc": "2.0", "id": message_id, "error": { "code": -32700, "message": "Parse error: Invalid MCPRequest" } }));
+        if !(request.id.is_string() || request.id.is_number()) {
+            return Some(error_response(Value::Null, INVALID_REQUEST, "Invalid Request: id must be a string or number"));
+        }
+        if in_batch && request.method == "initialize" {
+            return Some(error_response(message_id, INVALID_REQUEST, "Invalid Request: initialize must not be part of a batch"));
+        }
+        // Interceptors may rewrite the request, but the response always answers the original id
+        let client_info = (request.method == "initialize").then(|| request.params.as_ref().and_then(|p| p.get("clientInfo")).cloned());
+        let outcome = self.dispatch(request, client_id, false, in_batch, events).await;
+        if let (Ok(result), Some(client_info)) = (&outcome, client_info) {
+            self.record_initialize(client_id, client_info, result);
+        }
+        let response = match outcome {
+            Ok(result) => MCPResponse { jsonrpc: "2.0".to_string(), id: message_id, result: Some(result), error: None },
+            Err(error) => MCPResponse { jsonrpc: "2.0".to_string(), id: message_id, result: None, error: Some(error) },
+        };
+        match serde_json::to_string(&response) {
+            Ok(response) => Some(response),
+            Err(e) => {
+                error!("Failed to serialize response: {}", e);
+                Some(error_response(response.id, INTERNAL_ERROR, "Internal server error"))
+            }
+        }
+    }
+
+    // Run the interceptor chain around the router
+    async fn dispatch(
+        &self,
+        mut request: MCPRequest,
+        client_id: &str,
+        is_notification: bool,
+        in_batch: bool,
+        events: Option<&EventSender>,
+    ) -> Result<Value, MCPError> {
+        let session = SessionContext {
+            client_id: client_id.to_string(),
+            protocol_version: self.negotiated_version(client_id),
+            client_info: self.client_info(client_id),
+            is_notification,
+            in_batch,
+            received_at: Instant::now(),
+        };
+
+        let mut entered = 0;
+        let mut short_circuit = None;
+        for interceptor in &self.interceptors {
+            entered += 1;
+            if let Err(e) = interceptor.before(&session, &mut request).await {
+                info!("Interceptor '{}' rejected '{}' from {}", interceptor.name(), request.method, client_id);
+                short_circuit = Some(e);
+                break;
+            }
+        }
+
+        // Limit rejections are checked inside the chain, so interceptors such as
+        // the audit log still see them
+        if short_circuit.is_none() && request.method == "tools/call" {
+            if let Err(e) = self.limits.check_tool_arguments(&request) {
+                warn!("Rejecting tools/call from {}: {}", client_id, e);
+                short_circuit = Some(MCPError::new(INVALID_PARAMS, format!("Invalid params: {}", e)));
+            }
+        }
+
+        let mut outcome = match short_circuit {
+            Some(e) => Err(e),
+            None => {
+                let snapshot = self.snapshot.get();
+                let ctx = RequestContext {
+                    client_id: client_id.to_string(),
+                    registry: snapshot.registry.clone(),
+                    provider_info: snapshot.provider.info.clone(),
+                    progress_token: progress_token(&request),
+                    events: events.cloned(),
+                };
+                self.router.dispatch(ctx, &request).await
             }
+        };
+
+        for interceptor in self.interceptors[..entered].iter().rev() {
+            interceptor.after(&session, &request, &mut outcome).await;
         }
+        outcome
+    }
 
-        if responses.is_empty() { return None; }
+    fn record_initialize(&self, client_id: &str, client_info: Option<Value>, result: &Value) {
+        if let Some(version) = result.get("protocolVersion").and_then(|v| v.as_str()) {
+            info!("Client {} negotiated protocol version {}", client_id, version);
+            let client = InitializedClient { protocol_version: version.to_string(), client_info };
+            self.initialized.write().unwrap().insert(client_id.to_string(), client);
+        }
+    }
+
+    // Helper function to process a batch of messages
+    async fn process_batch(&self, messages: Vec<Value>, client_id: &str, events: Option<&EventSender>) -> Option<String> {
+        info!("Processing batch of {} messages from client {}", messages.len(), client_id);
+
+        if messages.is_empty() {
+            return Some(error_response(Value::Null, INVALID_REQUEST, "Invalid Request: empty batch"));
+        }
+        if let Err(version) = self.batching_allowed(client_id) {
+            warn!("Rejecting batch from {}: not supported by protocol version {}", client_id, version);
+            return Some(error_response(
+                Value::Null,
+                INVALID_REQUEST,
+                &format!("Invalid Request: batching is not supported in protocol version {}", version),
+            ));
+        }
+
+        // `buffered` keeps at most `limits.batch_concurrency` items in flight and
+        // yields their results in the original order
+        let responses: Vec<String> = stream::iter(messages)
+            .map(|message| self.process_item(message, client_id, true, events))
+            .buffered(self.limits.config().batch_concurrency)
+            .filter_map(|response| async move { response })
+            .collect()
+            .await;
+
+        // A batch made only of notifications gets no response at all
+        if responses.is_empty() {
+            return None;
+        }
+
+        // Each item is already serialized JSON, so join them without re-parsing
+        Some(format!("[{}]", responses.join(",")))
+    }
 
-        match serde_json::to_string(&responses) {
-            Ok(batch_response) => Some(batch_response),
+    /// Handle a message, streaming any messages related to its requests
+    /// (progress, logs, server-to-client requests) to `events` as they happen.
+    /// The final response is returned as usual and is not sent to `events`.
+    pub async fn handle_message_with_events(&self, message: String, client_id: String, events: EventSender) -> Option<String> {
+        self.handle(message, &client_id, Some(&events)).await
+    }
+
+    async fn handle(&self, message: String, client_id: &str, events: Option<&EventSender>) -> Option<String> {
+        let _in_flight = self.drain.track();
+        if let Some(metrics) = &self.metrics {
+            metrics.record_message_size("in", message.len());
+        }
+        let response = self.dispatch_message(message, client_id, events).await;
+        if let (Some(metrics), Some(response)) = (&self.metrics, &response) {
+            metrics.record_message_size("out", response.len());
+        }
+        response
+    }
+
+    async fn dispatch_message(&self, message: String, client_id: &str, events: Option<&EventSender>) -> Option<String> {
+        if let Err(e) = self.limits.check_message(&message) {
+            warn!("Rejecting message from {}: {}", client_id, e);
+            return Some(error_response(Value::Null, INVALID_REQUEST, &format!("Invalid Request: {}", e)));
+        }
+        match serde_json::from_str::<Value>(&message) {
+            Ok(Value::Array(batch)) => {
+                if let Err(e) = self.limits.check_batch(batch.len()) {
+                    warn!("Rejecting batch from {}: {}", client_id, e);
+                    return Some(error_response(Value::Null, INVALID_REQUEST, &format!("Invalid Request: {}", e)));
+                }
+                self.process_batch(batch, client_id, events).await
+            }
+            Ok(single) => {
+                info!("Processing single message from client {}", client_id);
+                self.process_item(single, client_id, false, events).await
+            }
             Err(e) => {
-                error!("Failed to serialize batch response: {}", e);
-                Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32603, "message": "Internal server error" } }).to_string())
+                error!("Invalid JSON from {}: {}", client_id, e);
+                Some(error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e)))
             }
         }
     }
 }
 
+// Progress token supplied by the client in `params._meta.progressToken`
+fn progress_token(request: &MCPRequest) -> Option<Value> {
+    request.params.as_ref()?.get("_meta")?.get("progressToken").cloned()
+}
+
+fn error_response(id: Value, code: i32, message: &str) -> String {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } }).to_string()
+}
+
 // Implementation for the struct itself
 impl MessageHandler for McpMessageHandler {
     fn handle_message<'a>(&'a self, message: String, client_id: String)
         -> Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>> {
 
-        Box::pin(async move {
-            let trimmed_message = message.trim();
-            // Clone necessary state Arc(s) for async usage
-            let registry = self.registry.clone(); // Use original variable name
-            let provider_info = self.provider_info.clone(); // Clone provider_info
-
-            if trimmed_message.starts_with('[') && trimmed_message.ends_with(']') {
-                match serde_json::from_str::<Vec<Value>>(&message) {
-                    Ok(batch) => self.process_batch(batch, &client_id).await,
-                    Err(e) => {
-                        error!("Invalid batch request from {}: {}", client_id, e);
-                        Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "Parse error: Invalid batch" } }).to_string())
-                    }
-                }
-            } else if trimmed_message.starts_with('{') && trimmed_message.ends_with('}') {
-                 info!("Processing single message from client {}", client_id);
-                 // Pass needed state Arcs
-                 crate::process_mcp_message(&message, &registry, &provider_info).await // Pass both
-            } else {
-                error!("Invalid message format from {}: {}", client_id, message);
-                Some(json!({ "jsonrpc": "2.0", "id": null, "error": { "code": -32700, "message": "Parse error: Invalid JSON" } }).to_string())
-            }
-        })
+        Box::pin(async move { self.handle(message, &client_id, None).await })
     }
 }
 
@@ -120,4 +355,191 @@ impl MessageHandler for Arc<McpMessageHandler> {
         -> Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>> {
         self.deref().handle_message(message, client_id)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plugin_registry::PluginRegistry;
+    use crate::reload::Provider;
+
+    fn snapshot(registry: Arc<PluginRegistry>, info: Value) -> Arc<Reloadable<Snapshot>> {
+        let provider = Provider { info: Arc::new(info), referrals: Arc::new(json!([])) };
+        Arc::new(Reloadable::new(Snapshot { provider, registry }))
+    }
+
+    fn handler() -> McpMessageHandler {
+        McpMessageHandler::new(snapshot(Arc::new(PluginRegistry::new()), json!({"name": "Test"})))
+    }
+
+    async fn send(handler: &McpMessageHandler, message: Value) -> Option<Value> {
+        handler
+            .handle_message(message.to_string(), "test-client".to_string())
+            .await
+            .map(|r| serde_json::from_str(&r).unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_empty_batch_is_invalid_request() {
+        let response = send(&handler(), json!([])).await.unwrap();
+        assert!(response.is_object());
+        assert_eq!(response["error"]["code"], -32600);
+    }
+
+    #[tokio::test]
+    async fn test_batch_preserves_order_and_skips_notifications() {
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": "a", "method": "ping" },
+            { "jsonrpc": "2.0", "method": "notifications/initialized" },
+            { "jsonrpc": "2.0", "id": 2, "method": "unknown/method" },
+            { "jsonrpc": "2.0", "id": 3, "method": "ping" }
+        ]);
+        let handler = handler().with_limits(LimitsConfig { batch_concurrency: 2, ..LimitsConfig::default() });
+        let response = send(&handler, batch).await.unwrap();
+        let items = response.as_array().unwrap();
+        let ids: Vec<Value> = items.iter().map(|r| r["id"].clone()).collect();
+        assert_eq!(ids, vec![json!("a"), json!(2), json!(3)]);
+        assert_eq!(items[1]["error"]["code"], -32601);
+    }
+
+    #[tokio::test]
+    async fn test_invalid_batch_items_get_errors() {
+        let batch = json!([1, { "jsonrpc": "2.0", "id": 5 }, { "jsonrpc": "2.0", "id": null, "method": "ping" }]);
+        let response = send(&handler(), batch).await.unwrap();
+        let items = response.as_array().unwrap();
+        assert_eq!(items.len(), 3);
+        assert!(items.iter().all(|r| r["error"]["code"] == -32600));
+        assert_eq!(items[1]["id"], 5);
+    }
+
+    #[tokio::test]
+    async fn test_notification_only_batch_has_no_response() {
+        let batch = json!([{ "jsonrpc": "2.0", "method": "notifications/initialized" }]);
+        assert!(send(&handler(), batch).await.is_none());
+        let single = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(send(&handler(), single).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_invalid_request_without_id_gets_error() {
+        for message in [json!({ "jsonrpc": "2.0", "method": 1 }), json!({ "jsonrpc": "1.0", "method": "ping" })] {
+            let response = send(&handler(), message).await.unwrap();
+            assert_eq!(response["error"]["code"], -32600);
+            assert!(response["id"].is_null());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_builtin_methods_can_be_overridden() {
+        let mut handler = handler();
+        handler.router_mut().register("ping", |_ctx, _params: Value| async { Ok(json!({ "pong": true })) });
+        let response = send(&handler, json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })).await.unwrap();
+        assert_eq!(response["result"]["pong"], true);
+
+        let response = send(&handler, json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/call", "params": {} })).await.unwrap();
+        assert_eq!(response["error"]["code"], -32602);
+    }
+
+    #[tokio::test]
+    async fn test_plugin_methods_follow_registry_changes() {
+        let registry = Arc::new(PluginRegistry::new());
+        let handler = McpMessageHandler::new(snapshot(registry.clone(), json!({})));
+        let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "mcpi/hello", "params": { "detail_level": "basic" } });
+        let response = send(&handler, request.clone()).await.unwrap();
+        assert_eq!(response["error"]["code"], -32601);
+
+        // A plugin registered after the handler was built is routed, as after a reload
+        registry.register_plugin(crate::plugins::hello::create_plugin("missing-data-dir").unwrap()).unwrap();
+        assert!(handler.router().methods(&registry).contains(&"mcpi/hello".to_string()));
+        let response = send(&handler, request).await.unwrap();
+        assert!(response["result"]["content"].is_array());
+    }
+
+    struct RejectTools;
+
+    impl Interceptor for RejectTools {
+        fn name(&self) -> &str {
+            "reject-tools"
+        }
+
+        fn before<'a>(&'a self, _ctx: &'a SessionContext, request: &'a mut MCPRequest) -> crate::interceptor::InterceptorFuture<'a, Result<(), MCPError>> {
+            Box::pin(async move {
+                if request.method.starts_with("tools/") {
+                    return Err(MCPError::new(-32001, "Forbidden"));
+                }
+                // Rewrite a legacy method name before dispatch
+                if request.method == "legacy/ping" {
+                    request.method = "ping".to_string();
+                }
+                Ok(())
+            })
+        }
+
+        fn after<'a>(&'a self, _ctx: &'a SessionContext, _request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> crate::interceptor::InterceptorFuture<'a, ()> {
+            Box::pin(async move {
+                if let Ok(Value::Object(result)) = outcome {
+                    result.insert("intercepted".to_string(), json!(true));
+                }
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_interceptors_apply_to_single_and_batch_requests() {
+        let handler = handler().with_interceptor(Arc::new(RejectTools));
+        let response = send(&handler, json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" })).await.unwrap();
+        assert_eq!(response["error"]["code"], -32001);
+
+        let batch = json!([
+            { "jsonrpc": "2.0", "id": 1, "method": "legacy/ping" },
+            { "jsonrpc": "2.0", "id": 2, "method": "tools/list" }
+        ]);
+        let response = send(&handler, batch).await.unwrap();
+        assert_eq!(response[0]["id"], 1);
+        assert_eq!(response[0]["result"]["intercepted"], true);
+        assert_eq!(response[1]["error"]["code"], -32001);
+    }
+
+    struct RecordOutcomes(std::sync::Mutex<Vec<Result<Value, MCPError>>>);
+
+    impl Interceptor for RecordOutcomes {
+        fn name(&self) -> &str {
+            "record-outcomes"
+        }
+
+        fn after<'a>(&'a self, _ctx: &'a SessionContext, _request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> crate::interceptor::InterceptorFuture<'a, ()> {
+            Box::pin(async move { self.0.lock().unwrap().push(outcome.clone()) })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limit_rejections_reach_interceptors() {
+        let outcomes = Arc::new(RecordOutcomes(std::sync::Mutex::new(Vec::new())));
+        let handler = handler()
+            .with_limits(LimitsConfig { max_string_bytes: 4, ..LimitsConfig::default() })
+            .with_interceptor(outcomes.clone());
+        let request = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": { "name": "t", "arguments": { "q": "too long" } }
+        });
+        let response = send(&handler, request).await.unwrap();
+        assert_eq!(response["error"]["code"], INVALID_PARAMS);
+        let outcomes = outcomes.0.lock().unwrap();
+        assert!(matches!(&outcomes[..], [Err(e)] if e.code == INVALID_PARAMS));
+    }
+
+    #[tokio::test]
+    async fn test_batching_rejected_for_older_protocol_version() {
+        let handler = handler();
+        let init = json!({
+            "jsonrpc": "2.0", "id": 1, "method": "initialize",
+            "params": { "protocolVersion": "2024-11-05", "capabilities": {}, "clientInfo": { "name": "t", "version": "1" } }
+        });
+        let response = send(&handler, init).await.unwrap();
+        assert_eq!(response["result"]["protocolVersion"], "2024-11-05");
+
+        let batch = json!([{ "jsonrpc": "2.0", "id": 2, "method": "ping" }]);
+        let response = send(&handler, batch).await.unwrap();
+        assert_eq!(response["error"]["code"], -32600);
+    }
+}
@@ -0,0 +1,214 @@
+// mcpi-server/src/methods.rs
+//
+// Built-in MCP methods, registered with the `MethodRouter` like any other handler.
+
+use crate::router::{MethodRouter, RequestContext};
+use mcpi_common::{
+    CallToolRequestParams, CallToolResult, CompleteRequestParams, CompleteResult,
+    CompleteResultCompletion, ContentItem, EmptyResult, Implementation, InitializeParams,
+    InitializeResult, ListResourcesResult, ListToolsResult, MCPError, ReadResourceRequestParams,
+    ReadResourceResult, Resource, ResourceContentUnion, ResourcesCapability, ServerCapabilities,
+    TextResourceContents, Tool, ToolsCapability, INVALID_PARAMS,
+    LATEST_MCP_VERSION, MCPI_VERSION, SUPPORTED_MCP_VERSIONS,
+};
+use serde_json::{json, Value};
+use tracing::warn;
+use url::Url;
+
+/// Register every built-in MCP method with the router
+pub fn register_builtin_methods(router: &mut MethodRouter) {
+    router
+        .register("initialize", initialize)
+        .register("ping", ping)
+        .register("resources/list", list_resources)
+        .register("resources/read", read_resource)
+        .register("tools/list", list_tools)
+        .register("tools/call", call_tool)
+        .register("completions", completions)
+        .register("notifications/initialized", initialized);
+}
+
+fn provider_str<'a>(ctx: &'a RequestContext, key: &str) -> Option<&'a str> {
+    ctx.provider_info.get(key).and_then(|v| v.as_str())
+}
+
+/// Pick the protocol version for a session: the client's requested version if
+/// we support it, otherwise the latest version we speak.
+fn negotiate_protocol_version(requested: &str) -> String {
+    if SUPPORTED_MCP_VERSIONS.contains(&requested) {
+        requested.to_string()
+    } else {
+        LATEST_MCP_VERSION.to_string()
+    }
+}
+
+async fn initialize(ctx: RequestContext, params: InitializeParams) -> Result<InitializeResult, MCPError> {
+    let capabilities = ServerCapabilities {
+        resources: Some(ResourcesCapability { list_changed: true, subscribe: true }),
+        tools: Some(ToolsCapability { list_changed: true }),
+        prompts: None,
+        logging: None,
+        completions: None,
+        experimental: None,
+    };
+    let name = provider_str(&ctx, "name").unwrap_or("").to_string();
+    let description = provider_str(&ctx, "description").unwrap_or("");
+
+    Ok(InitializeResult {
+        server_info: Implementation { name, version: MCPI_VERSION.to_string() },
+        protocol_version: negotiate_protocol_version(&params.protocol_version),
+        capabilities,
+        instructions: Some(format!("Provider: {}", description)),
+        _meta: None,
+    })
+}
+
+async fn initialized(_ctx: RequestContext, _params: Value) -> Result<EmptyResult, MCPError> {
+    Ok(EmptyResult { _meta: None })
+}
+
+async fn ping(_ctx: RequestContext, _params: Value) -> Result<EmptyResult, MCPError> {
+    Ok(EmptyResult { _meta: None })
+}
+
+async fn list_resources(ctx: RequestContext, _params: Value) -> Result<ListResourcesResult, MCPError> {
+    let domain = provider_str(&ctx, "domain").unwrap_or("example.com");
+    let resources = ctx
+        .registry
+        .get_all_plugins()
+        .iter()
+        .flat_map(|p| {
+            p.get_resources().into_iter().map(move |(name, suffix, description)| Resource {
+                name,
+                description,
+                uri: format!("mcpi://{}/resources/{}/{}", domain, p.name(), suffix),
+                mime_type: Some("application/json".into()),
+                annotations: None,
+            })
+        })
+        .collect();
+
+    Ok(ListResourcesResult { resources, next_cursor: None, _meta: None })
+}
+
+async fn read_resource(ctx: RequestContext, params: ReadResourceRequestParams) -> Result<ReadResourceResult, MCPError> {
+    let invalid = || MCPError::new(INVALID_PARAMS, "Invalid params");
+    let uri = Url::parse(&params.uri).map_err(|_| {
+        warn!("Invalid URI: {}", params.uri);
+        invalid()
+    })?;
+    if uri.scheme() != "mcpi" {
+        warn!("Invalid scheme: {}", uri.scheme());
+        return Err(invalid());
+    }
+
+    let path: Vec<&str> = uri.path_segments().map(|s| s.collect()).unwrap_or_default();
+    if path.len() < 3 || path[0] != "resources" {
+        warn!("Invalid path: {}", uri.path());
+        return Err(invalid());
+    }
+    let (plugin_name, resource_suffix) = (path[1], path[2..].join("/"));
+    let plugin = ctx.registry.get_plugin(plugin_name).ok_or_else(|| {
+        warn!("Plugin not found: {}", plugin_name);
+        invalid()
+    })?;
+
+    let span = tracing::info_span!("plugin_read_resource", plugin = plugin_name, resource = %resource_suffix);
+    match span.in_scope(|| plugin.read_resource(&resource_suffix)) {
+        Ok(ContentItem::Text { text, .. }) => {
+            let contents = ResourceContentUnion::Text(TextResourceContents {
+                uri: params.uri.clone(),
+                mime_type: Some("text/plain".to_string()),
+                text,
+            });
+            Ok(ReadResourceResult { contents: vec![contents], _meta: None })
+        }
+        Ok(other) => {
+            warn!("Cannot format ContentItem {:?} as ResourceContentUnion", other);
+            Err(MCPError::new(101, "Internal error"))
+        }
+        Err(e) => {
+            warn!("Read err: {}", e);
+            Err(MCPError::new(100, format!("Read err: {}", e)))
+        }
+    }
+}
+
+async fn list_tools(ctx: RequestContext, _params: Value) -> Result<ListToolsResult, MCPError> {
+    let tools = ctx
+        .registry
+        .get_all_plugins()
+        .iter()
+        .map(|p| Tool {
+            name: p.name().into(),
+            description: Some(p.description().into()),
+            input_schema: p.input_schema(),
+            annotations: p.get_tool_annotations(),
+        })
+        .collect();
+
+    Ok(ListToolsResult { tools, next_cursor: None, _meta: None })
+}
+
+async fn call_tool(ctx: RequestContext, params: CallToolRequestParams) -> Result<CallToolResult, MCPError> {
+    let operation = params.arguments.get("operation").and_then(|o| o.as_str()).unwrap_or("DEFAULT");
+    ctx.report_progress(0.0, Some(1.0), Some(&format!("Running {} {}", params.name, operation))).await;
+
+    let outcome = tracing::info_span!("plugin_execute", plugin = %params.name, operation)
+        .in_scope(|| ctx.registry.execute_plugin(&params.name, operation, &params.arguments));
+    ctx.report_progress(1.0, Some(1.0), None).await;
+
+    match outcome {
+        Ok(result) => {
+            let content = match result {
+                Value::String(text) => vec![ContentItem::Text { text, annotations: None }],
+                Value::Null => vec![],
+                other => vec![ContentItem::Text {
+                    text: serde_json::to_string_pretty(&other).unwrap_or_else(|_| other.to_string()),
+                    annotations: None,
+                }],
+            };
+            Ok(CallToolResult { content, is_error: false, _meta: None })
+        }
+        Err(e) => {
+            ctx.log("error", json!({ "tool": params.name, "operation": operation, "error": e.to_string() })).await;
+            let content = vec![ContentItem::Text { text: format!("Exec err: {}", e), annotations: None }];
+            Ok(CallToolResult { content, is_error: true, _meta: None })
+        }
+    }
+}
+
+async fn completions(ctx: RequestContext, params: CompleteRequestParams) -> Result<CompleteResult, MCPError> {
+    let param_name = &params.argument.name;
+    let partial_value = &params.argument.value;
+    let tool_name_context = params.context.as_ref().and_then(|c| c.get("name")).and_then(|v| v.as_str());
+
+    let values = match tool_name_context {
+        // Completing the tool name itself
+        None if param_name == "name" => ctx
+            .registry
+            .get_all_plugins()
+            .iter()
+            .map(|p| p.name().to_string())
+            .filter(|n| n.starts_with(partial_value.as_str()))
+            .collect(),
+        // Completing an argument of a specific tool
+        Some(tool_name) => match ctx.registry.get_plugin(tool_name) {
+            Some(plugin) => {
+                let context_json = serde_json::to_value(&params.context).unwrap_or(Value::Null);
+                plugin
+                    .get_completions(param_name, &Value::String(partial_value.clone()), &context_json)
+                    .into_iter()
+                    .filter_map(|v| v.as_str().map(String::from))
+                    .collect()
+            }
+            None => vec![],
+        },
+        None => vec![],
+    };
+
+    Ok(CompleteResult {
+        completion: CompleteResultCompletion { values, total: None, has_more: None },
+        _meta: None,
+    })
+}
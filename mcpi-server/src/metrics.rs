@@ -0,0 +1,337 @@
+// mcpi-server/src/metrics.rs
+//
+// Prometheus metrics, served in the text exposition format on `GET /metrics`.
+// Request, plugin call and message size metrics are recorded as requests are
+// handled; gauges such as active sessions and outbound queue depth are read from
+// each tenant's live state when the endpoint is scraped. Every series carries a
+// `tenant` label, and one scrape covers all tenants.
+
+use crate::interceptor::{Interceptor, InterceptorFuture, SessionContext};
+use crate::AppState;
+use axum::{
+    extract::State,
+    http::header,
+    response::IntoResponse,
+};
+use mcpi_common::{MCPError, MCPRequest, METHOD_NOT_FOUND};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::{atomic::Ordering, Arc, Mutex, RwLock, Weak};
+use std::time::Instant;
+
+/// Upper bounds of the request latency buckets, in seconds
+const DURATION_BUCKETS: &[f64] = &[0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// Upper bounds of the message size buckets, in bytes
+const SIZE_BUCKETS: &[f64] = &[128.0, 512.0, 2048.0, 8192.0, 32768.0, 131072.0, 524288.0, 2097152.0, 8388608.0];
+
+/// Label sets kept per metric. Method, plugin and operation names come from
+/// clients, so once a metric has this many series new ones are folded into `other`.
+const MAX_SERIES: usize = 1000;
+
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// Label values in the order of the metric's label names
+type Series<T> = BTreeMap<Vec<String>, T>;
+
+struct Histogram {
+    counts: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(buckets: &[f64]) -> Self {
+        Histogram { counts: vec![0; buckets.len()], sum: 0.0, count: 0 }
+    }
+
+    fn observe(&mut self, buckets: &[f64], value: f64) {
+        if let Some(i) = buckets.iter().position(|bound| value <= *bound) {
+            self.counts[i] += 1;
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
+// The series for `labels`, created if new and folded into `other` past MAX_SERIES.
+// The first label (the tenant) is always kept.
+fn series<T>(series: &mut Series<T>, mut labels: Vec<String>, new: impl FnOnce() -> T) -> &mut T {
+    if !series.contains_key(&labels) && series.len() >= MAX_SERIES {
+        for label in labels.iter_mut().skip(1) {
+            *label = "other".to_string();
+        }
+    }
+    series.entry(labels).or_insert_with(new)
+}
+
+/// Metrics recorded by every tenant, plus the tenants whose live state is read on scrape
+pub struct Metrics {
+    started: Instant,
+    // tenant, method, outcome
+    requests: Mutex<Series<u64>>,
+    // tenant, method
+    durations: Mutex<Series<Histogram>>,
+    // tenant, plugin, operation
+    plugin_calls: Mutex<Series<u64>>,
+    plugin_errors: Mutex<Series<u64>>,
+    // tenant, direction
+    message_sizes: Mutex<Series<Histogram>>,
+    tenants: RwLock<Vec<Weak<AppState>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Metrics {
+            started: Instant::now(),
+            requests: Mutex::new(BTreeMap::new()),
+            durations: Mutex::new(BTreeMap::new()),
+            plugin_calls: Mutex::new(BTreeMap::new()),
+            plugin_errors: Mutex::new(BTreeMap::new()),
+            message_sizes: Mutex::new(BTreeMap::new()),
+            tenants: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Include a tenant's sessions, queues and counters in every scrape
+    pub fn track(&self, state: &Arc<AppState>) {
+        self.tenants.write().unwrap().push(Arc::downgrade(state));
+    }
+
+    fn record_request(&self, tenant: &str, method: &str, outcome: &str, seconds: f64) {
+        let labels = vec![tenant.to_string(), method.to_string(), outcome.to_string()];
+        *series(&mut self.requests.lock().unwrap(), labels, || 0) += 1;
+        let labels = vec![tenant.to_string(), method.to_string()];
+        series(&mut self.durations.lock().unwrap(), labels, || Histogram::new(DURATION_BUCKETS)).observe(DURATION_BUCKETS, seconds);
+    }
+
+    fn record_plugin_call(&self, tenant: &str, plugin: &str, operation: &str, failed: bool) {
+        let labels = vec![tenant.to_string(), plugin.to_string(), operation.to_string()];
+        *series(&mut self.plugin_calls.lock().unwrap(), labels.clone(), || 0) += 1;
+        if failed {
+            *series(&mut self.plugin_errors.lock().unwrap(), labels, || 0) += 1;
+        }
+    }
+
+    fn record_message_size(&self, tenant: &str, direction: &str, bytes: usize) {
+        let labels = vec![tenant.to_string(), direction.to_string()];
+        series(&mut self.message_sizes.lock().unwrap(), labels, || Histogram::new(SIZE_BUCKETS)).observe(SIZE_BUCKETS, bytes as f64);
+    }
+
+    /// Every metric in the Prometheus text format
+    pub async fn render(&self) -> String {
+        let mut out = String::new();
+        gauge(&mut out, "mcpi_uptime_seconds", "Seconds since the server started", &[], &[(vec![], self.started.elapsed().as_secs_f64())]);
+        counter(&mut out, "mcpi_requests_total", "JSON-RPC requests handled, by method and outcome", &["tenant", "method", "outcome"], &self.requests.lock().unwrap());
+        histogram(&mut out, "mcpi_request_duration_seconds", "Time to handle a JSON-RPC request", &["tenant", "method"], DURATION_BUCKETS, &self.durations.lock().unwrap());
+        counter(&mut out, "mcpi_plugin_calls_total", "Plugin operations called through tools/call", &["tenant", "plugin", "operation"], &self.plugin_calls.lock().unwrap());
+        counter(&mut out, "mcpi_plugin_errors_total", "Plugin operations that returned an error", &["tenant", "plugin", "operation"], &self.plugin_errors.lock().unwrap());
+        histogram(&mut out, "mcpi_message_size_bytes", "Size of JSON-RPC messages received and sent", &["tenant", "direction"], SIZE_BUCKETS, &self.message_sizes.lock().unwrap());
+
+        let tenants: Vec<Arc<AppState>> = self.tenants.read().unwrap().iter().filter_map(Weak::upgrade).collect();
+        let mut sessions = Vec::new();
+        let mut http_requests = Vec::new();
+        let mut plugins = Vec::new();
+        let mut rejected = Vec::new();
+        let mut reloads = Vec::new();
+        for state in &tenants {
+            let tenant = state.tenant.clone();
+            let mut by_transport = state.http_sessions.count_by_transport().await;
+            by_transport.insert("websocket", state.ws_connections.len());
+            for transport in ["streamable_http", "legacy_sse", "websocket"] {
+                let count = by_transport.get(transport).copied().unwrap_or(0);
+                sessions.push((vec![tenant.clone(), transport.to_string()], count as f64));
+            }
+            http_requests.push((vec![tenant.clone()], state.request_count.load(Ordering::Relaxed) as f64));
+            plugins.push((vec![tenant.clone()], state.snapshot.get().registry.get_all_plugins().len() as f64));
+            if let Some(Value::Object(reasons)) = state.message_handler.limits().stats().get("rejected") {
+                for (reason, count) in reasons {
+                    rejected.push((vec![tenant.clone(), reason.clone()], count.as_f64().unwrap_or(0.0)));
+                }
+            }
+            let stats = state.reloader.stats();
+            for (outcome, key) in [("success", "reloads"), ("failure", "failures")] {
+                reloads.push((vec![tenant.clone(), outcome.to_string()], stats[key].as_f64().unwrap_or(0.0)));
+            }
+        }
+        gauge(&mut out, "mcpi_active_sessions", "Open sessions and connections, by transport", &["tenant", "transport"], &sessions);
+        counter_values(&mut out, "mcpi_http_requests_total", "HTTP requests to the MCP, discovery and admin endpoints", &["tenant"], &http_requests);
+        gauge(&mut out, "mcpi_plugins_loaded", "Plugins currently registered", &["tenant"], &plugins);
+        counter_values(&mut out, "mcpi_rejected_messages_total", "Messages rejected for exceeding a limit, by reason", &["tenant", "reason"], &rejected);
+        counter_values(&mut out, "mcpi_config_reloads_total", "Configuration reloads, by outcome", &["tenant", "outcome"], &reloads);
+
+        // The outbound queues and in-flight tracking are shared by all tenants
+        if let Some(state) = tenants.first() {
+            let stats = state.outbound.stats();
+            let value = |key: &str| stats[key].as_f64().unwrap_or(0.0);
+            gauge(&mut out, "mcpi_outbound_queued_messages", "Messages waiting in client outbound queues", &[], &[(vec![], value("queued_messages"))]);
+            gauge(&mut out, "mcpi_outbound_max_queue_depth", "Depth of the fullest client outbound queue", &[], &[(vec![], value("max_queue_depth"))]);
+            counter_values(&mut out, "mcpi_outbound_dropped_notifications_total", "Notifications dropped because a client fell behind", &[], &[(vec![], value("dropped_notifications"))]);
+            counter_values(&mut out, "mcpi_outbound_disconnects_total", "Clients disconnected because they fell behind", &[], &[(vec![], value("disconnects"))]);
+            gauge(&mut out, "mcpi_in_flight_requests", "Requests currently being handled", &[], &[(vec![], state.drain.in_flight() as f64)]);
+        }
+        out
+    }
+}
+
+impl Default for Metrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Records into the shared metrics under one tenant's label. Added to a message
+/// handler's interceptor chain, it times every request and counts plugin calls.
+#[derive(Clone)]
+pub struct TenantMetrics {
+    metrics: Arc<Metrics>,
+    tenant: String,
+}
+
+impl TenantMetrics {
+    pub fn new(metrics: Arc<Metrics>, tenant: &str) -> Self {
+        TenantMetrics { metrics, tenant: tenant.to_string() }
+    }
+
+    /// Record the size of a message received (`in`) or sent (`out`)
+    pub fn record_message_size(&self, direction: &str, bytes: usize) {
+        self.metrics.record_message_size(&self.tenant, direction, bytes);
+    }
+}
+
+impl Interceptor for TenantMetrics {
+    fn name(&self) -> &str {
+        "metrics"
+    }
+
+    fn after<'a>(&'a self, ctx: &'a SessionContext, request: &'a MCPRequest, outcome: &'a mut Result<Value, MCPError>) -> InterceptorFuture<'a, ()> {
+        Box::pin(async move {
+            let seconds = ctx.received_at.elapsed().as_secs_f64();
+            let (method, result) = match outcome {
+                // Unknown methods are counted under `unknown`
+                Err(e) if e.code == METHOD_NOT_FOUND => ("unknown", "error"),
+                Err(_) => (request.method.as_str(), "error"),
+                Ok(_) => (request.method.as_str(), "ok"),
+            };
+            self.metrics.record_request(&self.tenant, method, result, seconds);
+
+            if request.method == "tools/call" {
+                let params = request.params.as_ref();
+                let Some(plugin) = params.and_then(|p| p.get("name")).and_then(|n| n.as_str()) else { return };
+                let operation = params
+                    .and_then(|p| p.get("arguments"))
+                    .and_then(|a| a.get("operation"))
+                    .and_then(|o| o.as_str())
+                    .unwrap_or("DEFAULT");
+                // Plugin failures come back as a result with `isError` set
+                let failed = match outcome {
+                    Ok(result) => result.get("isError").and_then(|e| e.as_bool()).unwrap_or(false),
+                    Err(_) => true,
+                };
+                self.metrics.record_plugin_call(&self.tenant, plugin, operation, failed);
+            }
+        })
+    }
+}
+
+// Handler for GET /metrics
+pub async fn serve_metrics(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, CONTENT_TYPE)], state.metrics.render().await)
+}
+
+fn describe(out: &mut String, name: &str, help: &str, kind: &str) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+}
+
+fn sample(out: &mut String, name: &str, label_names: &[&str], label_values: &[String], extra: Option<(&str, &str)>, value: f64) {
+    let mut labels: Vec<String> = label_names
+        .iter()
+        .zip(label_values)
+        .map(|(name, value)| format!("{}=\"{}\"", name, escape(value)))
+        .collect();
+    if let Some((name, value)) = extra {
+        labels.push(format!("{}=\"{}\"", name, value));
+    }
+    if labels.is_empty() {
+        let _ = writeln!(out, "{} {}", name, value);
+    } else {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels.join(","), value);
+    }
+}
+
+fn counter(out: &mut String, name: &str, help: &str, label_names: &[&str], series: &Series<u64>) {
+    describe(out, name, help, "counter");
+    for (labels, value) in series {
+        sample(out, name, label_names, labels, None, *value as f64);
+    }
+}
+
+fn counter_values(out: &mut String, name: &str, help: &str, label_names: &[&str], values: &[(Vec<String>, f64)]) {
+    describe(out, name, help, "counter");
+    for (labels, value) in values {
+        sample(out, name, label_names, labels, None, *value);
+    }
+}
+
+fn gauge(out: &mut String, name: &str, help: &str, label_names: &[&str], values: &[(Vec<String>, f64)]) {
+    describe(out, name, help, "gauge");
+    for (labels, value) in values {
+        sample(out, name, label_names, labels, None, *value);
+    }
+}
+
+fn histogram(out: &mut String, name: &str, help: &str, label_names: &[&str], buckets: &[f64], series: &Series<Histogram>) {
+    describe(out, name, help, "histogram");
+    let bucket_name = format!("{}_bucket", name);
+    for (labels, histogram) in series {
+        let mut cumulative = 0;
+        for (bound, count) in buckets.iter().zip(&histogram.counts) {
+            cumulative += count;
+            sample(out, &bucket_name, label_names, labels, Some(("le", &bound.to_string())), cumulative as f64);
+        }
+        sample(out, &bucket_name, label_names, labels, Some(("le", "+Inf")), histogram.count as f64);
+        sample(out, &format!("{}_sum", name), label_names, labels, None, histogram.sum);
+        sample(out, &format!("{}_count", name), label_names, labels, None, histogram.count as f64);
+    }
+}
+
+fn escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_render_request_and_plugin_metrics() {
+        let metrics = Arc::new(Metrics::new());
+        let recorder = TenantMetrics::new(metrics.clone(), "shop");
+        let ctx = SessionContext {
+            client_id: "client".to_string(),
+            protocol_version: None,
+            client_info: None,
+            is_notification: false,
+            in_batch: false,
+            received_at: Instant::now(),
+        };
+        let request: MCPRequest = serde_json::from_value(json!({
+            "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+            "params": { "name": "weather_forecast", "arguments": { "operation": "get_forecast" } }
+        }))
+        .unwrap();
+        let mut outcome = Ok(json!({ "content": [], "isError": true }));
+        recorder.after(&ctx, &request, &mut outcome).await;
+        recorder.record_message_size("in", 300);
+
+        let text = metrics.render().await;
+        assert!(text.contains("mcpi_requests_total{tenant=\"shop\",method=\"tools/call\",outcome=\"ok\"} 1"));
+        assert!(text.contains("mcpi_request_duration_seconds_count{tenant=\"shop\",method=\"tools/call\"} 1"));
+        assert!(text.contains("mcpi_plugin_errors_total{tenant=\"shop\",plugin=\"weather_forecast\",operation=\"get_forecast\"} 1"));
+        assert!(text.contains("mcpi_message_size_bytes_bucket{tenant=\"shop\",direction=\"in\",le=\"128\"} 0"));
+        assert!(text.contains("mcpi_message_size_bytes_bucket{tenant=\"shop\",direction=\"in\",le=\"512\"} 1"));
+    }
+}
@@ -0,0 +1,306 @@
+// mcpi-server/src/outbound.rs
+//
+// Bounded per-session outbound queues. Everything a client receives (responses,
+// notifications, server-to-client requests) goes through a queue of its own, and
+// when a slow client lets that queue fill up the configured policy decides what
+// happens: the producer waits for room, notifications are dropped (responses and
+// requests never are), or the client is disconnected.
+
+use crate::config::check_keys;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::Notify;
+use tracing::warn;
+
+const DEFAULT_QUEUE_CAPACITY: usize = 256;
+
+/// What happens when a client's queue is full
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressurePolicy {
+    /// Wait until the client has read enough to make room
+    Block,
+    /// Drop notifications; responses and requests still wait for room
+    DropNotifications,
+    /// Disconnect the client
+    Disconnect,
+}
+
+impl BackpressurePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackpressurePolicy::Block => "block",
+            BackpressurePolicy::DropNotifications => "drop_notifications",
+            BackpressurePolicy::Disconnect => "disconnect",
+        }
+    }
+}
+
+/// Settings from the optional `outbound` block of the server config
+#[derive(Debug, Clone)]
+pub struct OutboundConfig {
+    /// Messages a client may have queued before the policy applies
+    pub queue_capacity: usize,
+    pub policy: BackpressurePolicy,
+}
+
+impl OutboundConfig {
+    /// Read the `outbound` block, e.g. `{ "queue_capacity": 256, "policy": "drop_notifications" }`.
+    /// `policy` is one of "block", "drop_notifications" (the default) or "disconnect".
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "outbound", &["queue_capacity", "policy"])?;
+        let queue_capacity = match config.and_then(|c| c.get("queue_capacity")) {
+            Some(value) => value
+                .as_u64()
+                .filter(|c| *c > 0)
+                .map(|c| c as usize)
+                .ok_or_else(|| format!("outbound.queue_capacity must be a positive number, got {}", value))?,
+            None => DEFAULT_QUEUE_CAPACITY,
+        };
+        let policy = match config.and_then(|c| c.get("policy")) {
+            Some(value) => match value.as_str() {
+                Some("block") => BackpressurePolicy::Block,
+                Some("drop_notifications") => BackpressurePolicy::DropNotifications,
+                Some("disconnect") => BackpressurePolicy::Disconnect,
+                _ => return Err(format!("outbound.policy must be \"block\", \"drop_notifications\" or \"disconnect\", got {}", value)),
+            },
+            None => BackpressurePolicy::DropNotifications,
+        };
+        Ok(OutboundConfig { queue_capacity, policy })
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    dropped_notifications: AtomicU64,
+    disconnects: AtomicU64,
+}
+
+// Overflow signal shared by the two ends of a queue
+#[derive(Default)]
+struct Overflow {
+    flag: AtomicBool,
+    notify: Notify,
+}
+
+type DepthProbe = Box<dyn Fn() -> Option<usize> + Send + Sync>;
+
+// Open queues by an internal id, with the client they belong to
+type OpenQueues = Mutex<HashMap<u64, (String, DepthProbe)>>;
+
+/// Creates the per-client queues and keeps the numbers shown in the admin stats
+pub struct OutboundQueues {
+    config: OutboundConfig,
+    counters: Arc<Counters>,
+    open: Arc<OpenQueues>,
+    next_id: AtomicU64,
+}
+
+impl OutboundQueues {
+    pub fn new(config: OutboundConfig) -> Self {
+        OutboundQueues { config, counters: Arc::default(), open: Arc::default(), next_id: AtomicU64::new(0) }
+    }
+
+    /// Open a queue for a client that follows the configured policy
+    pub fn open<T: AsRef<str> + Send + 'static>(&self, client_id: &str) -> (OutboundSender<T>, OutboundReceiver<T>) {
+        let (sender, mut receiver) = queue(self.config.queue_capacity, self.config.policy, Some(self.counters.clone()));
+        let weak = sender.tx.downgrade();
+        let probe: DepthProbe = Box::new(move || {
+            let tx = weak.upgrade().filter(|tx| !tx.is_closed())?;
+            Some(tx.max_capacity() - tx.capacity())
+        });
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        self.open.lock().unwrap().insert(id, (client_id.to_string(), probe));
+        receiver.registration = Some(Registration { id, open: Arc::downgrade(&self.open) });
+        (sender, receiver)
+    }
+
+    /// Policy, limits, counters and the depth of every open queue
+    pub fn stats(&self) -> Value {
+        // A queue whose senders are all gone stays tracked until its receiver is dropped
+        let depths: Vec<Value> = self
+            .open
+            .lock()
+            .unwrap()
+            .values()
+            .filter_map(|(client_id, probe)| Some(json!({ "client_id": client_id, "depth": probe()? })))
+            .collect();
+        let total: u64 = depths.iter().filter_map(|d| d["depth"].as_u64()).sum();
+        let max = depths.iter().filter_map(|d| d["depth"].as_u64()).max().unwrap_or(0);
+        json!({
+            "policy": self.config.policy.as_str(),
+            "queue_capacity": self.config.queue_capacity,
+            "open_queues": depths.len(),
+            "queued_messages": total,
+            "max_queue_depth": max,
+            "dropped_notifications": self.counters.dropped_notifications.load(Ordering::Relaxed),
+            "disconnects": self.counters.disconnects.load(Ordering::Relaxed),
+            "queues": depths,
+        })
+    }
+}
+
+/// Untracked queue that always waits for room, for hand-offs inside the server
+pub fn handoff<T: AsRef<str>>(capacity: usize) -> (OutboundSender<T>, OutboundReceiver<T>) {
+    queue(capacity, BackpressurePolicy::Block, None)
+}
+
+fn queue<T: AsRef<str>>(capacity: usize, policy: BackpressurePolicy, counters: Option<Arc<Counters>>) -> (OutboundSender<T>, OutboundReceiver<T>) {
+    let (tx, rx) = mpsc::channel(capacity);
+    let overflow = Arc::new(Overflow::default());
+    (OutboundSender { tx, policy, overflow: overflow.clone(), counters }, OutboundReceiver { rx, overflow, registration: None })
+}
+
+/// Producer end of a client's queue
+pub struct OutboundSender<T> {
+    tx: mpsc::Sender<T>,
+    policy: BackpressurePolicy,
+    overflow: Arc<Overflow>,
+    counters: Option<Arc<Counters>>,
+}
+
+impl<T> Clone for OutboundSender<T> {
+    fn clone(&self) -> Self {
+        OutboundSender { tx: self.tx.clone(), policy: self.policy, overflow: self.overflow.clone(), counters: self.counters.clone() }
+    }
+}
+
+impl<T: AsRef<str>> OutboundSender<T> {
+    /// Queue a message, applying the policy if the queue is full. Returns false
+    /// if the message will not reach the client.
+    pub async fn send(&self, message: T) -> bool {
+        if self.overflow.flag.load(Ordering::SeqCst) {
+            return false;
+        }
+        match self.tx.try_send(message) {
+            Ok(()) => true,
+            Err(TrySendError::Closed(_)) => false,
+            Err(TrySendError::Full(message)) => match self.policy {
+                BackpressurePolicy::DropNotifications if is_notification(message.as_ref()) => {
+                    if let Some(counters) = &self.counters {
+                        counters.dropped_notifications.fetch_add(1, Ordering::Relaxed);
+                    }
+                    warn!("Outbound queue full, dropping notification");
+                    false
+                }
+                BackpressurePolicy::Block | BackpressurePolicy::DropNotifications => self.tx.send(message).await.is_ok(),
+                BackpressurePolicy::Disconnect => {
+                    if !self.overflow.flag.swap(true, Ordering::SeqCst) {
+                        if let Some(counters) = &self.counters {
+                            counters.disconnects.fetch_add(1, Ordering::Relaxed);
+                        }
+                        warn!("Outbound queue full, disconnecting client");
+                        // Stores a permit, so a receiver that is not waiting yet still sees it
+                        self.overflow.notify.notify_one();
+                    }
+                    false
+                }
+            },
+        }
+    }
+
+    /// Whether the client has gone away or was disconnected
+    pub fn is_closed(&self) -> bool {
+        self.tx.is_closed() || self.overflow.flag.load(Ordering::SeqCst)
+    }
+}
+
+/// Consumer end of a client's queue, read by the transport's writer
+pub struct OutboundReceiver<T> {
+    rx: mpsc::Receiver<T>,
+    overflow: Arc<Overflow>,
+    registration: Option<Registration>,
+}
+
+// Stops tracking a queue once its receiver is dropped, i.e. the connection or stream ended
+struct Registration {
+    id: u64,
+    open: Weak<OpenQueues>,
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        if let Some(open) = self.open.upgrade() {
+            open.lock().unwrap().remove(&self.id);
+        }
+    }
+}
+
+impl<T> OutboundReceiver<T> {
+    /// Next message, or None once every sender is gone or the client was
+    /// disconnected for falling behind
+    pub async fn recv(&mut self) -> Option<T> {
+        if self.overflowed() {
+            return None;
+        }
+        tokio::select! {
+            biased;
+            _ = self.overflow.notify.notified() => None,
+            message = self.rx.recv() => message,
+        }
+    }
+
+    /// Next message if one is queued right now
+    pub fn try_recv(&mut self) -> Option<T> {
+        self.rx.try_recv().ok()
+    }
+
+    /// Whether the queue was closed because the client fell behind
+    pub fn overflowed(&self) -> bool {
+        self.overflow.flag.load(Ordering::SeqCst)
+    }
+}
+
+// Notifications carry a method but no id; responses and requests carry an id
+fn is_notification(message: &str) -> bool {
+    match serde_json::from_str::<Value>(message) {
+        Ok(value) => value.get("method").is_some() && value.get("id").is_none(),
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn queues(policy: BackpressurePolicy) -> OutboundQueues {
+        OutboundQueues::new(OutboundConfig { queue_capacity: 1, policy })
+    }
+
+    #[tokio::test]
+    async fn test_full_queue_policies() {
+        let notification = || json!({ "jsonrpc": "2.0", "method": "notifications/progress" }).to_string();
+        let response = || json!({ "jsonrpc": "2.0", "id": 1, "result": {} }).to_string();
+
+        // Notifications are dropped, responses wait for the reader
+        let dropping = queues(BackpressurePolicy::DropNotifications);
+        let (tx, mut rx) = dropping.open::<String>("a");
+        assert!(tx.send(notification()).await);
+        assert!(!tx.send(notification()).await);
+        let reader = tokio::spawn(async move { (rx.recv().await, rx.recv().await) });
+        assert!(tx.send(response()).await);
+        let (first, second) = reader.await.unwrap();
+        assert!(first.unwrap().contains("progress") && second.unwrap().contains("result"));
+        assert_eq!(dropping.stats()["dropped_notifications"], 1);
+
+        // Overflowing disconnects the client, even if messages are still queued
+        let disconnecting = queues(BackpressurePolicy::Disconnect);
+        let (tx, mut rx) = disconnecting.open::<String>("b");
+        assert!(tx.send(response()).await);
+        assert_eq!(disconnecting.stats()["queued_messages"], 1);
+        assert!(!tx.send(response()).await);
+        assert!(rx.recv().await.is_none() && rx.overflowed());
+        assert_eq!(disconnecting.stats()["disconnects"], 1);
+    }
+
+    #[test]
+    fn test_dropped_receiver_stops_tracking_queue() {
+        let queues = queues(BackpressurePolicy::Block);
+        let (_tx, rx) = queues.open::<String>("a");
+        assert_eq!(queues.open.lock().unwrap().len(), 1);
+        drop(rx);
+        assert!(queues.open.lock().unwrap().is_empty());
+    }
+}
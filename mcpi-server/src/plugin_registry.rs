@@ -1,14 +1,51 @@
 // mcpi-server/src/plugin_registry.rs
-use mcpi_common::{McpPlugin, PluginResult, JsonDataPlugin};
+use crate::config::check_keys;
+use mcpi_common::{McpPlugin, PluginResult};
 use serde_json::Value;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, RwLock};
 use std::error::Error;
-use tracing::info;
+use tracing::{info, warn};
+
+/// Settings from the optional `plugins` block of the server config
+#[derive(Debug, Clone, Default)]
+pub struct PluginsConfig {
+    /// Load only these plugins; None loads all of them
+    pub enabled: Option<Vec<String>>,
+    pub disabled: Vec<String>,
+}
+
+impl PluginsConfig {
+    /// Read the `plugins` block, e.g. `{ "enabled": ["hello", "weather"] }` or `{ "disabled": ["weather"] }`
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "plugins", &["enabled", "disabled"])?;
+        let names = |key: &str| -> Result<Option<Vec<String>>, String> {
+            match config.and_then(|c| c.get(key)) {
+                None => Ok(None),
+                Some(Value::Array(items)) => items
+                    .iter()
+                    .map(|item| item.as_str().map(str::to_string).ok_or_else(|| format!("plugins.{} entries must be strings", key)))
+                    .collect::<Result<Vec<_>, _>>()
+                    .map(Some),
+                Some(other) => Err(format!("plugins.{} must be an array of plugin names, got {}", key, other)),
+            }
+        };
+        Ok(PluginsConfig { enabled: names("enabled")?, disabled: names("disabled")?.unwrap_or_default() })
+    }
+
+    pub fn allows(&self, name: &str) -> bool {
+        self.enabled.as_ref().is_none_or(|enabled| enabled.iter().any(|n| n == name)) && !self.disabled.iter().any(|n| n == name)
+    }
+}
 
 /// Registry that manages all available plugins
 pub struct PluginRegistry {
     plugins: RwLock<HashMap<String, Arc<dyn McpPlugin>>>,
+    config: PluginsConfig,
+    // Plugins left out by the config
+    skipped: RwLock<Vec<String>>,
+    shut_down: AtomicBool,
 }
 
 impl PluginRegistry {
@@ -16,12 +53,26 @@ impl PluginRegistry {
     pub fn new() -> Self {
         PluginRegistry {
             plugins: RwLock::new(HashMap::new()),
+            config: PluginsConfig::default(),
+            skipped: RwLock::new(Vec::new()),
+            shut_down: AtomicBool::new(false),
         }
     }
 
+    /// Only register the plugins the config allows
+    pub fn with_config(mut self, config: PluginsConfig) -> Self {
+        self.config = config;
+        self
+    }
+
     /// Register a plugin with the registry
     pub fn register_plugin(&self, plugin: Arc<dyn McpPlugin>) -> Result<(), Box<dyn Error + Send + Sync>> {
         let name = plugin.name().to_string();
+        if !self.config.allows(&name) {
+            info!("Plugin {} disabled by config", name);
+            self.skipped.write().unwrap().push(name);
+            return Ok(());
+        }
         let mut plugins = self.plugins.write().unwrap();
         
         if plugins.contains_key(&name) {
@@ -33,6 +84,28 @@ impl PluginRegistry {
         Ok(())
     }
 
+    /// Run every plugin's shutdown hook once, logging failures
+    pub fn shutdown_plugins(&self) {
+        if self.shut_down.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        for (name, plugin) in self.plugins.read().unwrap().iter() {
+            match plugin.shutdown() {
+                Ok(()) => info!("Plugin {} shut down", name),
+                Err(e) => warn!("Plugin {} failed to shut down: {}", name, e),
+            }
+        }
+    }
+
+    /// Run every plugin's health check, by plugin name
+    pub fn check_health(&self) -> Vec<(String, Result<(), String>)> {
+        let plugins = self.get_all_plugins();
+        let mut results: Vec<(String, Result<(), String>)> =
+            plugins.iter().map(|plugin| (plugin.name().to_string(), plugin.health_check().map_err(|e| e.to_string()))).collect();
+        results.sort_by(|a, b| a.0.cmp(&b.0));
+        results
+    }
+
     /// Get a plugin by name
     pub fn get_plugin(&self, name: &str) -> Option<Arc<dyn McpPlugin>> {
         let plugins = self.plugins.read().unwrap();
@@ -61,7 +134,22 @@ impl PluginRegistry {
         
         // Register all extension plugins
         self.register_extension_plugins()?;
-        
+
+        self.check_configured_names()
+    }
+
+    // A name in the plugins block that matches no plugin is most likely a typo
+    fn check_configured_names(&self) -> Result<(), Box<dyn Error + Send + Sync>> {
+        let plugins = self.plugins.read().unwrap();
+        let skipped = self.skipped.read().unwrap();
+        let configured = self.config.enabled.iter().flatten().chain(&self.config.disabled);
+        for name in configured {
+            if !plugins.contains_key(name) && !skipped.contains(name) {
+                let mut known: Vec<&String> = plugins.keys().chain(skipped.iter()).collect();
+                known.sort();
+                return Err(format!("Unknown plugin '{}' in the plugins config; available: {:?}", name, known).into());
+            }
+        }
         Ok(())
     }
     
@@ -75,9 +163,8 @@ impl PluginRegistry {
         let hello_plugin = hello::create_plugin(data_path)?;
         self.register_plugin(hello_plugin)?;
         
-        // Register website plugin - now wrapped with JsonDataPlugin
-        let website_inner_plugin = website::WebsitePlugin::new(data_path);
-        let website_plugin = Arc::new(JsonDataPlugin::new(website_inner_plugin));
+        // Register website plugin - wrapped with JsonDataPlugin
+        let website_plugin = website::create_plugin(data_path)?;
         self.register_plugin(website_plugin)?;
         
         // Register store plugins
@@ -103,4 +190,12 @@ impl PluginRegistry {
         
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+// A registry replaced by a reload is dropped once the last request using it is
+// done, so its plugins are not shut down under an in-flight call
+impl Drop for PluginRegistry {
+    fn drop(&mut self) {
+        self.shutdown_plugins();
+    }
+}
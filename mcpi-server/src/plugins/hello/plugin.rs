@@ -106,11 +106,37 @@ impl McpPlugin for HelloPlugin {
         }
     }
     
+    fn custom_methods(&self) -> Vec<String> {
+        vec!["mcpi/hello".to_string()]
+    }
+
+    fn handle_method(&self, method: &str, params: &Value) -> PluginResult {
+        match method {
+            // Same introduction as the HELLO tool operation, without the tools/call envelope
+            "mcpi/hello" => self.execute("HELLO", params),
+            _ => Err(format!("Unsupported method: {}", method).into())
+        }
+    }
+
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             self.name.clone(),
-            format!("mcpi://provider/resources/hello_config.json"),
+            "mcpi://provider/resources/hello_config.json".to_string(),
             Some("Hello protocol configuration".to_string()),
         )]
     }
+
+    // Without a config file the plugin still answers, but only with the built-in introduction
+    fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let config_path = Path::new(&self.data_path);
+        if !config_path.exists() {
+            return Err(format!("Using built-in introduction, {} not found", config_path.display()).into());
+        }
+        let data = fs::read_to_string(config_path).map_err(|e| format!("Cannot read {}: {}", config_path.display(), e))?;
+        let config: Value = serde_json::from_str(&data).map_err(|e| format!("{} is not valid JSON: {}", config_path.display(), e))?;
+        if config.pointer("/default/introduction").and_then(|i| i.as_str()).is_none() {
+            return Err(format!("{} has no default.introduction", config_path.display()).into());
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -102,8 +102,18 @@ impl McpPlugin for SocialPlugin {
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             self.name.clone(),
-            format!("mcpi://provider/resources/social/referrals/data.json"),
+            "mcpi://provider/resources/social/referrals/data.json".to_string(),
             Some("Referral relationships".to_string()),
         )]
     }
+
+    // The referrals file is optional, but one that is present must parse
+    fn health_check(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let referrals_path = Path::new(&self.data_path);
+        if referrals_path.exists() {
+            let data = fs::read_to_string(referrals_path).map_err(|e| format!("Cannot read {}: {}", referrals_path.display(), e))?;
+            serde_json::from_str::<Value>(&data).map_err(|e| format!("{} is not valid JSON: {}", referrals_path.display(), e))?;
+        }
+        Ok(())
+    }
 }
\ No newline at end of file
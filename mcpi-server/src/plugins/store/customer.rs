@@ -82,7 +82,7 @@ impl McpPlugin for CustomerPlugin {
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             "customers".to_string(),
-            format!("mcpi://provider/resources/store/customers/data.json"),
+            "mcpi://provider/resources/store/customers/data.json".to_string(),
             Some("Customer data".to_string()),
         )]
     }
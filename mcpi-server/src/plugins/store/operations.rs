@@ -7,6 +7,7 @@ use tracing::info;
 // Currently, most operations are handled by the JsonDataPlugin.
 
 // Example of a custom operation that could be added in the future
+#[allow(dead_code)]
 pub fn calculate_product_stats(products: &Value) -> PluginResult {
     // Create Vec before using it to avoid temporary value issues
     let empty_vec = Vec::new();
@@ -78,7 +78,7 @@ impl McpPlugin for OrderPlugin {
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             "orders".to_string(),
-            format!("mcpi://provider/resources/store/orders/data.json"),
+            "mcpi://provider/resources/store/orders/data.json".to_string(),
             Some("Order data".to_string()),
         )]
     }
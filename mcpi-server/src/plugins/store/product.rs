@@ -73,14 +73,14 @@ impl McpPlugin for ProductPlugin {
     }
     
     // This is a default implementation that will be overridden by JsonDataPlugin
-    fn execute(&self, operation: &str, params: &Value) -> PluginResult {
+    fn execute(&self, _operation: &str, _params: &Value) -> PluginResult {
         Err("This method is overridden by JsonDataPlugin".into())
     }
     
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             "products".to_string(),
-            format!("mcpi://provider/resources/store/products/data.json"),
+            "mcpi://provider/resources/store/products/data.json".to_string(),
             Some("Product catalog data".to_string()),
         )]
     }
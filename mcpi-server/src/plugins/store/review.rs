@@ -71,14 +71,14 @@ impl McpPlugin for ReviewPlugin {
         })
     }
     
-    fn execute(&self, operation: &str, params: &Value) -> PluginResult {
+    fn execute(&self, _operation: &str, _params: &Value) -> PluginResult {
         Err("This method is overridden by JsonDataPlugin".into())
     }
     
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             "reviews".to_string(),
-            format!("mcpi://provider/resources/store/reviews/data.json"),
+            "mcpi://provider/resources/store/reviews/data.json".to_string(),
             Some("Review data".to_string()),
         )]
     }
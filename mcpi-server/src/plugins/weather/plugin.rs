@@ -103,7 +103,7 @@ impl McpPlugin for WeatherPlugin {
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             self.name.clone(),
-            format!("mcpi://provider/resources/weather/locations/data.json"),
+            "mcpi://provider/resources/weather/locations/data.json".to_string(),
             Some("Weather locations and forecasts".to_string()),
         )]
     }
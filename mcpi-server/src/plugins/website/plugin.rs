@@ -100,7 +100,7 @@ impl McpPlugin for WebsitePlugin {
     fn get_resources(&self) -> Vec<(String, String, Option<String>)> {
         vec![(
             self.name.clone(),
-            format!("mcpi://provider/resources/website/content/data.json"),
+            "mcpi://provider/resources/website/content/data.json".to_string(),
             Some(self.description.clone()),
         )]
     }
@@ -0,0 +1,326 @@
+// mcpi-server/src/reload.rs
+//
+// Hot reload of the provider info, referrals and plugins. A reload re-reads every
+// config layer, checks the JSON data files and rebuilds the plugins; only when all
+// of that succeeds are the new values swapped in, together and in one step, so a
+// bad edit leaves the running state untouched and no request sees new provider info
+// with old plugins. The work runs on the blocking pool, as do the watcher's scans of
+// the data directory. Connected clients are then told that the tool and resource
+// lists changed. Reloads are triggered by SIGHUP, `POST /api/admin/reload` or, with
+// `reload.watch` set, by a change to the data directory or config file.
+
+use crate::config::{check_keys, Cli, ServerConfig};
+use crate::plugin_registry::{PluginRegistry, PluginsConfig};
+use crate::tenants::TenantConfig;
+use crate::AppState;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tracing::{info, warn};
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Notifications sent to every connected client after a successful reload
+const LIST_CHANGED_NOTIFICATIONS: &[&str] = &["notifications/tools/list_changed", "notifications/resources/list_changed"];
+
+/// Settings from the optional `reload` block of the server config
+#[derive(Debug, Clone)]
+pub struct ReloadConfig {
+    /// Poll the data directory and config file for changes
+    pub watch: bool,
+    pub poll_interval: Duration,
+}
+
+impl ReloadConfig {
+    /// Read the `reload` block, e.g. `{ "watch": true, "poll_interval_secs": 2 }`
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "reload", &["watch", "poll_interval_secs"])?;
+        let watch = match config.and_then(|c| c.get("watch")) {
+            Some(value) => value.as_bool().ok_or_else(|| format!("reload.watch must be a boolean, got {}", value))?,
+            None => false,
+        };
+        let poll_interval = match config.and_then(|c| c.get("poll_interval_secs")) {
+            Some(value) => Duration::from_secs(
+                value
+                    .as_u64()
+                    .filter(|secs| *secs > 0)
+                    .ok_or_else(|| format!("reload.poll_interval_secs must be a positive number of seconds, got {}", value))?,
+            ),
+            None => DEFAULT_POLL_INTERVAL,
+        };
+        Ok(ReloadConfig { watch, poll_interval })
+    }
+}
+
+/// A value that is replaced as a whole; readers keep the snapshot they took
+pub struct Reloadable<T>(RwLock<Arc<T>>);
+
+impl<T> Reloadable<T> {
+    pub fn new(value: T) -> Self {
+        Reloadable(RwLock::new(Arc::new(value)))
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn set(&self, value: T) {
+        *self.0.write().unwrap() = Arc::new(value);
+    }
+}
+
+/// Provider info and referrals
+pub struct Provider {
+    pub info: Arc<Value>,
+    pub referrals: Arc<Value>,
+}
+
+impl Provider {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Provider {
+            info: Arc::new(config.get("provider").cloned().unwrap_or_else(|| json!({}))),
+            referrals: Arc::new(config.get("referrals").cloned().unwrap_or_else(|| json!([]))),
+        }
+    }
+}
+
+/// Where a reload reads the configuration from
+pub enum ConfigSource {
+    /// Every layer of the root configuration, as the server was started
+    Root(Cli),
+    Tenant(TenantConfig),
+}
+
+impl ConfigSource {
+    fn load(&self) -> Result<(ServerConfig, PluginsConfig), String> {
+        match self {
+            ConfigSource::Root(cli) => {
+                let config = ServerConfig::load(cli)?;
+                let settings = config.settings(cli.stdio)?;
+                Ok((config, settings.plugins))
+            }
+            ConfigSource::Tenant(tenant) => {
+                let config = ServerConfig::load_tenant(tenant)?;
+                let plugins = PluginsConfig::from_config(config.get("plugins"))?;
+                Ok((config, plugins))
+            }
+        }
+    }
+}
+
+/// Everything a reload swaps in, built and validated before anything changes.
+/// The plugins of a replaced snapshot shut down when the last request using its
+/// registry finishes.
+pub struct Snapshot {
+    pub provider: Provider,
+    pub registry: Arc<PluginRegistry>,
+}
+
+/// Re-reads the configuration the server was started with and keeps reload stats
+pub struct Reloader {
+    source: ConfigSource,
+    // One reload at a time
+    running: tokio::sync::Mutex<()>,
+    reloads: AtomicU64,
+    failures: AtomicU64,
+    last_reload: Mutex<Option<chrono::DateTime<chrono::Utc>>>,
+    last_error: Mutex<Option<String>>,
+}
+
+impl Reloader {
+    pub fn new(source: ConfigSource) -> Self {
+        Reloader {
+            source,
+            running: tokio::sync::Mutex::new(()),
+            reloads: AtomicU64::new(0),
+            failures: AtomicU64::new(0),
+            last_reload: Mutex::new(None),
+            last_error: Mutex::new(None),
+        }
+    }
+
+    /// Reload counts, when the last one succeeded and why the last one failed
+    pub fn stats(&self) -> Value {
+        json!({
+            "reloads": self.reloads.load(Ordering::Relaxed),
+            "failures": self.failures.load(Ordering::Relaxed),
+            "last_reload": self.last_reload.lock().unwrap().map(|t| t.to_rfc3339()),
+            "last_error": *self.last_error.lock().unwrap(),
+        })
+    }
+
+    fn prepare(&self) -> Result<Snapshot, String> {
+        let (config, plugins) = self.source.load()?;
+        check_data_files(&config.data_dir)?;
+
+        let provider = Provider::from_config(&config);
+        let registry = PluginRegistry::new().with_config(plugins);
+        registry
+            .register_all_plugins(&config.data_dir.to_string_lossy(), (*provider.referrals).clone())
+            .map_err(|e| e.to_string())?;
+        Ok(Snapshot { provider, registry: Arc::new(registry) })
+    }
+}
+
+/// Validate the current configuration and swap it in. On error the running state
+/// is left as it was.
+pub async fn reload(state: &AppState) -> Result<Value, String> {
+    let reloader = &state.reloader;
+    let _running = reloader.running.lock().await;
+
+    // Reading the config, checking every data file and building plugins all block
+    let preparing = reloader.clone();
+    let prepared = tokio::task::spawn_blocking(move || preparing.prepare())
+        .await
+        .unwrap_or_else(|e| Err(format!("Reload task failed: {}", e)));
+    let prepared = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            warn!("Reload of {} failed, keeping the current configuration: {}", state.tenant, e);
+            reloader.failures.fetch_add(1, Ordering::Relaxed);
+            *reloader.last_error.lock().unwrap() = Some(e.clone());
+            return Err(e);
+        }
+    };
+
+    let plugins = prepared.registry.get_all_plugins().len();
+    state.snapshot.set(prepared);
+    reloader.reloads.fetch_add(1, Ordering::Relaxed);
+    *reloader.last_reload.lock().unwrap() = Some(chrono::Utc::now());
+    *reloader.last_error.lock().unwrap() = None;
+
+    let mut notified = 0;
+    for method in LIST_CHANGED_NOTIFICATIONS {
+        let message = json!({ "jsonrpc": "2.0", "method": method }).to_string();
+        let subscribers = state.notifications.send(message.clone()).unwrap_or(0);
+        let reached = subscribers + state.ws_connections.broadcast(message.clone()).await + state.http_sessions.broadcast(message).await;
+        notified = notified.max(reached);
+    }
+    info!("Configuration of {} reloaded: {} plugins, {} clients notified", state.tenant, plugins, notified);
+    Ok(json!({ "plugins": plugins, "clients_notified": notified }))
+}
+
+/// Reload every tenant on each SIGHUP
+#[cfg(unix)]
+pub async fn reload_on_sighup(tenants: Vec<Arc<AppState>>) {
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(signal) => signal,
+        Err(e) => {
+            warn!("Cannot listen for SIGHUP, config reload on signal disabled: {}", e);
+            return;
+        }
+    };
+    while hangup.recv().await.is_some() {
+        info!("SIGHUP received, reloading configuration");
+        for state in &tenants {
+            let _ = reload(state).await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_tenants: Vec<Arc<AppState>>) {}
+
+/// Reload whenever a file in the data directory or the config file changes
+pub async fn watch_for_changes(state: Arc<AppState>, data_dir: PathBuf, config_file: Option<PathBuf>, poll_interval: Duration) {
+    let mut interval = tokio::time::interval(poll_interval);
+    let mut last = scan(&data_dir, config_file.as_deref()).await;
+    loop {
+        interval.tick().await;
+        let current = scan(&data_dir, config_file.as_deref()).await;
+        if current != last {
+            info!("Configuration files changed, reloading");
+            last = current;
+            let _ = reload(&state).await;
+        }
+    }
+}
+
+// Take a snapshot on the blocking pool, since it walks the whole data directory
+async fn scan(data_dir: &Path, config_file: Option<&Path>) -> HashMap<PathBuf, SystemTime> {
+    let (data_dir, config_file) = (data_dir.to_path_buf(), config_file.map(Path::to_path_buf));
+    tokio::task::spawn_blocking(move || snapshot(&data_dir, config_file.as_deref())).await.unwrap_or_default()
+}
+
+// Modification times of every file under the data directory and of the config file
+fn snapshot(data_dir: &Path, config_file: Option<&Path>) -> HashMap<PathBuf, SystemTime> {
+    let mut files = HashMap::new();
+    visit_files(data_dir, &mut |path| {
+        if let Ok(modified) = fs::metadata(path).and_then(|m| m.modified()) {
+            files.insert(path.to_path_buf(), modified);
+        }
+    });
+    if let Some(file) = config_file {
+        if let Ok(modified) = fs::metadata(file).and_then(|m| m.modified()) {
+            files.insert(file.to_path_buf(), modified);
+        }
+    }
+    files
+}
+
+// Plugins read their data files when called, so a file that no longer parses is
+// reported here rather than on the next request
+fn check_data_files(data_dir: &Path) -> Result<(), String> {
+    let mut errors = Vec::new();
+    visit_files(data_dir, &mut |path| {
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let parsed = fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|text| {
+                if text.trim().is_empty() {
+                    return Ok(());
+                }
+                serde_json::from_str::<Value>(&text).map(|_| ()).map_err(|e| e.to_string())
+            });
+            if let Err(e) = parsed {
+                errors.push(format!("Invalid JSON in {}: {}", path.display(), e));
+            }
+        }
+    });
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.join("; "))
+    }
+}
+
+fn visit_files(dir: &Path, visit: &mut dyn FnMut(&Path)) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            visit_files(&path, visit);
+        } else {
+            visit(&path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_data_file_fails_validation() {
+        let dir = std::env::temp_dir().join(format!("mcpi-reload-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(dir.join("server")).unwrap();
+        fs::create_dir_all(dir.join("hello")).unwrap();
+        fs::write(dir.join("server").join("data.json"), r#"{ "provider": { "name": "Before" } }"#).unwrap();
+        fs::write(dir.join("hello").join("data.json"), r#"{ "default": {} }"#).unwrap();
+
+        let reloader = Reloader::new(ConfigSource::Root(Cli { data_dir: Some(dir.clone()), ..Cli::default() }));
+        let prepared = reloader.prepare().unwrap();
+        assert_eq!(prepared.provider.info["name"], "Before");
+        assert!(prepared.registry.get_plugin("hello").is_some());
+
+        let before = snapshot(&dir, None);
+        fs::write(dir.join("hello").join("data.json"), r#"{ "default": "#).unwrap();
+        let err = reloader.prepare().err().unwrap();
+        assert!(err.contains("hello") && err.contains("Invalid JSON"));
+        assert_eq!(snapshot(&dir, None).len(), before.len());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
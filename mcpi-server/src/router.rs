@@ -0,0 +1,199 @@
+// mcpi-server/src/router.rs
+
+use crate::outbound::OutboundSender;
+use crate::plugin_registry::PluginRegistry;
+use crate::telemetry;
+use mcpi_common::{McpPlugin, MCPError, MCPRequest, INTERNAL_ERROR, INVALID_PARAMS, METHOD_NOT_FOUND};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use tracing::{info, warn, Instrument};
+use uuid::Uuid;
+
+/// Boxed future returned by a registered method
+pub type MethodFuture = Pin<Box<dyn Future<Output = Result<Value, MCPError>> + Send>>;
+
+type BoxedMethod = Arc<dyn Fn(RequestContext, Value) -> MethodFuture + Send + Sync>;
+
+/// Queue carrying serialized JSON-RPC messages related to an in-flight request
+pub type EventSender = OutboundSender<String>;
+
+/// Per-request state handed to every method handler
+#[derive(Clone)]
+pub struct RequestContext {
+    pub client_id: String,
+    pub registry: Arc<PluginRegistry>,
+    pub provider_info: Arc<Value>,
+    /// Progress token from the request's `_meta`, if the client asked for progress
+    pub progress_token: Option<Value>,
+    /// Where messages related to this request go; None if the transport cannot stream them
+    pub events: Option<EventSender>,
+}
+
+impl RequestContext {
+    /// Send a notification related to this request. Returns false if it could not be delivered.
+    pub async fn notify(&self, method: &str, params: Value) -> bool {
+        self.send(json!({ "jsonrpc": "2.0", "method": method, "params": params })).await
+    }
+
+    /// Send a server-to-client request related to this request and return its id
+    #[allow(dead_code)] // Used by plugins/handlers that need input from the client
+    pub async fn request(&self, method: &str, params: Value) -> Option<String> {
+        let id = format!("srv-{}", Uuid::new_v4());
+        self.send(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }))
+            .await
+            .then_some(id)
+    }
+
+    /// Report progress, if the client supplied a progress token
+    pub async fn report_progress(&self, progress: f64, total: Option<f64>, message: Option<&str>) {
+        if let Some(token) = &self.progress_token {
+            let mut params = json!({ "progressToken": token, "progress": progress });
+            if let Some(total) = total {
+                params["total"] = json!(total);
+            }
+            if let Some(message) = message {
+                params["message"] = json!(message);
+            }
+            self.notify("notifications/progress", params).await;
+        }
+    }
+
+    /// Send a log message notification to the client
+    pub async fn log(&self, level: &str, data: Value) {
+        self.notify("notifications/message", json!({ "level": level, "logger": "mcpi-server", "data": data })).await;
+    }
+
+    async fn send(&self, message: Value) -> bool {
+        match &self.events {
+            Some(events) => events.send(message.to_string()).await,
+            None => false,
+        }
+    }
+}
+
+/// Registry of JSON-RPC methods keyed by method name
+pub struct MethodRouter {
+    methods: HashMap<String, BoxedMethod>,
+}
+
+impl MethodRouter {
+    /// Create an empty router
+    pub fn new() -> Self {
+        MethodRouter {
+            methods: HashMap::new(),
+        }
+    }
+
+    /// Register a typed async handler for `method`, replacing any existing one.
+    ///
+    /// Params are deserialized into `P` (a missing `params` member is treated as
+    /// `null`) and the handler's result is serialized back to JSON.
+    pub fn register<P, R, F, Fut>(&mut self, method: &str, handler: F) -> &mut Self
+    where
+        P: DeserializeOwned + Send + 'static,
+        R: Serialize + 'static,
+        F: Fn(RequestContext, P) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, MCPError>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let boxed: BoxedMethod = Arc::new(move |ctx, params| {
+            let handler = handler.clone();
+            Box::pin(async move {
+                let params = serde_json::from_value::<P>(params)
+                    .map_err(|e| MCPError::new(INVALID_PARAMS, format!("Invalid params: {}", e)))?;
+                let result = handler(ctx, params).await?;
+                serde_json::to_value(result)
+                    .map_err(|e| MCPError::new(INTERNAL_ERROR, format!("Failed to serialize result: {}", e)))
+            })
+        });
+
+        if self.methods.insert(method.to_string(), boxed).is_some() {
+            info!("Replaced handler for method: {}", method);
+        }
+        self
+    }
+
+    /// Find the plugin declaring `method` among the registry's current plugins.
+    ///
+    /// Plugin methods are resolved on every call, so plugins added, replaced or
+    /// removed by a reload take effect immediately. They must be namespaced
+    /// (contain a `/`) and never shadow a registered method.
+    pub fn resolve_plugin_method(&self, registry: &PluginRegistry, method: &str) -> Option<Arc<dyn McpPlugin>> {
+        if !method.contains('/') || self.has_method(method) {
+            return None;
+        }
+        let mut plugins: Vec<Arc<dyn McpPlugin>> = registry
+            .get_all_plugins()
+            .into_iter()
+            .filter(|plugin| plugin.custom_methods().iter().any(|m| m == method))
+            .collect();
+        plugins.sort_by(|a, b| a.name().cmp(b.name()));
+        if plugins.len() > 1 {
+            warn!("Method '{}' is declared by several plugins, using '{}'", method, plugins[0].name());
+        }
+        plugins.into_iter().next()
+    }
+
+    /// Check whether a handler is registered for `method`
+    pub fn has_method(&self, method: &str) -> bool {
+        self.methods.contains_key(method)
+    }
+
+    /// Names of all registered methods and of the methods the registry's plugins declare
+    pub fn methods(&self, registry: &PluginRegistry) -> Vec<String> {
+        let mut names: Vec<String> = self.methods.keys().cloned().collect();
+        for plugin in registry.get_all_plugins() {
+            names.extend(plugin.custom_methods().into_iter().filter(|m| self.resolve_plugin_method(registry, m).is_some()));
+        }
+        names.sort();
+        names.dedup();
+        names
+    }
+
+    /// Dispatch a request to its handler and return the JSON-RPC result
+    pub async fn dispatch(&self, ctx: RequestContext, request: &MCPRequest) -> Result<Value, MCPError> {
+        let span = tracing::info_span!(
+            "process_mcp_req",
+            id = %request.id,
+            method = %request.method,
+            client = %ctx.client_id,
+            otel.status_code = tracing::field::Empty
+        );
+        // A trace context in `_meta` names the caller's span directly, so it wins over the HTTP headers
+        telemetry::continue_from_meta(&span, request.params.as_ref().and_then(|p| p.get("_meta")));
+        let outcome = async {
+            info!("Processing");
+            match self.methods.get(&request.method) {
+                Some(handler) => handler(ctx, request.params.clone().unwrap_or(Value::Null)).await,
+                None => match self.resolve_plugin_method(&ctx.registry, &request.method) {
+                    Some(plugin) => {
+                        let params = request.params.clone().unwrap_or(Value::Null);
+                        tracing::info_span!("plugin_method", plugin = plugin.name(), method = %request.method)
+                            .in_scope(|| plugin.handle_method(&request.method, &params))
+                            .map_err(|e| MCPError::new(INTERNAL_ERROR, e.to_string()))
+                    }
+                    None => {
+                        warn!("Method not found: {}", request.method);
+                        Err(MCPError::new(METHOD_NOT_FOUND, format!("Method not found: {}", request.method)))
+                    }
+                },
+            }
+        }
+        .instrument(span.clone())
+        .await;
+        if outcome.is_err() {
+            span.record("otel.status_code", "ERROR");
+        }
+        outcome
+    }
+}
+
+impl Default for MethodRouter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
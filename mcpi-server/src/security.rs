@@ -0,0 +1,201 @@
+// mcpi-server/src/security.rs
+//
+// Network exposure settings: where the server binds, which browser origins may
+// talk to the MCP endpoints, and the CORS policy of each route group. Validating
+// `Origin` on the MCP endpoints keeps a malicious page from reaching a local
+// server through DNS rebinding.
+
+use crate::config::check_keys;
+use axum::{
+    body::Body,
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::warn;
+
+/// Origins accepted on the MCP endpoints unless configured otherwise: local pages
+/// and browser extensions. Entries ending in `*` match by prefix.
+const DEFAULT_ALLOWED_ORIGINS: &[&str] = &[
+    "http://localhost",
+    "http://localhost:*",
+    "https://localhost",
+    "https://localhost:*",
+    "http://127.0.0.1",
+    "http://127.0.0.1:*",
+    "https://127.0.0.1",
+    "https://127.0.0.1:*",
+    "chrome-extension://*",
+];
+
+/// A list of allowed origins. `*` allows every origin.
+#[derive(Debug, Clone)]
+pub struct OriginPolicy {
+    allowed: Vec<String>,
+}
+
+impl OriginPolicy {
+    pub fn new(allowed: Vec<String>) -> Self {
+        OriginPolicy { allowed }
+    }
+
+    pub fn allows(&self, origin: &str) -> bool {
+        self.allowed.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => origin.starts_with(prefix),
+            None => pattern == origin,
+        })
+    }
+
+    fn allows_any(&self) -> bool {
+        self.allowed.iter().any(|pattern| pattern == "*")
+    }
+
+    fn is_empty(&self) -> bool {
+        self.allowed.is_empty()
+    }
+}
+
+/// CORS policies per route group. An empty policy sends no CORS headers, so
+/// only same-origin pages can read responses.
+#[derive(Debug, Clone)]
+pub struct CorsConfig {
+    pub mcp: OriginPolicy,
+    pub discovery: OriginPolicy,
+    pub admin: OriginPolicy,
+}
+
+/// Settings from the optional `http` block of the server config
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    pub bind_address: IpAddr,
+    pub port: u16,
+    /// Whether to listen on TCP at all; off when only a Unix socket is wanted
+    pub tcp_enabled: bool,
+    /// Origins allowed to call the MCP endpoints; requests without `Origin` are always allowed
+    pub allowed_origins: OriginPolicy,
+    pub cors: CorsConfig,
+}
+
+impl HttpConfig {
+    /// Read the `http` block, e.g.
+    /// `{ "bind_address": "0.0.0.0", "port": 3001, "tcp": true, "allowed_origins": ["https://example.com"],
+    ///    "cors": { "mcp": ["https://example.com"], "discovery": ["*"], "admin": [] } }`.
+    ///
+    /// Debug builds bind to localhost unless told otherwise; release builds bind to
+    /// every interface. The MCP CORS policy defaults to the allowed origins.
+    pub fn from_config(config: Option<&Value>, default_port: u16) -> Result<Self, String> {
+        check_keys(config, "http", &["bind_address", "port", "tcp", "allowed_origins", "cors"])?;
+        let default_bind = if cfg!(debug_assertions) { Ipv4Addr::LOCALHOST } else { Ipv4Addr::UNSPECIFIED };
+        let bind_address = match config.and_then(|c| c.get("bind_address")) {
+            Some(Value::String(address)) => address.parse().map_err(|e| format!("Invalid http.bind_address '{}': {}", address, e))?,
+            Some(other) => return Err(format!("http.bind_address must be a string, got {}", other)),
+            None => IpAddr::V4(default_bind),
+        };
+        let port = match config.and_then(|c| c.get("port")) {
+            Some(value) => value
+                .as_u64()
+                .and_then(|p| u16::try_from(p).ok())
+                .ok_or_else(|| format!("Invalid http.port: {}", value))?,
+            None => default_port,
+        };
+        let tcp_enabled = match config.and_then(|c| c.get("tcp")) {
+            Some(value) => value.as_bool().ok_or_else(|| format!("http.tcp must be a boolean, got {}", value))?,
+            None => true,
+        };
+
+        let allowed_origins = match origin_list(config, "allowed_origins")? {
+            Some(origins) => OriginPolicy::new(origins),
+            None => OriginPolicy::new(DEFAULT_ALLOWED_ORIGINS.iter().map(|o| o.to_string()).collect()),
+        };
+        let cors_config = config.and_then(|c| c.get("cors"));
+        check_keys(cors_config, "http.cors", &["mcp", "discovery", "admin"])?;
+        let cors = CorsConfig {
+            mcp: origin_list(cors_config, "mcp")?.map(OriginPolicy::new).unwrap_or_else(|| allowed_origins.clone()),
+            discovery: origin_list(cors_config, "discovery")?.map(OriginPolicy::new).unwrap_or_else(|| OriginPolicy::new(vec!["*".to_string()])),
+            admin: origin_list(cors_config, "admin")?.map(OriginPolicy::new).unwrap_or_else(|| OriginPolicy::new(vec![])),
+        };
+
+        Ok(HttpConfig { bind_address, port, tcp_enabled, allowed_origins, cors })
+    }
+
+    pub fn socket_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind_address, self.port)
+    }
+}
+
+fn origin_list(config: Option<&Value>, key: &str) -> Result<Option<Vec<String>>, String> {
+    match config.and_then(|c| c.get(key)) {
+        None => Ok(None),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| item.as_str().map(str::to_string).ok_or_else(|| format!("http.{} entries must be strings", key)))
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err(format!("http.{} must be an array of origins", key)),
+    }
+}
+
+/// Build the CORS layer for a route group
+pub fn cors_layer(policy: &OriginPolicy) -> CorsLayer {
+    let layer = CorsLayer::new()
+        .allow_methods([Method::GET, Method::POST, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::CONTENT_TYPE,
+            header::ACCEPT,
+            header::AUTHORIZATION,
+            HeaderName::from_static("mcp-session-id"),
+            HeaderName::from_static("mcp-protocol-version"),
+            HeaderName::from_static("last-event-id"),
+        ])
+        .expose_headers([HeaderName::from_static("mcp-session-id")]);
+
+    if policy.allows_any() {
+        layer.allow_origin(Any)
+    } else if policy.is_empty() {
+        CorsLayer::new()
+    } else {
+        let policy = policy.clone();
+        layer.allow_origin(AllowOrigin::predicate(move |origin: &HeaderValue, _| {
+            origin.to_str().map(|o| policy.allows(o)).unwrap_or(false)
+        }))
+    }
+}
+
+/// Reject requests to the MCP endpoints whose `Origin` is not allowed
+pub async fn validate_origin(State(policy): State<Arc<OriginPolicy>>, request: Request<Body>, next: Next) -> Response {
+    if let Some(origin) = request.headers().get(header::ORIGIN) {
+        let allowed = origin.to_str().map(|o| policy.allows(o)).unwrap_or(false);
+        if !allowed {
+            warn!("Rejected {} {} from origin {:?}", request.method(), request.uri().path(), origin);
+            return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+        }
+    }
+    next.run(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_origin_patterns() {
+        let config = HttpConfig::from_config(None, 3001).unwrap();
+        let policy = &config.allowed_origins;
+        assert!(policy.allows("http://localhost:3001"));
+        assert!(policy.allows("chrome-extension://abcdef"));
+        assert!(!policy.allows("http://localhost.evil.com"));
+        assert!(!policy.allows("https://evil.example"));
+
+        let config = HttpConfig::from_config(Some(&json!({ "allowed_origins": ["https://app.example"] })), 3001).unwrap();
+        assert!(config.allowed_origins.allows("https://app.example"));
+        assert!(!config.allowed_origins.allows("http://localhost:3001"));
+        assert!(config.cors.mcp.allows("https://app.example"));
+        assert!(config.cors.admin.is_empty());
+    }
+}
@@ -0,0 +1,507 @@
+// mcpi-server/src/sessions.rs
+//
+// Streamable HTTP sessions. Each session keeps an event log for its standalone
+// GET stream: every event gets the next id in the session, and the most recent
+// events are buffered so a client reconnecting with `Last-Event-ID` receives
+// whatever it missed instead of silently losing it.
+//
+// Sessions expire after a period without activity or after a fixed lifetime,
+// whichever comes first; a background task calls `SessionStore::reap` to drop them.
+
+use crate::config::check_keys;
+use crate::outbound::{self, OutboundQueues, OutboundReceiver, OutboundSender};
+use crate::router::EventSender;
+use futures::Stream;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Number of events kept per session for replay
+pub const REPLAY_BUFFER_SIZE: usize = 256;
+
+/// Capacity of the queue between request handlers and the event log
+const HANDOFF_CAPACITY: usize = 32;
+
+/// A serialized JSON-RPC message and its event id within the session
+#[derive(Debug, Clone)]
+pub struct SseMessage {
+    pub id: u64,
+    pub data: String,
+}
+
+impl AsRef<str> for SseMessage {
+    fn as_ref(&self) -> &str {
+        &self.data
+    }
+}
+
+/// Session lifetime limits
+#[derive(Debug, Clone)]
+pub struct SessionConfig {
+    /// Expire a session with no requests and no open stream for this long
+    pub idle_timeout: Duration,
+    /// Expire a session this long after it was created, regardless of activity
+    pub absolute_timeout: Duration,
+    /// How often the reaper looks for expired sessions
+    pub reap_interval: Duration,
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        SessionConfig {
+            idle_timeout: Duration::from_secs(30 * 60),
+            absolute_timeout: Duration::from_secs(24 * 60 * 60),
+            reap_interval: Duration::from_secs(60),
+        }
+    }
+}
+
+impl SessionConfig {
+    /// Read the optional `sessions` block of the server config, e.g.
+    /// `{ "idle_timeout_secs": 1800, "absolute_timeout_secs": 86400, "reap_interval_secs": 60 }`.
+    /// Missing keys keep their defaults.
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "sessions", &["idle_timeout_secs", "absolute_timeout_secs", "reap_interval_secs"])?;
+        let mut session_config = SessionConfig::default();
+        let secs = |key: &str| -> Result<Option<Duration>, String> {
+            match config.and_then(|c| c.get(key)) {
+                Some(value) => value
+                    .as_u64()
+                    .filter(|secs| *secs > 0)
+                    .map(|secs| Some(Duration::from_secs(secs)))
+                    .ok_or_else(|| format!("sessions.{} must be a positive number of seconds, got {}", key, value)),
+                None => Ok(None),
+            }
+        };
+        if let Some(idle) = secs("idle_timeout_secs")? {
+            session_config.idle_timeout = idle;
+        }
+        if let Some(absolute) = secs("absolute_timeout_secs")? {
+            session_config.absolute_timeout = absolute;
+        }
+        if let Some(interval) = secs("reap_interval_secs")? {
+            session_config.reap_interval = interval;
+        }
+        Ok(session_config)
+    }
+}
+
+/// One live session or connection, as listed by the admin API
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub id: String,
+    pub transport: &'static str,
+    pub connected_at: chrono::DateTime<chrono::Utc>,
+    pub last_activity: chrono::DateTime<chrono::Utc>,
+    /// HTTP requests on the session, or messages received on a WebSocket
+    pub requests: u64,
+    /// Whether a stream to the client is open
+    pub streaming: bool,
+}
+
+// The wall-clock time of an earlier instant
+fn wall_clock(instant: Instant) -> chrono::DateTime<chrono::Utc> {
+    chrono::Utc::now() - chrono::Duration::from_std(instant.elapsed()).unwrap_or_default()
+}
+
+/// Server-side state of a Streamable HTTP session
+pub struct HttpSession {
+    pub events: Arc<EventLog>,
+    /// Transport that opened the session, e.g. `streamable_http`
+    transport: &'static str,
+    created_at: Instant,
+    last_activity: Mutex<Instant>,
+    // HTTP requests made on the session
+    requests: AtomicU64,
+}
+
+impl HttpSession {
+    pub fn new(session_id: &str, transport: &'static str, queues: Arc<OutboundQueues>) -> Self {
+        let now = Instant::now();
+        HttpSession {
+            events: Arc::new(EventLog::new(session_id, REPLAY_BUFFER_SIZE, queues)),
+            transport,
+            created_at: now,
+            last_activity: Mutex::new(now),
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    fn touch(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn summary(&self, session_id: &str) -> SessionSummary {
+        SessionSummary {
+            id: session_id.to_string(),
+            transport: self.transport,
+            connected_at: wall_clock(self.created_at),
+            last_activity: wall_clock(*self.last_activity.lock().unwrap()),
+            requests: self.requests.load(Ordering::Relaxed),
+            streaming: self.events.has_subscribers(),
+        }
+    }
+
+    fn is_expired(&self, config: &SessionConfig, now: Instant) -> bool {
+        if now.duration_since(self.created_at) >= config.absolute_timeout {
+            return true;
+        }
+        // An open GET stream counts as activity
+        let idle = now.duration_since(*self.last_activity.lock().unwrap());
+        idle >= config.idle_timeout && !self.events.has_subscribers()
+    }
+}
+
+/// All live Streamable HTTP sessions, keyed by session id
+pub struct SessionStore {
+    sessions: RwLock<HashMap<String, HttpSession>>,
+    config: SessionConfig,
+    // Where the GET streams' outbound queues come from
+    queues: Arc<OutboundQueues>,
+}
+
+impl SessionStore {
+    pub fn new(config: SessionConfig, queues: Arc<OutboundQueues>) -> Self {
+        SessionStore { sessions: RwLock::new(HashMap::new()), config, queues }
+    }
+
+    pub fn config(&self) -> &SessionConfig {
+        &self.config
+    }
+
+    /// Start a new session and return its id and event log. Ids are random v4
+    /// UUIDs so they cannot be guessed from other sessions' ids.
+    pub async fn create(&self, transport: &'static str) -> (String, Arc<EventLog>) {
+        let session_id = Uuid::new_v4().to_string();
+        let session = HttpSession::new(&session_id, transport, self.queues.clone());
+        let events = session.events.clone();
+        self.sessions.write().await.insert(session_id.clone(), session);
+        (session_id, events)
+    }
+
+    /// Record activity on a session and return its event log, or None if the
+    /// session is unknown or has expired
+    pub async fn touch(&self, session_id: &str) -> Option<Arc<EventLog>> {
+        let sessions = self.sessions.read().await;
+        let session = sessions.get(session_id)?;
+        if session.is_expired(&self.config, Instant::now()) {
+            return None;
+        }
+        session.touch();
+        Some(session.events.clone())
+    }
+
+    /// A session's event log, without counting it as activity
+    pub async fn events(&self, session_id: &str) -> Option<Arc<EventLog>> {
+        self.sessions.read().await.get(session_id).map(|session| session.events.clone())
+    }
+
+    /// Every live session, oldest first
+    pub async fn list(&self) -> Vec<SessionSummary> {
+        let mut sessions: Vec<SessionSummary> = self.sessions.read().await.iter().map(|(id, session)| session.summary(id)).collect();
+        sessions.sort_by_key(|session| session.connected_at);
+        sessions
+    }
+
+    /// End a session. Returns false if it did not exist.
+    pub async fn remove(&self, session_id: &str) -> bool {
+        self.sessions.write().await.remove(session_id).is_some()
+    }
+
+    pub async fn len(&self) -> usize {
+        self.sessions.read().await.len()
+    }
+
+    /// Number of live sessions opened by each transport
+    pub async fn count_by_transport(&self) -> HashMap<&'static str, usize> {
+        let mut counts = HashMap::new();
+        for session in self.sessions.read().await.values() {
+            *counts.entry(session.transport).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Drop every expired session and return their ids. Open streams on a
+    /// dropped session end once its event log goes away.
+    pub async fn reap(&self) -> Vec<String> {
+        let now = Instant::now();
+        let mut sessions = self.sessions.write().await;
+        let expired: Vec<String> = sessions
+            .iter()
+            .filter(|(_, session)| session.is_expired(&self.config, now))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in &expired {
+            sessions.remove(id);
+            info!("Session {} expired", id);
+        }
+        expired
+    }
+
+    /// Publish a message on every session's GET stream and return how many sessions
+    /// it was sent to. Each session gets it on its own task, so a stream that blocks
+    /// under the Block policy holds up only its own session.
+    pub async fn broadcast(&self, data: String) -> usize {
+        let logs: Vec<Arc<EventLog>> = self.sessions.read().await.values().map(|session| session.events.clone()).collect();
+        for log in &logs {
+            let (log, data) = (log.clone(), data.clone());
+            tokio::spawn(async move { log.publish(data).await });
+        }
+        logs.len()
+    }
+
+    /// Drop every session and return their ids, ending all open streams
+    pub async fn close_all(&self) -> Vec<String> {
+        self.sessions.write().await.drain().map(|(id, _)| id).collect()
+    }
+}
+
+struct LogState {
+    last_id: u64,
+    buffer: VecDeque<SseMessage>,
+    // Queues of the GET streams currently reading the log
+    subscribers: Vec<OutboundSender<SseMessage>>,
+}
+
+/// Ordered, bounded log of the events sent on a session's GET stream
+pub struct EventLog {
+    session_id: String,
+    state: Mutex<LogState>,
+    // Held while delivering, so every subscriber receives events in id order
+    publishing: tokio::sync::Mutex<()>,
+    queues: Arc<OutboundQueues>,
+    capacity: usize,
+}
+
+impl EventLog {
+    pub fn new(session_id: &str, capacity: usize, queues: Arc<OutboundQueues>) -> Self {
+        EventLog {
+            session_id: session_id.to_string(),
+            state: Mutex::new(LogState { last_id: 0, buffer: VecDeque::with_capacity(capacity), subscribers: Vec::new() }),
+            publishing: tokio::sync::Mutex::new(()),
+            queues,
+            capacity,
+        }
+    }
+
+    /// Append a message to the log and deliver it to connected streams, applying
+    /// the backpressure policy to each of them. Returns its event id.
+    pub async fn publish(&self, data: String) -> u64 {
+        let _publishing = self.publishing.lock().await;
+        let (message, subscribers) = {
+            let mut state = self.state.lock().unwrap();
+            state.last_id += 1;
+            let message = SseMessage { id: state.last_id, data };
+            if state.buffer.len() == self.capacity {
+                state.buffer.pop_front();
+            }
+            state.buffer.push_back(message.clone());
+            state.subscribers.retain(|subscriber| !subscriber.is_closed());
+            (message, state.subscribers.clone())
+        };
+        for subscriber in &subscribers {
+            // A subscriber disconnected for falling behind can resume from the buffer
+            subscriber.send(message.clone()).await;
+        }
+        message.id
+    }
+
+    /// Whether any GET stream is currently reading this log
+    pub fn has_subscribers(&self) -> bool {
+        self.state.lock().unwrap().subscribers.iter().any(|subscriber| !subscriber.is_closed())
+    }
+
+    /// Sender whose messages are published to this log, for request handlers
+    /// that emit related messages while the client is not reading the POST response
+    pub fn sender(self: &Arc<Self>) -> EventSender {
+        let (tx, mut rx) = outbound::handoff(HANDOFF_CAPACITY);
+        let log = self.clone();
+        tokio::spawn(async move {
+            while let Some(message) = rx.recv().await {
+                log.publish(message).await;
+            }
+        });
+        tx
+    }
+
+    /// Stream of events after `last_event_id` (replayed from the buffer), followed
+    /// by live events. Without `last_event_id` only new events are streamed.
+    /// The stream ends once the session is dropped, or when the client falls so
+    /// far behind that the backpressure policy disconnects it.
+    pub fn subscribe(self: &Arc<Self>, last_event_id: Option<u64>) -> impl Stream<Item = SseMessage> + Send + 'static {
+        let mut state = self.state.lock().unwrap();
+        let (tx, live) = self.queues.open::<SseMessage>(&self.session_id);
+        state.subscribers.push(tx);
+        // An id from the future (e.g. from before a restart) resumes from the present
+        let last_sent = last_event_id.map_or(state.last_id, |id| id.min(state.last_id));
+        let pending = Self::buffered_after(&state, last_sent);
+        drop(state);
+
+        let subscription = Subscription { live, pending, last_sent };
+        futures::stream::unfold(subscription, |mut sub| async move {
+            loop {
+                if let Some(message) = sub.pending.pop_front() {
+                    sub.last_sent = message.id;
+                    return Some((message, sub));
+                }
+                match sub.live.recv().await {
+                    // Already delivered from the replay buffer
+                    Some(message) if message.id <= sub.last_sent => continue,
+                    Some(message) => {
+                        sub.last_sent = message.id;
+                        return Some((message, sub));
+                    }
+                    None => {
+                        if sub.live.overflowed() {
+                            warn!("Closing a stream that fell behind after event {}; it can resume with Last-Event-ID", sub.last_sent);
+                        }
+                        return None;
+                    }
+                }
+            }
+        })
+    }
+
+    fn buffered_after(state: &LogState, after: u64) -> VecDeque<SseMessage> {
+        if let Some(oldest) = state.buffer.front() {
+            if oldest.id > after + 1 {
+                warn!("Events {}..{} are no longer buffered and cannot be replayed", after + 1, oldest.id - 1);
+            }
+        }
+        state.buffer.iter().filter(|m| m.id > after).cloned().collect()
+    }
+}
+
+struct Subscription {
+    live: OutboundReceiver<SseMessage>,
+    pending: VecDeque<SseMessage>,
+    last_sent: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::{BackpressurePolicy, OutboundConfig};
+    use futures::StreamExt;
+
+    fn queues(queue_capacity: usize, policy: BackpressurePolicy) -> Arc<OutboundQueues> {
+        Arc::new(OutboundQueues::new(OutboundConfig { queue_capacity, policy }))
+    }
+
+    #[tokio::test]
+    async fn test_replay_after_last_event_id() {
+        let log = Arc::new(EventLog::new("s", 4, queues(16, BackpressurePolicy::Block)));
+        for i in 1..=6 {
+            log.publish(format!("m{}", i)).await;
+        }
+
+        // Ids 3..=6 are still buffered; a client that saw id 4 gets 5 and 6 then live events
+        let mut stream = Box::pin(log.subscribe(Some(4)));
+        log.publish("m7".to_string()).await;
+        let ids: Vec<u64> = stream.by_ref().take(3).map(|m| m.id).collect().await;
+        assert_eq!(ids, vec![5, 6, 7]);
+
+        // A fresh subscriber only sees new events
+        let mut fresh = Box::pin(log.subscribe(None));
+        log.publish("m8".to_string()).await;
+        assert_eq!(fresh.next().await.unwrap().data, "m8");
+    }
+
+    #[tokio::test]
+    async fn test_stalled_stream_does_not_block_other_sessions() {
+        let store = SessionStore::new(SessionConfig::default(), queues(1, BackpressurePolicy::Block));
+        let (_, stalled) = store.create("streamable_http").await;
+        let (_, reading) = store.create("streamable_http").await;
+        // Never read, so its queue is full after the first message
+        let _stalled_stream = stalled.subscribe(None);
+        let mut stream = Box::pin(reading.subscribe(None));
+
+        for i in 1..=3 {
+            let sent = tokio::time::timeout(Duration::from_secs(1), store.broadcast(format!("m{}", i))).await;
+            assert_eq!(sent.unwrap(), 2);
+            let received = tokio::time::timeout(Duration::from_secs(1), stream.next()).await;
+            assert_eq!(received.unwrap().unwrap().data, format!("m{}", i));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reap_expired_sessions() {
+        let store = SessionStore::new(
+            SessionConfig {
+                idle_timeout: Duration::from_millis(20),
+                absolute_timeout: Duration::from_secs(60),
+                reap_interval: Duration::from_secs(60),
+            },
+            queues(16, BackpressurePolicy::Block),
+        );
+        let (idle_id, _) = store.create("streamable_http").await;
+        let (streaming_id, events) = store.create("streamable_http").await;
+        let _stream = events.subscribe(None);
+        drop(events);
+
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(store.touch(&idle_id).await.is_none());
+        assert_eq!(store.reap().await, vec![idle_id]);
+        // A session with an open stream is not idle
+        assert!(store.touch(&streaming_id).await.is_some());
+        assert_eq!(store.len().await, 1);
+    }
+
+    #[tokio::test]
+    async fn test_list_sessions() {
+        let store = SessionStore::new(SessionConfig::default(), queues(16, BackpressurePolicy::Block));
+        let (http_id, _) = store.create("streamable_http").await;
+        let (sse_id, events) = store.create("legacy_sse").await;
+        let _stream = events.subscribe(None);
+        store.touch(&http_id).await;
+        store.touch(&http_id).await;
+
+        let sessions = store.list().await;
+        let http = sessions.iter().find(|s| s.id == http_id).unwrap();
+        assert_eq!((http.transport, http.requests, http.streaming), ("streamable_http", 2, false));
+        let sse = sessions.iter().find(|s| s.id == sse_id).unwrap();
+        assert_eq!((sse.transport, sse.requests, sse.streaming), ("legacy_sse", 0, true));
+        assert_eq!(store.count_by_transport().await.get("legacy_sse"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_misses_nothing() {
+        // Blocking: the publisher waits for the reader, so nothing is skipped
+        let log = Arc::new(EventLog::new("s", REPLAY_BUFFER_SIZE, queues(4, BackpressurePolicy::Block)));
+        let stream = log.subscribe(None);
+        let publisher = log.clone();
+        tokio::spawn(async move {
+            for i in 1..=20 {
+                publisher.publish(i.to_string()).await;
+            }
+        });
+        let ids: Vec<u64> = stream.take(20).map(|m| m.id).collect().await;
+        assert_eq!(ids, (1..=20).collect::<Vec<_>>());
+
+        // Disconnecting: the stream ends, and resuming with Last-Event-ID replays the rest
+        let log = Arc::new(EventLog::new("s", REPLAY_BUFFER_SIZE, queues(4, BackpressurePolicy::Disconnect)));
+        let stream = log.subscribe(None);
+        for i in 1..=10 {
+            log.publish(i.to_string()).await;
+        }
+        let seen: Vec<u64> = stream.map(|m| m.id).collect().await;
+        assert!(seen.len() < 10);
+        let resumed: Vec<u64> = log.subscribe(Some(seen.last().copied().unwrap_or(0))).take(10 - seen.len()).map(|m| m.id).collect().await;
+        assert_eq!(seen.into_iter().chain(resumed).collect::<Vec<_>>(), (1..=10).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_session_config_rejects_non_positive_values() {
+        let config = SessionConfig::from_config(Some(&serde_json::json!({ "idle_timeout_secs": 90 }))).unwrap();
+        assert_eq!(config.idle_timeout, Duration::from_secs(90));
+        for bad in [serde_json::json!({ "reap_interval_secs": 0 }), serde_json::json!({ "idle_timeout_secs": "60" })] {
+            assert!(SessionConfig::from_config(Some(&bad)).is_err());
+        }
+    }
+}
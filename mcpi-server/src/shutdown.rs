@@ -0,0 +1,167 @@
+// mcpi-server/src/shutdown.rs
+//
+// Graceful shutdown. On Ctrl+C or SIGTERM the server drains: new sessions are
+// refused while requests already in flight get up to `drain_timeout` to finish.
+// Then WebSocket clients get a close frame, SSE streams end, the transports stop
+// and plugins run their shutdown hooks.
+
+use crate::config::check_keys;
+use crate::AppState;
+use serde_json::Value;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::{info, warn};
+
+const DEFAULT_DRAIN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Reason sent in the close frame of WebSocket connections
+pub const SHUTDOWN_REASON: &str = "Server shutting down";
+
+/// Settings from the optional `shutdown` block of the server config
+#[derive(Debug, Clone)]
+pub struct ShutdownConfig {
+    /// How long in-flight requests may take to finish once shutdown starts
+    pub drain_timeout: Duration,
+}
+
+impl ShutdownConfig {
+    /// Read the `shutdown` block, e.g. `{ "drain_timeout_secs": 30 }`
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "shutdown", &["drain_timeout_secs"])?;
+        let drain_timeout = match config.and_then(|c| c.get("drain_timeout_secs")) {
+            Some(value) => Duration::from_secs(
+                value.as_u64().ok_or_else(|| format!("shutdown.drain_timeout_secs must be a number of seconds, got {}", value))?,
+            ),
+            None => DEFAULT_DRAIN_TIMEOUT,
+        };
+        Ok(ShutdownConfig { drain_timeout })
+    }
+}
+
+/// Tracks requests in flight and whether the server is draining
+pub struct Drain {
+    draining: AtomicBool,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl Drain {
+    pub fn new() -> Self {
+        Drain { draining: AtomicBool::new(false), in_flight: AtomicUsize::new(0), idle: Notify::new() }
+    }
+
+    /// Whether new sessions should be refused
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::SeqCst)
+    }
+
+    pub fn begin(&self) {
+        self.draining.store(true, Ordering::SeqCst);
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Count a request as in flight until the returned guard is dropped
+    pub fn track(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight { drain: self.clone() }
+    }
+
+    /// Wait until nothing is in flight. Returns false if `timeout` elapsed first.
+    pub async fn wait_idle(&self, timeout: Duration) -> bool {
+        tokio::time::timeout(timeout, async {
+            loop {
+                // Created before the check so a request finishing in between is not missed
+                let idle = self.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                idle.await;
+            }
+        })
+        .await
+        .is_ok()
+    }
+}
+
+impl Default for Drain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct InFlight {
+    drain: Arc<Drain>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.drain.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.drain.idle.notify_waiters();
+        }
+    }
+}
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM
+pub async fn shutdown_signal() {
+    let ctrl_c = async { tokio::signal::ctrl_c().await.expect("Failed to install Ctrl+C handler") };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => info!("Ctrl+C received, shutting down..."),
+        _ = terminate => info!("SIGTERM received, shutting down..."),
+    }
+}
+
+/// Refuse new sessions, let in-flight requests finish, then close every tenant's
+/// WebSocket connections and HTTP sessions so the transports can stop
+pub async fn drain(drain: &Drain, tenants: &[Arc<AppState>], config: &ShutdownConfig) {
+    drain.begin();
+    info!("Draining {} in-flight requests (up to {:?})", drain.in_flight(), config.drain_timeout);
+    if !drain.wait_idle(config.drain_timeout).await {
+        warn!("Drain timeout elapsed with {} requests still in flight", drain.in_flight());
+    }
+
+    for state in tenants {
+        let closed_ws = state.ws_connections.close_all(SHUTDOWN_REASON);
+        // Dropping a session's event log ends its SSE streams
+        let closed_sessions = state.http_sessions.close_all().await;
+        for session_id in &closed_sessions {
+            state.message_handler.forget_client(session_id);
+        }
+        info!("Closed {} WebSocket connections and {} HTTP sessions of {}", closed_ws, closed_sessions.len(), state.tenant);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_wait_idle_waits_for_in_flight_requests() {
+        let drain = Arc::new(Drain::new());
+        assert!(drain.wait_idle(Duration::from_millis(10)).await);
+
+        let request = drain.track();
+        assert!(!drain.wait_idle(Duration::from_millis(10)).await);
+
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            drop(request);
+        });
+        assert!(drain.wait_idle(Duration::from_secs(5)).await);
+        assert_eq!(drain.in_flight(), 0);
+    }
+}
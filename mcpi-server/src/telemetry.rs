@@ -0,0 +1,318 @@
+// mcpi-server/src/telemetry.rs
+//
+// Optional OpenTelemetry export of the server's tracing spans, to an OTLP
+// collector over HTTP or to a JSONL file. W3C trace context (`traceparent`,
+// `tracestate`) is read from HTTP headers and from a request's `params._meta`,
+// so server and plugin spans join the trace of the agent that made the call.
+
+use crate::config::check_keys;
+use axum::http::HeaderMap;
+use opentelemetry::propagation::Extractor;
+use opentelemetry::trace::{SpanId, Status, TraceContextExt, TracerProvider as _};
+use opentelemetry::{global, Context, KeyValue};
+use opentelemetry_otlp::{WithExportConfig, WithHttpConfig};
+use opentelemetry_sdk::error::{OTelSdkError, OTelSdkResult};
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Sampler, SdkTracerProvider, SpanData, SpanExporter};
+use opentelemetry_sdk::Resource;
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tracing::{warn, Span, Subscriber};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::filter::filter_fn;
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::Layer;
+
+const DEFAULT_ENDPOINT: &str = "http://localhost:4318/v1/traces";
+const DEFAULT_SERVICE_NAME: &str = "mcpi-server";
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where finished spans are sent
+#[derive(Debug, Clone)]
+pub enum TelemetryExporter {
+    /// OTLP over HTTP with protobuf bodies, e.g. to a local collector
+    Otlp { endpoint: String, headers: HashMap<String, String> },
+    /// One JSON object per span, appended to a file
+    File(PathBuf),
+}
+
+/// Settings from the optional `telemetry` block of the server config
+#[derive(Debug, Clone)]
+pub struct TelemetryConfig {
+    pub exporter: TelemetryExporter,
+    pub service_name: String,
+    /// Share of new traces recorded; traces started by a caller follow its sampling decision
+    pub sample_ratio: f64,
+}
+
+impl TelemetryConfig {
+    /// Read the `telemetry` block, e.g. `{ "exporter": "otlp", "endpoint": "http://localhost:4318/v1/traces" }`
+    /// or `{ "exporter": "file", "path": "traces/spans.jsonl" }`. Optional keys are `headers`,
+    /// `service_name` and `sample_ratio`. Returns None when the block is absent or `enabled` is false.
+    pub fn from_config(config: Option<&Value>) -> Result<Option<Self>, String> {
+        let Some(config) = config else { return Ok(None) };
+        check_keys(Some(config), "telemetry", &["enabled", "exporter", "endpoint", "headers", "path", "service_name", "sample_ratio"])?;
+        if config.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+            return Ok(None);
+        }
+        let string = |key: &str| match config.get(key) {
+            Some(Value::String(value)) if !value.is_empty() => Ok(Some(value.clone())),
+            Some(other) => Err(format!("telemetry.{} must be a non-empty string, got {}", key, other)),
+            None => Ok(None),
+        };
+        let exporter = match string("exporter")?.as_deref() {
+            Some("otlp") | None => {
+                let headers = match config.get("headers") {
+                    Some(Value::Object(headers)) => headers
+                        .iter()
+                        .map(|(name, value)| match value {
+                            Value::String(value) => Ok((name.clone(), value.clone())),
+                            other => Err(format!("telemetry.headers.{} must be a string, got {}", name, other)),
+                        })
+                        .collect::<Result<_, _>>()?,
+                    Some(other) => return Err(format!("telemetry.headers must map header names to values, got {}", other)),
+                    None => HashMap::new(),
+                };
+                TelemetryExporter::Otlp { endpoint: string("endpoint")?.unwrap_or_else(|| DEFAULT_ENDPOINT.to_string()), headers }
+            }
+            Some("file") => TelemetryExporter::File(PathBuf::from(string("path")?.ok_or("telemetry.path is required for the file exporter")?)),
+            Some(other) => return Err(format!("telemetry.exporter must be \"otlp\" or \"file\", got \"{}\"", other)),
+        };
+        let sample_ratio = match config.get("sample_ratio") {
+            Some(value) => value
+                .as_f64()
+                .filter(|ratio| (0.0..=1.0).contains(ratio))
+                .ok_or_else(|| format!("telemetry.sample_ratio must be a number from 0 to 1, got {}", value))?,
+            None => 1.0,
+        };
+        Ok(Some(TelemetryConfig {
+            exporter,
+            service_name: string("service_name")?.unwrap_or_else(|| DEFAULT_SERVICE_NAME.to_string()),
+            sample_ratio,
+        }))
+    }
+
+    /// Create the exporter and install the W3C trace context propagator
+    pub fn build(&self) -> Result<Telemetry, String> {
+        let resource = Resource::builder().with_service_name(self.service_name.clone()).build();
+        let sampler = Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(self.sample_ratio)));
+        let builder = SdkTracerProvider::builder().with_resource(resource).with_sampler(sampler);
+        let provider = match &self.exporter {
+            TelemetryExporter::Otlp { endpoint, headers } => {
+                let exporter = opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .with_endpoint(endpoint.clone())
+                    .with_headers(headers.clone())
+                    .with_timeout(EXPORT_TIMEOUT)
+                    .build()
+                    .map_err(|e| format!("Cannot create the OTLP exporter for {}: {}", endpoint, e))?;
+                builder.with_batch_exporter(exporter).build()
+            }
+            TelemetryExporter::File(path) => {
+                let exporter = FileExporter::open(path.clone()).map_err(|e| format!("Cannot open trace file {}: {}", path.display(), e))?;
+                builder.with_batch_exporter(exporter).build()
+            }
+        };
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        Ok(Telemetry { provider, description: self.describe() })
+    }
+
+    fn describe(&self) -> String {
+        match &self.exporter {
+            TelemetryExporter::Otlp { endpoint, .. } => format!("OTLP collector at {}", endpoint),
+            TelemetryExporter::File(path) => format!("file {}", path.display()),
+        }
+    }
+}
+
+/// The running exporter; spans reach it through `layer`
+pub struct Telemetry {
+    provider: SdkTracerProvider,
+    description: String,
+}
+
+impl Telemetry {
+    /// Subscriber layer that turns the server's own spans into OpenTelemetry spans.
+    /// Other crates are left out, so the exporter's HTTP client is never traced.
+    pub fn layer<S>(&self) -> impl Layer<S>
+    where
+        S: Subscriber + for<'a> LookupSpan<'a>,
+    {
+        tracing_opentelemetry::layer()
+            .with_tracer(self.provider.tracer(DEFAULT_SERVICE_NAME))
+            .with_filter(filter_fn(|metadata| metadata.target().starts_with("mcpi")))
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Export the spans still buffered; called once at shutdown
+    pub fn shutdown(&self) {
+        if let Err(e) = self.provider.shutdown() {
+            warn!("Failed to flush telemetry: {}", e);
+        }
+    }
+}
+
+// --- Context Propagation ---
+
+struct HeaderExtractor<'a>(&'a HeaderMap);
+
+impl Extractor for HeaderExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.to_str().ok())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(|name| name.as_str()).collect()
+    }
+}
+
+struct MetaExtractor<'a>(&'a Map<String, Value>);
+
+impl Extractor for MetaExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(|value| value.as_str())
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+// The remote parent described by a carrier, if it holds a valid `traceparent`.
+// Without telemetry the global propagator is a no-op and this is always None.
+fn remote_parent(carrier: &dyn Extractor) -> Option<Context> {
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(carrier));
+    context.span().span_context().is_valid().then_some(context)
+}
+
+/// Make `span` a child of the trace context in a request's `_meta`, if any
+pub fn continue_from_meta(span: &Span, meta: Option<&Value>) {
+    if let Some(parent) = meta.and_then(|m| m.as_object()).and_then(|meta| remote_parent(&MetaExtractor(meta))) {
+        let _ = span.set_parent(parent);
+    }
+}
+
+/// Span for one MCP message received over HTTP, continuing the caller's trace
+/// from a `traceparent` header
+pub fn http_span(headers: &HeaderMap, transport: &'static str) -> Span {
+    let span = tracing::info_span!("mcp_http_request", otel.kind = "server", transport);
+    if let Some(parent) = remote_parent(&HeaderExtractor(headers)) {
+        let _ = span.set_parent(parent);
+    }
+    span
+}
+
+// --- File Exporter ---
+
+#[derive(Debug)]
+struct FileExporter {
+    file: Mutex<File>,
+    service_name: Mutex<Option<String>>,
+}
+
+impl FileExporter {
+    fn open(path: PathBuf) -> std::io::Result<Self> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        Ok(FileExporter { file: Mutex::new(file), service_name: Mutex::new(None) })
+    }
+
+    fn record(&self, span: &SpanData) -> Value {
+        let parent = (span.parent_span_id != SpanId::INVALID).then(|| span.parent_span_id.to_string());
+        let (status, status_message) = match &span.status {
+            Status::Unset => ("unset", None),
+            Status::Ok => ("ok", None),
+            Status::Error { description } => ("error", Some(description.to_string())),
+        };
+        json!({
+            "service": *self.service_name.lock().unwrap(),
+            "trace_id": span.span_context.trace_id().to_string(),
+            "span_id": span.span_context.span_id().to_string(),
+            "parent_span_id": parent,
+            "name": span.name,
+            "kind": format!("{:?}", span.span_kind).to_lowercase(),
+            "start_time_unix_nano": unix_nanos(span.start_time),
+            "end_time_unix_nano": unix_nanos(span.end_time),
+            "status": status,
+            "status_message": status_message,
+            "attributes": attributes(&span.attributes),
+            "events": span.events.iter().map(|event| json!({
+                "name": event.name,
+                "time_unix_nano": unix_nanos(event.timestamp),
+                "attributes": attributes(&event.attributes),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+impl SpanExporter for FileExporter {
+    async fn export(&self, batch: Vec<SpanData>) -> OTelSdkResult {
+        let mut lines = String::new();
+        for span in &batch {
+            lines.push_str(&self.record(span).to_string());
+            lines.push('\n');
+        }
+        let mut file = self.file.lock().unwrap();
+        file.write_all(lines.as_bytes()).map_err(|e| OTelSdkError::InternalFailure(e.to_string()))
+    }
+
+    fn set_resource(&mut self, resource: &Resource) {
+        let name = resource.get(&opentelemetry::Key::from_static_str("service.name")).map(|name| name.to_string());
+        *self.service_name.lock().unwrap() = name;
+    }
+}
+
+fn unix_nanos(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH).map(|d| d.as_nanos() as u64).unwrap_or(0)
+}
+
+fn attributes(attributes: &[KeyValue]) -> Value {
+    let map = attributes
+        .iter()
+        .map(|kv| {
+            let value = match &kv.value {
+                opentelemetry::Value::Bool(b) => json!(b),
+                opentelemetry::Value::I64(i) => json!(i),
+                opentelemetry::Value::F64(f) => json!(f),
+                other => json!(other.to_string()),
+            };
+            (kv.key.to_string(), value)
+        })
+        .collect();
+    Value::Object(map)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trace_context_from_meta_and_headers() {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let traceparent = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+        let meta = json!({ "traceparent": traceparent, "progressToken": 1 });
+        let parent = remote_parent(&MetaExtractor(meta.as_object().unwrap())).unwrap();
+        assert_eq!(parent.span().span_context().trace_id().to_string(), "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert!(parent.span().span_context().is_remote());
+
+        let mut headers = HeaderMap::new();
+        headers.insert("traceparent", traceparent.parse().unwrap());
+        assert!(remote_parent(&HeaderExtractor(&headers)).is_some());
+        headers.insert("traceparent", "garbage".parse().unwrap());
+        assert!(remote_parent(&HeaderExtractor(&headers)).is_none());
+
+        assert!(TelemetryConfig::from_config(Some(&json!({ "exporter": "file" }))).is_err());
+        assert!(TelemetryConfig::from_config(Some(&json!({ "sample_ratio": 2 }))).is_err());
+    }
+}
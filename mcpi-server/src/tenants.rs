@@ -0,0 +1,122 @@
+// mcpi-server/src/tenants.rs
+//
+// Multi-tenant hosting. Each entry of the `tenants` block is a provider with its
+// own data directory, branding, plugins, discovery document and sessions, served
+// from the same process and port. A request goes to the first tenant whose hosts
+// and path prefix both match it; anything else is served by the root configuration.
+
+use crate::config::check_keys;
+use serde_json::Value;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+/// One entry of the `tenants` block
+#[derive(Debug, Clone)]
+pub struct TenantConfig {
+    pub name: String,
+    /// `Host` header values served by this tenant, without port; empty matches any host
+    pub hosts: Vec<String>,
+    /// Path the tenant's routes are mounted under, e.g. `/shop-a`
+    pub path_prefix: Option<String>,
+    pub data_dir: PathBuf,
+    /// Overrides the `plugins` block of the tenant's own data file
+    pub plugins: Option<Value>,
+}
+
+impl TenantConfig {
+    /// Read the `tenants` block, e.g.
+    /// `[{ "name": "shop-a", "hosts": ["shop-a.example.com"], "data_dir": "tenants/shop-a" },
+    ///   { "name": "shop-b", "path_prefix": "/shop-b", "data_dir": "tenants/shop-b", "plugins": { "disabled": ["weather_forecast"] } }]`
+    pub fn list_from_config(config: Option<&Value>) -> Result<Vec<Self>, String> {
+        let Some(config) = config else { return Ok(Vec::new()) };
+        let entries = config.as_array().ok_or_else(|| format!("tenants must be an array, got {}", config))?;
+        let mut tenants = Vec::new();
+        let mut names = HashSet::new();
+        let mut routes = HashSet::new();
+        for entry in entries {
+            let tenant = Self::from_entry(entry)?;
+            if !names.insert(tenant.name.clone()) {
+                return Err(format!("Duplicate tenant name '{}'", tenant.name));
+            }
+            let hosts = if tenant.hosts.is_empty() { vec![String::new()] } else { tenant.hosts.clone() };
+            for host in hosts {
+                if !routes.insert((host.clone(), tenant.path_prefix.clone())) {
+                    return Err(format!("Tenant '{}' has the same host and path prefix as another tenant", tenant.name));
+                }
+            }
+            tenants.push(tenant);
+        }
+        Ok(tenants)
+    }
+
+    fn from_entry(entry: &Value) -> Result<Self, String> {
+        let name = entry
+            .get("name")
+            .and_then(|n| n.as_str())
+            .filter(|n| !n.is_empty())
+            .ok_or_else(|| format!("Every tenant needs a name, got {}", entry))?
+            .to_string();
+        let field = |key: &str| format!("tenants.{}.{}", name, key);
+        check_keys(Some(entry), &format!("tenants.{}", name), &["name", "hosts", "path_prefix", "data_dir", "plugins"])?;
+
+        let hosts = match entry.get("hosts") {
+            None => Vec::new(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|h| h.as_str().map(|h| h.to_ascii_lowercase()).ok_or_else(|| format!("{} entries must be strings", field("hosts"))))
+                .collect::<Result<_, _>>()?,
+            Some(other) => return Err(format!("{} must be an array of host names, got {}", field("hosts"), other)),
+        };
+        let path_prefix = match entry.get("path_prefix") {
+            None => None,
+            Some(Value::String(prefix)) if prefix.starts_with('/') && prefix.len() > 1 && !prefix.ends_with('/') => Some(prefix.clone()),
+            Some(other) => return Err(format!("{} must look like \"/name\", got {}", field("path_prefix"), other)),
+        };
+        if hosts.is_empty() && path_prefix.is_none() {
+            return Err(format!("Tenant '{}' needs hosts, a path_prefix or both", name));
+        }
+        let data_dir = match entry.get("data_dir") {
+            Some(Value::String(dir)) => PathBuf::from(dir),
+            Some(other) => return Err(format!("{} must be a string, got {}", field("data_dir"), other)),
+            None => return Err(format!("{} is required", field("data_dir"))),
+        };
+        Ok(TenantConfig { name, hosts, path_prefix, data_dir, plugins: entry.get("plugins").cloned() })
+    }
+
+    /// Whether a request for `host` (without port) and `path` is addressed to this tenant
+    pub fn matches(&self, host: Option<&str>, path: &str) -> bool {
+        let host_matches = self.hosts.is_empty() || host.is_some_and(|host| self.hosts.iter().any(|h| h.eq_ignore_ascii_case(host)));
+        let path_matches = match &self.path_prefix {
+            Some(prefix) => path.strip_prefix(prefix.as_str()).is_some_and(|rest| rest.is_empty() || rest.starts_with('/')),
+            None => true,
+        };
+        host_matches && path_matches
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_tenant_matching() {
+        let tenants = TenantConfig::list_from_config(Some(&json!([
+            { "name": "a", "hosts": ["Shop-A.example.com"], "data_dir": "a" },
+            { "name": "b", "path_prefix": "/shop-b", "data_dir": "b" },
+        ])))
+        .unwrap();
+        assert!(tenants[0].matches(Some("shop-a.example.com"), "/mcp"));
+        assert!(!tenants[0].matches(Some("other.example.com"), "/mcp"));
+        assert!(tenants[1].matches(Some("anything"), "/shop-b/mcp"));
+        assert!(tenants[1].matches(None, "/shop-b"));
+        assert!(!tenants[1].matches(None, "/shop-bb/mcp"));
+
+        assert!(TenantConfig::list_from_config(Some(&json!([{ "name": "c", "data_dir": "c" }]))).is_err());
+        assert!(TenantConfig::list_from_config(Some(&json!([
+            { "name": "d", "path_prefix": "/x", "data_dir": "d" },
+            { "name": "e", "path_prefix": "/x", "data_dir": "e" },
+        ])))
+        .is_err());
+    }
+}
@@ -0,0 +1,189 @@
+// mcpi-server/src/tls.rs
+//
+// Optional TLS termination (HTTPS and WSS) using rustls. Certificates are read
+// from PEM files and re-read on SIGHUP, so renewed certificates take effect
+// without a restart. For local testing the server can generate a self-signed
+// certificate for localhost. With a client CA configured, the listener also
+// requires every client to present a certificate signed by it (mTLS).
+
+use crate::config::check_keys;
+use axum_server::tls_rustls::RustlsConfig;
+use serde_json::Value;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tracing::{error, info, warn};
+
+/// Where generated self-signed certificates are written unless paths are configured,
+/// relative to the data directory
+const DEFAULT_SELF_SIGNED_CERT: &str = "server/tls/cert.pem";
+const DEFAULT_SELF_SIGNED_KEY: &str = "server/tls/key.pem";
+
+/// Settings from the optional `tls` block of the server config
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// Generate a self-signed certificate for localhost if the files do not exist
+    pub self_signed: bool,
+    /// Require client certificates signed by one of the CAs in this PEM file
+    pub client_ca: Option<PathBuf>,
+}
+
+impl TlsConfig {
+    /// Read the `tls` block, e.g. `{ "cert_path": "cert.pem", "key_path": "key.pem" }`
+    /// or `{ "self_signed": true, "client_ca": "clients.pem" }`. Returns None when TLS
+    /// is not configured.
+    pub fn from_config(config: Option<&Value>, data_dir: &Path) -> Result<Option<Self>, String> {
+        let Some(config) = config else { return Ok(None) };
+        check_keys(Some(config), "tls", &["enabled", "self_signed", "cert_path", "key_path", "client_ca"])?;
+        if config.get("enabled").and_then(|v| v.as_bool()) == Some(false) {
+            return Ok(None);
+        }
+        let self_signed = config.get("self_signed").and_then(|v| v.as_bool()).unwrap_or(false);
+        let path = |key: &str, default: &str| -> Result<PathBuf, String> {
+            match config.get(key) {
+                Some(Value::String(path)) => Ok(PathBuf::from(path)),
+                Some(other) => Err(format!("tls.{} must be a string, got {}", key, other)),
+                None if self_signed => Ok(data_dir.join(default)),
+                None => Err(format!("tls.{} is required unless tls.self_signed is set", key)),
+            }
+        };
+        let client_ca = match config.get("client_ca") {
+            Some(Value::String(path)) => Some(PathBuf::from(path)),
+            Some(other) => return Err(format!("tls.client_ca must be a string, got {}", other)),
+            None => None,
+        };
+        Ok(Some(TlsConfig {
+            cert_path: path("cert_path", DEFAULT_SELF_SIGNED_CERT)?,
+            key_path: path("key_path", DEFAULT_SELF_SIGNED_KEY)?,
+            self_signed,
+            client_ca,
+        }))
+    }
+
+    /// Load the certificate and key, generating a self-signed pair first if asked to
+    pub async fn load(&self) -> io::Result<RustlsConfig> {
+        // rustls needs a process-wide crypto provider; an error means one is already installed
+        let _ = rustls::crypto::ring::default_provider().install_default();
+
+        if self.self_signed && !(self.cert_path.exists() && self.key_path.exists()) {
+            generate_self_signed(&self.cert_path, &self.key_path)?;
+        }
+        match &self.client_ca {
+            Some(client_ca) => Ok(RustlsConfig::from_config(self.client_auth_config(client_ca)?)),
+            None => RustlsConfig::from_pem_file(&self.cert_path, &self.key_path).await,
+        }
+    }
+
+    /// Re-read the certificate, key and client CA
+    async fn reload(&self, rustls_config: &RustlsConfig) -> io::Result<()> {
+        match &self.client_ca {
+            Some(client_ca) => {
+                rustls_config.reload_from_config(self.client_auth_config(client_ca)?);
+                Ok(())
+            }
+            None => rustls_config.reload_from_pem_file(&self.cert_path, &self.key_path).await,
+        }
+    }
+
+    // A server config that only completes handshakes with clients whose
+    // certificate chains to `client_ca`
+    fn client_auth_config(&self, client_ca: &Path) -> io::Result<Arc<rustls::ServerConfig>> {
+        use rustls::pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer};
+        let certs = CertificateDer::pem_file_iter(&self.cert_path)
+            .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+            .map_err(|e| pem_error(&self.cert_path, e))?;
+        let key = PrivateKeyDer::from_pem_file(&self.key_path).map_err(|e| pem_error(&self.key_path, e))?;
+        let mut roots = rustls::RootCertStore::empty();
+        for ca in CertificateDer::pem_file_iter(client_ca).map_err(|e| pem_error(client_ca, e))? {
+            roots.add(ca.map_err(|e| pem_error(client_ca, e))?).map_err(io::Error::other)?;
+        }
+        if roots.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("No CA certificates in {}", client_ca.display())));
+        }
+
+        let verifier = rustls::server::WebPkiClientVerifier::builder(Arc::new(roots)).build().map_err(io::Error::other)?;
+        let mut config = rustls::ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(certs, key)
+            .map_err(io::Error::other)?;
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        Ok(Arc::new(config))
+    }
+}
+
+fn pem_error(path: &Path, e: rustls::pki_types::pem::Error) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", path.display(), e))
+}
+
+// Write a certificate and key for localhost to the given paths. The key is
+// readable by its owner only.
+fn generate_self_signed(cert_path: &Path, key_path: &Path) -> io::Result<()> {
+    let names = vec!["localhost".to_string(), "127.0.0.1".to_string(), "::1".to_string()];
+    let certified = rcgen::generate_simple_self_signed(names).map_err(io::Error::other)?;
+    for path in [cert_path, key_path] {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+    }
+    fs::write(cert_path, certified.cert.pem())?;
+    let mut key_file = fs::OpenOptions::new();
+    key_file.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut key_file, 0o600);
+    key_file.open(key_path)?.write_all(certified.key_pair.serialize_pem().as_bytes())?;
+    warn!("Generated self-signed certificate {} for localhost; clients must trust it explicitly", cert_path.display());
+    Ok(())
+}
+
+/// Re-read the certificate and key whenever the process receives SIGHUP. A
+/// failed reload keeps serving the previous certificate.
+#[cfg(unix)]
+pub async fn reload_on_sighup(tls: TlsConfig, rustls_config: RustlsConfig) {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            error!("Cannot listen for SIGHUP, certificate reload disabled: {}", e);
+            return;
+        }
+    };
+    while hangups.recv().await.is_some() {
+        match tls.reload(&rustls_config).await {
+            Ok(()) => info!("Reloaded TLS certificate from {}", tls.cert_path.display()),
+            Err(e) => error!("TLS certificate reload failed, keeping the current one: {}", e),
+        }
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn reload_on_sighup(_tls: TlsConfig, _rustls_config: RustlsConfig) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_self_signed_certificate_loads() {
+        let dir = std::env::temp_dir().join(format!("mcpi-tls-{}", uuid::Uuid::new_v4()));
+        assert!(TlsConfig::from_config(Some(&json!({ "cert_path": "cert.pem" })), &dir).is_err());
+
+        let config = json!({
+            "self_signed": true,
+            "cert_path": dir.join("cert.pem"),
+            "key_path": dir.join("key.pem"),
+        });
+        let tls = TlsConfig::from_config(Some(&config), &dir).unwrap().unwrap();
+        assert!(tls.load().await.is_ok());
+        assert!(tls.cert_path.exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(fs::metadata(&tls.key_path).unwrap().permissions().mode() & 0o777, 0o600);
+        }
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
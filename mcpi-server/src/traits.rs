@@ -1,4 +1,7 @@
 // mcpi-server/src/traits.rs
+use crate::transport::Shutdown;
+use std::error::Error;
+use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
 
@@ -9,25 +12,39 @@ pub trait MessageHandler: Send + Sync {
         -> Pin<Box<dyn Future<Output = Option<String>> + Send + 'a>>;
 }
 
-// NOTE: McpTransport and TransportError are likely no longer needed
-// if using the single-server model directly in main.rs.
-// Keep them if you have other uses, otherwise they can be removed.
-/*
-use std::fmt;
-use std::error::Error;
-use std::sync::Arc;
+pub type TransportFuture<'a, T> = Pin<Box<dyn Future<Output = T> + Send + 'a>>;
 
 #[derive(Debug)]
 pub enum TransportError {
-    StartupError(String),
-    ShutdownError(String),
-    ConnectionError(String),
+    /// The transport could not acquire its listener or streams
+    Startup(String),
+    /// The transport failed while serving
+    Connection(String),
+    /// The transport did not stop cleanly
+    Shutdown(String),
 }
-impl fmt::Display for TransportError { ... }
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransportError::Startup(msg) => write!(f, "transport failed to start: {}", msg),
+            TransportError::Connection(msg) => write!(f, "transport failed: {}", msg),
+            TransportError::Shutdown(msg) => write!(f, "transport failed to shut down: {}", msg),
+        }
+    }
+}
+
 impl Error for TransportError {}
 
-pub trait McpTransport: Send + Sync {
-    fn start(&self, message_handler: Arc<dyn MessageHandler>) -> Result<(), TransportError>;
-    fn shutdown(&self) -> Result<(), TransportError>;
+// A way for clients to reach the server. Transports are started together by the
+// TransportManager and all share the message handler and session store in AppState.
+pub trait McpTransport: Send {
+    fn name(&self) -> &'static str;
+
+    /// Acquire the listener or streams. A failure here aborts server startup.
+    fn start(&mut self) -> TransportFuture<'_, Result<(), TransportError>>;
+
+    /// Serve clients until `shutdown` fires or the transport's input ends, then release
+    /// everything acquired in `start`
+    fn serve(self: Box<Self>, shutdown: Shutdown) -> TransportFuture<'static, Result<(), TransportError>>;
 }
-*/
\ No newline at end of file
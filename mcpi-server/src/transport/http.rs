@@ -0,0 +1,156 @@
+// mcpi-server/src/transport/http.rs
+//
+// The router served by the HTTP listeners. Only the enabled MCP endpoints are
+// mounted; discovery, health probes, admin and metrics routes are always
+// present, the latter two behind the admin authentication and on their own
+// listener if one is configured. With tenants configured, each tenant gets its own router and
+// requests are dispatched by `Host` header and path prefix.
+
+use super::{legacy_sse, streamable_http, websocket, TransportsConfig};
+use crate::admin_auth::{self, AdminConfig};
+use crate::limits;
+use crate::security::{self, HttpConfig};
+use crate::tenants::TenantConfig;
+use crate::{admin, discovery_handler, health, metrics, AppState};
+use axum::{
+    extract::{DefaultBodyLimit, Request},
+    http::{header, Uri},
+    middleware,
+    response::Response,
+    routing::{delete, get, post},
+    Router,
+};
+use std::sync::Arc;
+use tower::ServiceExt;
+use tower_http::trace::TraceLayer;
+
+/// Routes of the public listeners: MCP, discovery, health probes and, unless admin has a
+/// listener of its own, the admin routes
+pub fn router(state: Arc<AppState>, transports: &TransportsConfig, http_config: &HttpConfig, admin: &AdminConfig) -> Router {
+    let mut mcp_routes = Router::new();
+    if transports.streamable_http {
+        mcp_routes = mcp_routes.route(
+            "/mcp",
+            get(streamable_http::handle_streamable_get)
+                .post(streamable_http::handle_streamable_post)
+                .delete(streamable_http::handle_streamable_delete),
+        );
+    }
+    if transports.legacy_sse {
+        mcp_routes = mcp_routes
+            .route("/sse", get(legacy_sse::handle_sse_get))
+            .route("/messages", post(legacy_sse::handle_messages_post));
+    }
+    if transports.websocket {
+        mcp_routes = mcp_routes.route("/mcpi", get(websocket::ws_handler));
+    }
+
+    // Each route group has its own CORS policy; the MCP endpoints also check Origin.
+    // Bodies over the message limit are refused with 413 before they are buffered.
+    let origin_policy = Arc::new(http_config.allowed_origins.clone());
+    let limits = state.message_handler.limits().clone();
+    let mcp_routes = mcp_routes
+        .layer(DefaultBodyLimit::max(limits.config().max_message_bytes))
+        .layer(middleware::from_fn_with_state(limits, limits::count_oversized_bodies))
+        .layer(middleware::from_fn_with_state(origin_policy, security::validate_origin))
+        .layer(security::cors_layer(&http_config.cors.mcp));
+    let discovery_routes = Router::new()
+        .route("/mcpi/discover", get(discovery_handler))
+        .layer(security::cors_layer(&http_config.cors.discovery));
+
+    let probe_routes = Router::new().route("/healthz", get(health::liveness)).route("/readyz", get(health::readiness));
+
+    let mut router = Router::new().merge(mcp_routes).merge(discovery_routes).merge(probe_routes);
+    if admin.listener.is_none() {
+        router = router.merge(admin_routes(http_config, admin));
+    }
+    router.layer(TraceLayer::new_for_http()).with_state(state)
+}
+
+/// Routes of the admin listener
+pub fn admin_router(state: Arc<AppState>, http_config: &HttpConfig, admin: &AdminConfig) -> Router {
+    admin_routes(http_config, admin).layer(TraceLayer::new_for_http()).with_state(state)
+}
+
+// CORS is outermost so preflight requests, which carry no credentials, are answered
+fn admin_routes(http_config: &HttpConfig, admin: &AdminConfig) -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/admin", get(admin::serve_admin_html))
+        .route("/api/admin/stats", get(admin::get_stats))
+        .route("/api/admin/plugins", get(admin::get_plugins))
+        .route("/api/admin/reload", post(admin::reload_config))
+        .route("/api/admin/sessions", get(admin::get_sessions))
+        .route("/api/admin/sessions/:id", delete(admin::terminate_session))
+        .route("/api/admin/sessions/:id/notify", post(admin::notify_session))
+        .route("/api/admin/audit", get(admin::query_audit))
+        .route("/metrics", get(metrics::serve_metrics))
+        .layer(middleware::from_fn_with_state(admin.auth.clone(), admin_auth::require_admin))
+        .layer(security::cors_layer(&http_config.cors.admin))
+}
+
+/// Route each request to the first tenant it matches, with the tenant's path
+/// prefix removed; requests no tenant matches go to `root`
+pub fn tenant_router(root: Router, tenants: Vec<(TenantConfig, Router)>) -> Router {
+    if tenants.is_empty() {
+        return root;
+    }
+    let tenants = Arc::new(tenants);
+    Router::new().fallback(move |request: Request| {
+        let tenants = tenants.clone();
+        let root = root.clone();
+        async move {
+            let host = request_host(&request);
+            let path = request.uri().path().to_string();
+            match tenants.iter().find(|(tenant, _)| tenant.matches(host.as_deref(), &path)) {
+                Some((tenant, router)) => {
+                    let request = match &tenant.path_prefix {
+                        Some(prefix) => strip_prefix(request, prefix),
+                        None => request,
+                    };
+                    dispatch(router.clone(), request).await
+                }
+                None => dispatch(root, request).await,
+            }
+        }
+    })
+}
+
+async fn dispatch(router: Router, request: Request) -> Response {
+    match router.oneshot(request).await {
+        Ok(response) => response,
+        Err(infallible) => match infallible {},
+    }
+}
+
+// Host name without port, from `Host` or, for HTTP/2, the request URI
+fn request_host(request: &Request) -> Option<String> {
+    let host = request
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .or_else(|| request.uri().host())?;
+    let name = match host.rsplit_once(':') {
+        // Leave bracketed IPv6 literals without a port alone
+        Some((name, port)) if !port.contains(']') => name,
+        _ => host,
+    };
+    Some(name.to_ascii_lowercase())
+}
+
+fn strip_prefix(mut request: Request, prefix: &str) -> Request {
+    let uri = request.uri();
+    let rest = uri.path().strip_prefix(prefix).unwrap_or(uri.path());
+    let path = if rest.is_empty() { "/" } else { rest };
+    let path_and_query = match uri.query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path.to_string(),
+    };
+    let mut parts = uri.clone().into_parts();
+    if let Ok(path_and_query) = path_and_query.parse() {
+        parts.path_and_query = Some(path_and_query);
+        if let Ok(stripped) = Uri::from_parts(parts) {
+            *request.uri_mut() = stripped;
+        }
+    }
+    request
+}
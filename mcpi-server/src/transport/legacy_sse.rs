@@ -0,0 +1,98 @@
+// mcpi-server/src/transport/legacy_sse.rs
+//
+// HTTP+SSE transport from the 2024-11-05 spec, for clients that predate
+// Streamable HTTP. `GET /sse` opens a session and announces where to post with an
+// `endpoint` event; requests then go to `POST /messages?sessionId=...` and every
+// response is delivered on the SSE stream. Sessions live in the same store as
+// `/mcp` sessions and end when the stream is closed.
+
+use crate::telemetry;
+use crate::AppState;
+use axum::{
+    extract::{Query, State},
+    http::{HeaderMap, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse, Response},
+};
+use futures::{stream, Stream, StreamExt};
+use serde::Deserialize;
+use std::convert::Infallible;
+use std::sync::{atomic::Ordering, Arc};
+use std::time::Duration;
+use tracing::{info, warn, Instrument};
+
+/// Path clients post messages to, announced in the `endpoint` event
+const MESSAGES_PATH: &str = "/messages";
+
+#[derive(Deserialize)]
+pub struct MessagesQuery {
+    #[serde(rename = "sessionId")]
+    session_id: String,
+}
+
+// Ends the session once the client drops the SSE stream, since this transport
+// has no other way to close it
+struct SessionGuard {
+    state: Arc<AppState>,
+    session_id: String,
+}
+
+impl Drop for SessionGuard {
+    fn drop(&mut self) {
+        let state = self.state.clone();
+        let session_id = std::mem::take(&mut self.session_id);
+        tokio::spawn(async move {
+            if state.http_sessions.remove(&session_id).await {
+                state.message_handler.forget_client(&session_id);
+                info!("Legacy SSE session {} closed", session_id);
+            }
+        });
+    }
+}
+
+pub async fn handle_sse_get(State(state): State<Arc<AppState>>) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, StatusCode> {
+    state.request_count.fetch_add(1, Ordering::SeqCst);
+    if state.drain.is_draining() {
+        return Err(StatusCode::SERVICE_UNAVAILABLE);
+    }
+    let (session_id, events) = state.http_sessions.create("legacy_sse").await;
+    info!("Legacy SSE stream opened session: {}", session_id);
+
+    let endpoint = Event::default().event("endpoint").data(format!("{}{}?sessionId={}", state.path_prefix, MESSAGES_PATH, session_id));
+    let guard = SessionGuard { state: state.clone(), session_id };
+    let messages = events.subscribe(None).map(move |message| {
+        let _guard = &guard;
+        Ok(Event::default().event("message").id(message.id.to_string()).data(message.data))
+    });
+
+    Ok(Sse::new(stream::once(async { Ok(endpoint) }).chain(messages))
+        .keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("keep-alive")))
+}
+
+pub async fn handle_messages_post(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<MessagesQuery>,
+    headers: HeaderMap,
+    body: String,
+) -> Response {
+    state.request_count.fetch_add(1, Ordering::SeqCst);
+    let Some(events) = state.http_sessions.touch(&query.session_id).await else {
+        warn!("POST /messages for unknown or expired session: {}", query.session_id);
+        return (StatusCode::NOT_FOUND, "Session not found").into_response();
+    };
+
+    // The response goes out on the SSE stream after any related messages, so the
+    // POST is acknowledged straight away
+    let handler = state.message_handler.clone();
+    let session_id = query.session_id;
+    // Shutdown waits until the response is queued on the stream
+    let in_flight = state.drain.track();
+    let span = telemetry::http_span(&headers, "legacy_sse");
+    tokio::spawn(async move {
+        let _in_flight = in_flight;
+        let sender = events.sender();
+        if let Some(response) = handler.handle_message_with_events(body, session_id, sender.clone()).await {
+            sender.send(response).await;
+        }
+    }.instrument(span));
+    (StatusCode::ACCEPTED, "Accepted").into_response()
+}
@@ -1,41 +1,201 @@
-pub mod traits;
-mod websocket;
-mod streamable_http; // Add this new module
+// mcpi-server/src/transport/mod.rs
+//
+// Everything that carries JSON-RPC between clients and the server. The listeners
+// (TCP, Unix socket, stdio) implement `McpTransport` and are run together by the
+// `TransportManager`; the HTTP endpoints (Streamable HTTP, legacy SSE, WebSocket)
+// are mounted on the router the HTTP listeners serve. The optional `transports`
+// config block selects which of them are enabled. All of them share the message
+// handler and session store in `AppState`.
 
-pub use traits::{MessageHandler, McpTransport, TransportError};
-pub use websocket::WebSocketTransport;
-pub use streamable_http::StreamableHttpTransport; // Export the new transport
+pub mod http;
+pub mod legacy_sse;
+pub mod stdio;
+pub mod streamable_http;
+pub mod tcp;
+#[cfg(unix)]
+pub mod unix_socket;
+pub mod websocket;
 
-use std::sync::Arc;
+use crate::config::check_keys;
+use crate::traits::{McpTransport, TransportError};
+use serde_json::Value;
+use std::future::Future;
+use tokio::sync::watch;
+use tokio::task::{JoinError, JoinSet};
+use tracing::{error, info};
 
+/// Settings from the optional `transports` block of the server config
+#[derive(Debug, Clone)]
+pub struct TransportsConfig {
+    /// `/mcp`
+    pub streamable_http: bool,
+    /// `/sse` and `/messages`
+    pub legacy_sse: bool,
+    /// `/mcpi`
+    pub websocket: bool,
+    /// Newline-delimited JSON-RPC on stdin/stdout
+    pub stdio: bool,
+}
+
+impl TransportsConfig {
+    /// Read the `transports` block, e.g.
+    /// `{ "streamable_http": true, "legacy_sse": false, "websocket": true, "stdio": false }`.
+    /// The HTTP endpoints are enabled and stdio is disabled unless configured.
+    pub fn from_config(config: Option<&Value>) -> Result<Self, String> {
+        check_keys(config, "transports", &["streamable_http", "legacy_sse", "websocket", "stdio"])?;
+        let flag = |key: &str, default: bool| match config.and_then(|c| c.get(key)) {
+            Some(value) => value.as_bool().ok_or_else(|| format!("transports.{} must be a boolean, got {}", key, value)),
+            None => Ok(default),
+        };
+        Ok(TransportsConfig {
+            streamable_http: flag("streamable_http", true)?,
+            legacy_sse: flag("legacy_sse", true)?,
+            websocket: flag("websocket", true)?,
+            stdio: flag("stdio", false)?,
+        })
+    }
+
+    /// Only stdio, as selected by `--stdio`
+    pub fn stdio_only() -> Self {
+        TransportsConfig { streamable_http: false, legacy_sse: false, websocket: false, stdio: true }
+    }
+}
+
+/// Resolves once the manager asks its transports to stop
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub async fn wait(mut self) {
+        // A dropped manager also counts as a request to stop
+        let _ = self.0.wait_for(|stop| *stop).await;
+    }
+}
+
+/// Starts the enabled transports together and stops them together
 pub struct TransportManager {
     transports: Vec<Box<dyn McpTransport>>,
-    message_handler: Arc<dyn MessageHandler>,
+    shutdown: watch::Sender<bool>,
 }
 
 impl TransportManager {
-    pub fn new(message_handler: impl MessageHandler + 'static) -> Self {
-        Self {
-            transports: Vec::new(),
-            message_handler: Arc::new(message_handler),
-        }
+    pub fn new() -> Self {
+        TransportManager { transports: Vec::new(), shutdown: watch::channel(false).0 }
     }
-    
+
     pub fn register_transport(&mut self, transport: Box<dyn McpTransport>) {
         self.transports.push(transport);
     }
-    
-    pub async fn start_all(&self) -> Result<(), TransportError> {
-        for transport in &self.transports {
-            transport.start(self.message_handler.clone())?;
+
+    pub fn is_empty(&self) -> bool {
+        self.transports.is_empty()
+    }
+
+    /// Start every transport. If one fails, the error is returned and dropping the
+    /// manager releases whatever the others acquired.
+    pub async fn start_all(&mut self) -> Result<(), TransportError> {
+        for transport in &mut self.transports {
+            transport.start().await?;
+            info!("Started {} transport", transport.name());
         }
         Ok(())
     }
-    
-    pub async fn shutdown_all(&self) -> Result<(), TransportError> {
-        for transport in &self.transports {
-            let _ = transport.shutdown();
+
+    /// Serve on every started transport until `signal` resolves or one transport
+    /// stops on its own (e.g. stdin closes). `before_stop` then runs while the
+    /// transports are still up, after which they are stopped and waited for.
+    /// Returns the first error any transport reported.
+    pub async fn run(self, signal: impl Future<Output = ()>, before_stop: impl Future<Output = ()>) -> Result<(), TransportError> {
+        let TransportManager { transports, shutdown } = self;
+        let mut tasks = JoinSet::new();
+        for transport in transports {
+            let name = transport.name();
+            let stop = Shutdown(shutdown.subscribe());
+            tasks.spawn(async move { (name, transport.serve(stop).await) });
         }
-        Ok(())
+
+        let mut result = Ok(());
+        tokio::select! {
+            _ = signal => info!("Stopping all transports"),
+            Some(joined) = tasks.join_next() => {
+                result = finished(joined);
+                info!("Stopping the remaining transports");
+            }
+        }
+        before_stop.await;
+        let _ = shutdown.send(true);
+        while let Some(joined) = tasks.join_next().await {
+            let outcome = finished(joined);
+            if result.is_ok() {
+                result = outcome;
+            }
+        }
+        result
     }
-}
\ No newline at end of file
+}
+
+impl Default for TransportManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn finished(joined: Result<(&'static str, Result<(), TransportError>), JoinError>) -> Result<(), TransportError> {
+    match joined {
+        Ok((name, Ok(()))) => {
+            info!("{} transport stopped", name);
+            Ok(())
+        }
+        Ok((name, Err(e))) => {
+            error!("{} transport stopped with an error: {}", name, e);
+            Err(e)
+        }
+        Err(e) => Err(TransportError::Shutdown(e.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::traits::TransportFuture;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    // Stops on its own when `lifetime` is set, otherwise waits for shutdown
+    struct FakeTransport {
+        lifetime: Option<std::time::Duration>,
+        stopped: Arc<AtomicUsize>,
+    }
+
+    impl McpTransport for FakeTransport {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn start(&mut self) -> TransportFuture<'_, Result<(), TransportError>> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn serve(self: Box<Self>, shutdown: Shutdown) -> TransportFuture<'static, Result<(), TransportError>> {
+            Box::pin(async move {
+                match self.lifetime {
+                    Some(lifetime) => tokio::time::sleep(lifetime).await,
+                    None => shutdown.wait().await,
+                }
+                self.stopped.fetch_add(1, Ordering::SeqCst);
+                Ok(())
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_one_transport_stopping_stops_the_rest() {
+        let stopped = Arc::new(AtomicUsize::new(0));
+        let mut manager = TransportManager::new();
+        manager.register_transport(Box::new(FakeTransport { lifetime: None, stopped: stopped.clone() }));
+        manager.register_transport(Box::new(FakeTransport { lifetime: Some(std::time::Duration::from_millis(10)), stopped: stopped.clone() }));
+        manager.start_all().await.unwrap();
+        manager.run(std::future::pending(), async {}).await.unwrap();
+        assert_eq!(stopped.load(Ordering::SeqCst), 2);
+    }
+}
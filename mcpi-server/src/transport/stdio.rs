@@ -0,0 +1,133 @@
+// mcpi-server/src/transport/stdio.rs
+//
+// stdio transport: newline-delimited JSON-RPC on stdin/stdout, as used by
+// desktop MCP hosts that launch the server as a subprocess. Nothing but
+// JSON-RPC messages may be written to stdout in this mode; logs go to stderr.
+// Responses, messages related to in-flight requests (progress, logs,
+// server-to-client requests) and server-wide notifications all share one
+// stdout writer.
+
+use super::Shutdown;
+use crate::message_handler::McpMessageHandler;
+use crate::outbound::{self, OutboundReceiver};
+use crate::traits::{McpTransport, TransportError, TransportFuture};
+use std::future::Future;
+use std::io;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+/// Client ID used for the single stdio session
+pub const STDIO_CLIENT_ID: &str = "stdio";
+
+/// Responses waiting for stdout before handlers are made to wait
+const STDOUT_QUEUE_CAPACITY: usize = 256;
+
+pub struct StdioTransport {
+    message_handler: Arc<McpMessageHandler>,
+    notifications: broadcast::Receiver<String>,
+}
+
+impl StdioTransport {
+    pub fn new(message_handler: Arc<McpMessageHandler>, notifications: broadcast::Receiver<String>) -> Self {
+        StdioTransport { message_handler, notifications }
+    }
+}
+
+impl McpTransport for StdioTransport {
+    fn name(&self) -> &'static str {
+        "stdio"
+    }
+
+    fn start(&mut self) -> TransportFuture<'_, Result<(), TransportError>> {
+        // stdin and stdout are always there
+        Box::pin(async { Ok(()) })
+    }
+
+    fn serve(self: Box<Self>, shutdown: Shutdown) -> TransportFuture<'static, Result<(), TransportError>> {
+        Box::pin(async move {
+            serve(self.message_handler, self.notifications, shutdown.wait())
+                .await
+                .map_err(|e| TransportError::Connection(format!("stdio: {}", e)))
+        })
+    }
+}
+
+/// Serve the message handler over stdin/stdout until stdin is closed or `shutdown` resolves.
+/// Messages from `notifications` are forwarded to stdout as they arrive.
+pub async fn serve(
+    message_handler: Arc<McpMessageHandler>,
+    mut notifications: broadcast::Receiver<String>,
+    shutdown: impl Future<Output = ()>,
+) -> io::Result<()> {
+    info!("Serving MCP over stdio");
+    // A single local client: waiting on a full pipe is the only sensible policy
+    let (outbound_tx, outbound_rx) = outbound::handoff::<String>(STDOUT_QUEUE_CAPACITY);
+    let writer = tokio::spawn(write_stdout(outbound_rx));
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    tokio::pin!(shutdown);
+    loop {
+        let line = tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => line,
+                None => {
+                    info!("stdin closed, ending stdio session");
+                    break;
+                }
+            },
+            notification = notifications.recv(), if !notifications.is_closed() => {
+                match notification {
+                    Ok(message) => {
+                        if !outbound_tx.send(message).await {
+                            warn!("stdout writer closed, dropping notification");
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(missed)) => warn!("stdio missed {} notifications", missed),
+                    Err(broadcast::error::RecvError::Closed) => {}
+                }
+                continue;
+            }
+            _ = &mut shutdown => break,
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        // Each message is handled on its own task so a slow tool call does not
+        // hold up the rest of the session; responses carry their request ids.
+        let handler = message_handler.clone();
+        let outbound = outbound_tx.clone();
+        tokio::spawn(async move {
+            let response = handler.handle_message_with_events(line, STDIO_CLIENT_ID.to_string(), outbound.clone()).await;
+            if let Some(response) = response {
+                if !outbound.send(response).await {
+                    warn!("stdout writer closed, dropping response");
+                }
+            }
+        });
+    }
+
+    message_handler.forget_client(STDIO_CLIENT_ID);
+    // The writer finishes once every in-flight task has dropped its sender
+    drop(outbound_tx);
+    match writer.await {
+        Ok(result) => result,
+        Err(e) => {
+            error!("stdout writer task failed: {}", e);
+            Ok(())
+        }
+    }
+}
+
+async fn write_stdout(mut outbound_rx: OutboundReceiver<String>) -> io::Result<()> {
+    let mut stdout = tokio::io::stdout();
+    while let Some(message) = outbound_rx.recv().await {
+        // Serialized JSON never contains raw newlines, so one message per line is safe
+        stdout.write_all(message.as_bytes()).await?;
+        stdout.write_all(b"\n").await?;
+        stdout.flush().await?;
+    }
+    Ok(())
+}
@@ -1,271 +1,172 @@
-use crate::transport::traits::{McpTransport, MessageHandler, TransportError};
+// mcpi-server/src/transport/streamable_http.rs
+//
+// Streamable HTTP transport on `/mcp`: POST carries client messages, GET opens the
+// session's SSE stream and DELETE ends the session.
+
+use crate::telemetry;
+use crate::traits::MessageHandler;
+use crate::AppState;
 use axum::{
-    // Removed unused: State, WebSocketUpgrade
-    routing::get, // Removed unused: post (delete uses its own async block)
-    Router, // Removed unused: Json
-    response::IntoResponse, // Removed unused: Response
-    http::{StatusCode, HeaderMap}, // Removed unused: Method, HeaderValue
+    extract::State,
+    http::{header::{HeaderName, HeaderValue}, HeaderMap, StatusCode},
+    response::{sse::{Event, KeepAlive, Sse}, IntoResponse},
 };
-use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
-// Removed unused: warn
-use tracing::{error, info};
+use futures::Stream;
+use serde_json::Value;
+use std::{
+    convert::Infallible,
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
+use tokio_stream::StreamExt;
+use tracing::{info, warn, Instrument, Span};
 use uuid::Uuid;
-use std::collections::HashMap;
-use tokio::sync::RwLock;
-use std::net::SocketAddr;
-
-// Structure to store session information
-struct SessionInfo {
-    last_event_id: Option<String>,
-    #[allow(dead_code)] // Added allow(dead_code) as is_active seems unused currently
-    is_active: bool,
-    #[allow(dead_code)] // Added allow(dead_code) as created_at seems unused currently
-    created_at: std::time::Instant,
-}
-
-pub struct StreamableHttpTransport {
-    port: u16,
-    shutdown_signal: Mutex<Option<broadcast::Sender<()>>>,
-    sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
-}
 
-impl StreamableHttpTransport {
-    pub fn new(port: u16) -> Self {
-        Self {
-            port,
-            shutdown_signal: Mutex::new(None),
-            sessions: Arc::new(RwLock::new(HashMap::new())),
-        }
-    }
-
-    // Handle HTTP POST requests (client to server)
-    async fn handle_post(
-        message_handler: Arc<dyn MessageHandler>,
-        sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
-        body: String,
-        headers: HeaderMap, // Type already specified here
-    ) -> impl IntoResponse {
-        // Extract session ID from headers
-        let session_id = headers.get("mcp-session-id")
-            .and_then(|v| v.to_str().ok()) // Use and_then and ok() for safer parsing
-            .map(|s| s.to_string());
-
-        if let Some(ref session_id_str) = session_id {
-            // Check if session exists
-            let sessions_read = sessions.read().await;
-            if !sessions_read.contains_key(session_id_str) {
-                // Drop not needed, scope ends anyway
-                return (StatusCode::NOT_FOUND, "Session not found").into_response();
+static MCP_SESSION_ID: HeaderName = HeaderName::from_static("mcp-session-id");
+static LAST_EVENT_ID: HeaderName = HeaderName::from_static("last-event-id");
+
+pub async fn handle_streamable_get(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<(HeaderMap, Sse<impl Stream<Item = Result<Event, Infallible>>>), StatusCode> {
+    state.request_count.fetch_add(1, Ordering::SeqCst);
+    let provided_session_id = headers.get(&MCP_SESSION_ID).and_then(|hv| hv.to_str().ok());
+
+    // A reconnecting client sends the id of the last event it saw to get the rest replayed
+    let last_event_id = headers.get(&LAST_EVENT_ID).and_then(|hv| hv.to_str().ok()).and_then(|id| match id.trim().parse::<u64>() {
+        Ok(id) => Some(id),
+        Err(_) => { warn!("Ignoring malformed Last-Event-ID: {}", id); None }
+    });
+
+    let session_id: String;
+    let mut response_headers = HeaderMap::new();
+
+    response_headers.insert(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    response_headers.insert(axum::http::header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response_headers.insert(axum::http::header::CONNECTION, HeaderValue::from_static("keep-alive"));
+
+    let events = match provided_session_id {
+        Some(id) => match state.http_sessions.touch(id).await {
+            Some(events) => {
+                session_id = id.to_string();
+                match last_event_id {
+                    Some(last) => info!("SSE stream resuming session {} after event {}", session_id, last),
+                    None => info!("SSE stream re-establishing session: {}", session_id),
+                }
+                events
             }
-        } else {
-             // Decide how to handle posts without a session ID - maybe require it?
-             // For now, proceeding but logging a warning might be good.
-             info!("POST request received without mcp-session-id header.");
-             // Or return BadRequest:
-             // return (StatusCode::BAD_REQUEST, "mcp-session-id header required for POST").into_response();
+            None => {
+                warn!("GET /mcp for unknown or expired session: {}", id);
+                return Err(StatusCode::NOT_FOUND);
+            }
+        },
+        None if state.drain.is_draining() => return Err(StatusCode::SERVICE_UNAVAILABLE),
+        None => {
+            let (new_session_id, events) = state.http_sessions.create("streamable_http").await;
+            session_id = new_session_id;
+            info!("SSE stream creating new session: {}", session_id);
+            events
         }
+    };
+    response_headers.insert(MCP_SESSION_ID.clone(), HeaderValue::from_str(&session_id).unwrap());
 
-        // Generate a client ID for message handling (use session or new UUID)
-        let client_id = session_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let stream = events
+        .subscribe(last_event_id)
+        .map(|message| Ok(Event::default().event("message").id(message.id.to_string()).data(message.data)));
 
-        // Process the message with the handler
-        // Note: check_if_needs_response logic removed as handle_message should always be called
-        // The handler itself decides if a response string is generated.
-        if let Some(response) = message_handler.handle_message(body, client_id).await {
-            (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, "application/json")], response).into_response()
-        } else {
-             // Handler returned None, meaning no direct response is needed for this message/batch
-             // According to JSON-RPC spec for notifications, no content is returned.
-             // For batches containing only notifications, an empty response might be expected by some clients,
-             // but often 204 No Content is acceptable. Let's use 204.
-            (StatusCode::NO_CONTENT, "").into_response()
-        }
-    }
+    let sse = Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("keep-alive"));
 
-    // Handle HTTP GET requests (server to client, opens SSE stream)
-    async fn handle_get(
-        _message_handler: Arc<dyn MessageHandler>, // Prefixed with _ as it's unused
-        sessions: Arc<RwLock<HashMap<String, SessionInfo>>>,
-        headers: HeaderMap, // Type already specified here
-    ) -> impl IntoResponse {
-        // Extract session ID and Last-Event-ID from headers
-        let session_id = headers.get("mcp-session-id")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
-
-        let last_event_id = headers.get("last-event-id")
-            .and_then(|v| v.to_str().ok())
-            .map(|s| s.to_string());
+    Ok((response_headers, sse))
+}
 
-        if let Some(ref session_id_str) = session_id {
-            // Check if session exists and update last_event_id
-            let mut sessions_write = sessions.write().await;
-            if let Some(session) = sessions_write.get_mut(session_id_str) {
-                 // Update session with last event ID if provided
-                if let Some(id) = last_event_id {
-                     info!("Updating last_event_id for session {}: {}", session_id_str, id);
-                     session.last_event_id = Some(id);
-                }
-                 // Session exists, proceed to setup SSE
-            } else {
-                 // Session not found
-                 // Drop not needed
-                 return (StatusCode::NOT_FOUND, "Session not found").into_response();
-            }
-        } else {
-             // Require session ID for GET requests establishing SSE stream
-             return (StatusCode::BAD_REQUEST, "mcp-session-id header required for GET").into_response();
+pub async fn handle_streamable_post( State(state): State<Arc<AppState>>, headers: HeaderMap, body: String ) -> impl IntoResponse {
+    state.request_count.fetch_add(1, Ordering::SeqCst);
+    let session_id = headers.get(&MCP_SESSION_ID).and_then(|v| v.to_str().ok()).map(str::to_string);
+    let client_id = session_id.clone().unwrap_or_else(|| format!("http-{}", Uuid::new_v4()));
+
+    let session_events = match session_id {
+        Some(ref id_str) => match state.http_sessions.touch(id_str).await {
+            Some(events) => { info!("POST /mcp for session: {}", id_str); Some(events) }
+            // The client must start a new session
+            None => { warn!("POST /mcp for unknown or expired session: {}", id_str); return (StatusCode::NOT_FOUND, "Session not found").into_response(); }
+        },
+        None if state.drain.is_draining() => return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response(),
+        None => { info!("POST /mcp without session ID (client_id: {})", client_id); None }
+    };
+
+    let span = telemetry::http_span(&headers, "streamable_http");
+    // A sessionless client id is used for this one POST, so its state is dropped afterwards
+    let ephemeral = session_id.is_none();
+    if wants_event_stream(&headers, &body) {
+        return stream_post_response(&state, body, client_id, ephemeral, span).into_response();
+    }
+    // With a plain JSON response, related messages go out on the session's GET stream
+    let response = match session_events {
+        Some(events) => state.message_handler.handle_message_with_events(body, client_id, events.sender()).instrument(span).await,
+        None => {
+            let response = state.message_handler.handle_message(body, client_id.clone()).instrument(span).await;
+            state.message_handler.forget_client(&client_id);
+            response
         }
+    };
+    if let Some(response_body) = response { (StatusCode::OK, [(axum::http::header::CONTENT_TYPE, HeaderValue::from_static("application/json"))], response_body).into_response() }
+    // Input made only of notifications or responses is acknowledged without a body
+    else { (StatusCode::ACCEPTED, "").into_response() }
+}
 
-        // Placeholder SSE setup
-        // TODO: Implement actual Server-Sent Events stream logic here
-        // This would involve holding the connection open and sending events
-        info!("SSE stream requested for session: {}", session_id.unwrap_or_default());
-        (StatusCode::OK, [
-            (axum::http::header::CONTENT_TYPE, "text/event-stream"),
-            (axum::http::header::CACHE_CONTROL, "no-cache"),
-            (axum::http::header::CONNECTION, "keep-alive"),
-            // Add CORS headers if needed, e.g.:
-            // (axum::http::header::ACCESS_CONTROL_ALLOW_ORIGIN, "*"),
-        ], "data: Connected\n\n").into_response()
-    }
+// Methods whose calls may emit progress, logs or server-to-client requests while running
+const STREAMING_METHODS: &[&str] = &["tools/call"];
 
-    // Method create_session is unused - remove or add #[allow(dead_code)]
-    #[allow(dead_code)]
-    async fn create_session(&self, session_id: &str) -> Result<(), TransportError> {
-        let mut sessions = self.sessions.write().await;
-        if sessions.contains_key(session_id) {
-            Err(TransportError::ConnectionError(format!("Session {} already exists", session_id)))
-        } else {
-            info!("Creating new session: {}", session_id);
-            sessions.insert(session_id.to_string(), SessionInfo {
-                last_event_id: None,
-                is_active: true,
-                created_at: std::time::Instant::now(),
-            });
-            Ok(())
-        }
+// Decide whether a POST should be answered with an SSE stream instead of a JSON body.
+// Clients that accept only SSE always get a stream; clients that accept both get one
+// for calls that can emit related messages, and plain JSON for simple calls.
+fn wants_event_stream(headers: &HeaderMap, body: &str) -> bool {
+    let accept = headers.get(axum::http::header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or("");
+    if !accept.contains("text/event-stream") {
+        return false;
     }
-
-    // Method delete_session is unused - remove or add #[allow(dead_code)]
-    #[allow(dead_code)]
-    async fn delete_session(&self, session_id: &str) -> Result<(), TransportError> {
-        let mut sessions = self.sessions.write().await;
-        if sessions.remove(session_id).is_some() {
-            info!("Deleted session: {}", session_id);
-            Ok(())
-        } else {
-            Err(TransportError::ConnectionError(format!("Session {} not found", session_id)))
-        }
+    let Ok(message) = serde_json::from_str::<Value>(body) else { return false };
+    let items = match &message { Value::Array(items) => items.iter().collect(), single => vec![single] };
+    let requests: Vec<&Value> = items.into_iter().filter(|m| m.get("id").is_some() && m.get("method").is_some()).collect();
+    if requests.is_empty() {
+        return false;
+    }
+    if !(accept.contains("application/json") || accept.contains("*/*")) {
+        return true;
     }
+    requests.iter().any(|r| {
+        let method = r.get("method").and_then(|m| m.as_str()).unwrap_or("");
+        let has_progress_token = r.pointer("/params/_meta/progressToken").is_some();
+        STREAMING_METHODS.contains(&method) || has_progress_token
+    })
 }
 
-impl McpTransport for StreamableHttpTransport {
-    fn start(&self, message_handler: Arc<dyn MessageHandler>) -> Result<(), TransportError> {
-        let port = self.port;
-        let (shutdown_tx, mut shutdown_rx) = broadcast::channel(1);
-
-        // Clone Arc for the closures
-        let message_handler_for_routes = message_handler.clone();
-        let sessions_for_routes = self.sessions.clone();
-
-        // Build application with HTTP routes
-        let app = Router::new()
-            .route("/mcpi",
-                get({
-                    let handler = message_handler_for_routes.clone();
-                    let sessions = sessions_for_routes.clone();
-                    // *** FIX: Added type annotation ***
-                    move |headers: HeaderMap| Self::handle_get(handler.clone(), sessions.clone(), headers)
-                })
-                .post({
-                    let handler = message_handler_for_routes.clone();
-                    let sessions = sessions_for_routes.clone();
-                    // *** FIX: Added type annotation ***
-                    move |headers: HeaderMap, body: String| Self::handle_post(handler.clone(), sessions.clone(), body, headers)
-                })
-                .delete({
-                    let sessions = sessions_for_routes.clone();
-                     // *** FIX: Added type annotation ***
-                    move |headers: HeaderMap| async move {
-                        let session_id = headers.get("mcp-session-id")
-                            .and_then(|v| v.to_str().ok())
-                            .map(|s| s.to_string());
-
-                        if let Some(id) = session_id {
-                            let mut sessions_write = sessions.write().await;
-                            if sessions_write.remove(&id).is_some() {
-                                info!("Session {} terminated via DELETE request", id);
-                                (StatusCode::OK, "Session terminated").into_response()
-                            } else {
-                                info!("DELETE request for non-existent session {}", id);
-                                (StatusCode::NOT_FOUND, "Session not found").into_response()
-                            }
-                        } else {
-                            (StatusCode::BAD_REQUEST, "mcp-session-id header required for DELETE").into_response()
-                        }
-                    }
-                })
-            );
-
-        // Set up server address
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-
-        info!("Starting Streamable HTTP transport on {}", addr);
-
-        // Start the server in a background task
-        let server_handle = tokio::spawn(async move {
-            axum::Server::bind(&addr)
-                .serve(app.into_make_service())
-                .with_graceful_shutdown(async move {
-                    shutdown_rx.recv().await.ok(); // Wait for shutdown signal
-                    info!("Graceful shutdown initiated for Streamable HTTP transport");
-                })
-                .await
-                .map_err(|e| error!("Streamable HTTP server error: {}", e))
-                .ok(); // Log error but don't panic
-
-            info!("Streamable HTTP transport shut down completely");
-        });
-
-        // Store shutdown sender
-        match self.shutdown_signal.lock() {
-             Ok(mut guard) => {
-                *guard = Some(shutdown_tx);
-             }
-             Err(poisoned) => {
-                 error!("Shutdown signal mutex poisoned: {}", poisoned);
-                 // Attempt recovery or return error
-                 // For simplicity, returning error here
-                 // Could potentially try to recover the lock: *poisoned.into_inner() = Some(shutdown_tx);
-                 server_handle.abort(); // Stop the server task if we can't store the shutdown handle
-                 return Err(TransportError::StartupError("Failed to acquire lock for shutdown signal".to_string()));
-             }
-         }
-
-        Ok(())
-    }
+// Answer a POST with an SSE stream: related messages as they are produced, then the
+// final response, after which the stream closes.
+fn stream_post_response(state: &AppState, body: String, client_id: String, ephemeral: bool, span: Span) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    info!("Upgrading POST /mcp response to SSE for client {}", client_id);
+    let (events_tx, events_rx) = state.outbound.open::<String>(&client_id);
+    let message_handler = state.message_handler.clone();
+    tokio::spawn(async move {
+        let response = message_handler.handle_message_with_events(body, client_id.clone(), events_tx.clone()).await;
+        if ephemeral {
+            message_handler.forget_client(&client_id);
+        }
+        if let Some(response) = response {
+            events_tx.send(response).await;
+        }
+        // Dropping the last sender ends the stream
+    }.instrument(span));
+    let stream = futures::stream::unfold(events_rx, |mut rx| async move { rx.recv().await.map(|message| (message, rx)) })
+        .map(|message| Ok(Event::default().event("message").data(message)));
+    Sse::new(stream).keep_alive(KeepAlive::new().interval(Duration::from_secs(15)).text("keep-alive"))
+}
 
-    fn shutdown(&self) -> Result<(), TransportError> {
-         match self.shutdown_signal.lock() {
-             Ok(guard) => {
-                 if let Some(sender) = guard.as_ref() {
-                     match sender.send(()) {
-                         Ok(_) => info!("Shutdown signal sent to Streamable HTTP transport"),
-                         Err(_) => info!("Streamable HTTP transport already shut down or receiver dropped"), // Not necessarily an error
-                     }
-                 } else {
-                     info!("Streamable HTTP transport was not running or already shut down");
-                 }
-             },
-             Err(poisoned) => {
-                 error!("Shutdown signal mutex poisoned during shutdown: {}", poisoned);
-                 return Err(TransportError::ShutdownError("Failed to acquire lock for shutdown signal".to_string()));
-             }
-         }
-         Ok(())
-    }
+pub async fn handle_streamable_delete( State(state): State<Arc<AppState>>, headers: HeaderMap ) -> impl IntoResponse {
+    state.request_count.fetch_add(1, Ordering::SeqCst);
+    if let Some(session_id) = headers.get(&MCP_SESSION_ID).and_then(|v| v.to_str().ok()) {
+        if state.http_sessions.remove(session_id).await { state.message_handler.forget_client(session_id); info!("Session {} terminated via DELETE /mcp", session_id); (StatusCode::OK, "Session terminated").into_response() }
+        else { warn!("DELETE /mcp for non-existent session: {}", session_id); (StatusCode::NOT_FOUND, "Session not found").into_response() }
+    } else { warn!("DELETE /mcp missing mcp-session-id"); (StatusCode::BAD_REQUEST, "mcp-session-id header required").into_response() }
 }
 
-// Removed unused helper function check_if_needs_response
\ No newline at end of file
@@ -0,0 +1,88 @@
+// mcpi-server/src/transport/tcp.rs
+//
+// TCP listener serving the HTTP router, with optional TLS termination.
+
+use super::Shutdown;
+use crate::tls::{self, TlsConfig};
+use crate::traits::{McpTransport, TransportError, TransportFuture};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use std::net::SocketAddr;
+use tokio::net::TcpListener;
+use tracing::info;
+
+enum Listener {
+    Plain(TcpListener),
+    Tls(std::net::TcpListener, RustlsConfig),
+}
+
+pub struct TcpTransport {
+    addr: SocketAddr,
+    tls: Option<TlsConfig>,
+    app: Router,
+    listener: Option<Listener>,
+}
+
+impl TcpTransport {
+    pub fn new(addr: SocketAddr, tls: Option<TlsConfig>, app: Router) -> Self {
+        TcpTransport { addr, tls, app, listener: None }
+    }
+}
+
+impl McpTransport for TcpTransport {
+    fn name(&self) -> &'static str {
+        if self.tls.is_some() { "https" } else { "http" }
+    }
+
+    fn start(&mut self) -> TransportFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            let listener = TcpListener::bind(self.addr)
+                .await
+                .map_err(|e| TransportError::Startup(format!("Cannot listen on {}: {}", self.addr, e)))?;
+            self.listener = Some(match &self.tls {
+                Some(tls) => {
+                    let rustls_config = tls.load().await.map_err(|e| TransportError::Startup(e.to_string()))?;
+                    let listener = listener.into_std().map_err(|e| TransportError::Startup(e.to_string()))?;
+                    Listener::Tls(listener, rustls_config)
+                }
+                None => Listener::Plain(listener),
+            });
+            Ok(())
+        })
+    }
+
+    fn serve(self: Box<Self>, shutdown: Shutdown) -> TransportFuture<'static, Result<(), TransportError>> {
+        Box::pin(async move {
+            let TcpTransport { addr, tls, app, listener } = *self;
+            match listener {
+                Some(Listener::Plain(listener)) => {
+                    info!("Server listening on {}", addr);
+                    axum::serve(listener, app.into_make_service())
+                        .with_graceful_shutdown(shutdown.wait())
+                        .await
+                        .map_err(|e| TransportError::Connection(e.to_string()))
+                }
+                Some(Listener::Tls(listener, rustls_config)) => {
+                    let reloader = tls.map(|tls| tokio::spawn(tls::reload_on_sighup(tls, rustls_config.clone())));
+                    let handle = axum_server::Handle::new();
+                    let shutdown_handle = handle.clone();
+                    tokio::spawn(async move {
+                        shutdown.wait().await;
+                        shutdown_handle.graceful_shutdown(None);
+                    });
+                    info!("Server listening on {} (TLS)", addr);
+                    let result = axum_server::from_tcp_rustls(listener, rustls_config)
+                        .handle(handle)
+                        .serve(app.into_make_service())
+                        .await
+                        .map_err(|e| TransportError::Connection(e.to_string()));
+                    if let Some(reloader) = reloader {
+                        reloader.abort();
+                    }
+                    result
+                }
+                None => Err(TransportError::Startup(format!("{} was never started", addr))),
+            }
+        })
+    }
+}
@@ -0,0 +1,213 @@
+// mcpi-server/src/transport/unix_socket.rs
+//
+// Unix domain socket listener for sidecar deployments. It serves the same router
+// as the TCP listener (MCP, MCPI WebSocket, discovery and admin), and access is
+// controlled by the socket file's permissions. A socket file left by an earlier
+// run is replaced, but never one that a running server still accepts on. On
+// shutdown, open connections finish their requests as on TCP.
+
+use super::Shutdown;
+use crate::config::check_keys;
+use crate::traits::{McpTransport, TransportError, TransportFuture};
+use axum::Router;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto::Builder;
+use hyper_util::server::graceful::GracefulShutdown;
+use hyper_util::service::TowerToHyperService;
+use serde_json::Value;
+use std::fs;
+use std::io;
+use std::os::unix::fs::{DirBuilderExt, FileTypeExt, PermissionsExt};
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use tokio::net::UnixListener;
+use tracing::{info, warn};
+
+/// Socket permissions unless configured: owner and group may connect
+const DEFAULT_SOCKET_MODE: u32 = 0o660;
+
+/// Settings from the optional `unix_socket` block of the server config
+#[derive(Debug, Clone)]
+pub struct UnixSocketConfig {
+    pub path: PathBuf,
+    /// Permission bits applied to the socket file
+    pub mode: u32,
+}
+
+impl UnixSocketConfig {
+    /// Read the `unix_socket` block, e.g. `{ "path": "/run/mcpi/mcpi.sock", "mode": "0660" }`.
+    /// Returns None when no socket is configured.
+    pub fn from_config(config: Option<&Value>) -> Result<Option<Self>, String> {
+        let Some(config) = config else { return Ok(None) };
+        check_keys(Some(config), "unix_socket", &["path", "mode"])?;
+        let path = match config.get("path") {
+            Some(Value::String(path)) => PathBuf::from(path),
+            _ => return Err("unix_socket.path is required and must be a string".to_string()),
+        };
+        let mode = match config.get("mode") {
+            Some(Value::String(mode)) => u32::from_str_radix(mode.trim_start_matches("0o"), 8)
+                .ok()
+                .filter(|m| *m <= 0o777)
+                .ok_or_else(|| format!("Invalid unix_socket.mode '{}', expected octal such as \"0660\"", mode))?,
+            Some(other) => return Err(format!("unix_socket.mode must be an octal string, got {}", other)),
+            None => DEFAULT_SOCKET_MODE,
+        };
+        Ok(Some(UnixSocketConfig { path, mode }))
+    }
+}
+
+pub struct UnixSocketTransport {
+    config: UnixSocketConfig,
+    app: Router,
+    listener: Option<UnixListener>,
+    /// Set once this transport owns the socket file
+    bound: bool,
+}
+
+impl UnixSocketTransport {
+    pub fn new(config: UnixSocketConfig, app: Router) -> Self {
+        UnixSocketTransport { config, app, listener: None, bound: false }
+    }
+
+    fn bind(&self) -> io::Result<UnixListener> {
+        // A socket left behind by an earlier run would make bind fail
+        if let Ok(metadata) = fs::symlink_metadata(&self.config.path) {
+            if !metadata.file_type().is_socket() {
+                return Err(io::Error::new(
+                    io::ErrorKind::AlreadyExists,
+                    format!("{} exists and is not a socket", self.config.path.display()),
+                ));
+            }
+            match UnixStream::connect(&self.config.path) {
+                Err(e) if e.kind() == io::ErrorKind::ConnectionRefused => fs::remove_file(&self.config.path)?,
+                Ok(_) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} is in use by another server", self.config.path.display()),
+                    ))
+                }
+                Err(e) => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::AddrInUse,
+                        format!("{} may be in use, not replacing it: {}", self.config.path.display(), e),
+                    ))
+                }
+            }
+        }
+
+        // Bind inside a private directory and move the socket into place once its
+        // mode is set, so it is never reachable with umask-derived permissions
+        let staging = staging_dir(&self.config.path);
+        fs::DirBuilder::new().mode(0o700).create(&staging)?;
+        let bound = (|| {
+            let staged = staging.join("socket");
+            let listener = UnixListener::bind(&staged)?;
+            fs::set_permissions(&staged, fs::Permissions::from_mode(self.config.mode))?;
+            fs::rename(&staged, &self.config.path)?;
+            Ok(listener)
+        })();
+        let _ = fs::remove_dir_all(&staging);
+        bound
+    }
+}
+
+// Private directory next to the socket path, on the same filesystem so the
+// socket can be renamed out of it
+fn staging_dir(path: &Path) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let parent = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    parent.join(format!(".{}.{}", name, std::process::id()))
+}
+
+impl McpTransport for UnixSocketTransport {
+    fn name(&self) -> &'static str {
+        "unix_socket"
+    }
+
+    fn start(&mut self) -> TransportFuture<'_, Result<(), TransportError>> {
+        Box::pin(async move {
+            let listener = self
+                .bind()
+                .map_err(|e| TransportError::Startup(format!("Cannot listen on {}: {}", self.config.path.display(), e)))?;
+            self.listener = Some(listener);
+            self.bound = true;
+            Ok(())
+        })
+    }
+
+    fn serve(mut self: Box<Self>, shutdown: Shutdown) -> TransportFuture<'static, Result<(), TransportError>> {
+        Box::pin(async move {
+            let Some(listener) = self.listener.take() else {
+                return Err(TransportError::Startup(format!("{} was never started", self.config.path.display())));
+            };
+            info!("Server listening on unix:{} (mode {:o})", self.config.path.display(), self.config.mode);
+
+            let connections = GracefulShutdown::new();
+            let shutdown = shutdown.wait();
+            tokio::pin!(shutdown);
+            loop {
+                let stream = tokio::select! {
+                    accepted = listener.accept() => match accepted {
+                        Ok((stream, _)) => stream,
+                        Err(e) => {
+                            warn!("Unix socket accept error: {}", e);
+                            continue;
+                        }
+                    },
+                    _ = &mut shutdown => break,
+                };
+                let service = TowerToHyperService::new(self.app.clone());
+                let watcher = connections.watcher();
+                tokio::spawn(async move {
+                    // Upgrades are needed for the /mcpi WebSocket endpoint
+                    let builder = Builder::new(TokioExecutor::new());
+                    let connection = builder.serve_connection_with_upgrades(TokioIo::new(stream), service);
+                    if let Err(e) = watcher.watch(connection).await {
+                        warn!("Unix socket connection error: {}", e);
+                    }
+                });
+            }
+            // Let open connections finish their requests, as the TCP listener does
+            connections.shutdown().await;
+            info!("Unix socket listener on {} shut down", self.config.path.display());
+            Ok(())
+        })
+    }
+}
+
+// The socket file is removed however the transport ends, including when another
+// transport fails to start after this one was bound
+impl Drop for UnixSocketTransport {
+    fn drop(&mut self) {
+        if self.bound {
+            let _ = fs::remove_file(&self.config.path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_socket_is_created_with_configured_mode() {
+        let dir = std::env::temp_dir().join(format!("mcpi-uds-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = UnixSocketConfig { path: dir.join("mcpi.sock"), mode: 0o600 };
+        let transport = UnixSocketTransport::new(config.clone(), Router::new());
+        let listener = transport.bind().unwrap();
+
+        let metadata = fs::symlink_metadata(&config.path).unwrap();
+        assert!(metadata.file_type().is_socket());
+        assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+        // Only the socket is left behind, not the staging directory
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+
+        // A socket that is still accepting is not taken over; a stale one is replaced
+        let second = UnixSocketTransport::new(config.clone(), Router::new());
+        assert_eq!(second.bind().err().unwrap().kind(), io::ErrorKind::AddrInUse);
+        drop(listener);
+        assert!(second.bind().is_ok());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -1,108 +1,317 @@
-use crate::transport::traits::{McpTransport, MessageHandler, TransportError};
+// mcpi-server/src/transport/websocket.rs
+//
+// MCPI WebSocket transport. Every connection gets an outbound queue of JSON-RPC
+// messages, registered by client id in `WsConnections`, which is how the rest of
+// the server reaches a WebSocket client. Replies, messages related to in-flight
+// requests and pushed messages all share that queue, so they reach the socket in
+// the order they were produced; a writer task merges it with control frames.
+
+use crate::outbound::{OutboundQueues, OutboundReceiver};
+use crate::router::EventSender;
+use crate::sessions::SessionSummary;
+use crate::AppState;
 use axum::{
-    extract::WebSocketUpgrade,
-    routing::get,
-    Router,
+    extract::{ws::{close_code, CloseFrame, Message, WebSocket, WebSocketUpgrade}, State},
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use futures::{SinkExt, StreamExt};
-use std::net::SocketAddr;
-use std::sync::{Arc, Mutex};
-use tokio::sync::broadcast;
-use tracing::{error, info};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::{mpsc, Semaphore};
+use tokio::task::JoinSet;
+use tracing::{info, warn};
 use uuid::Uuid;
 
-pub struct WebSocketTransport {
-    port: u16,
-    shutdown_signal: Mutex<Option<broadcast::Sender<()>>>,
+// A connected client's JSON-RPC queue and its control frame queue
+struct WsClient {
+    outbound: EventSender,
+    control: mpsc::UnboundedSender<Message>,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    last_activity: Mutex<chrono::DateTime<chrono::Utc>>,
+    // Text messages received from the client
+    messages: AtomicU64,
+}
+
+/// Ceiling on WebSocket messages, as a multiple of the message limit
+const WS_MESSAGE_SLACK: usize = 2;
+
+/// Outbound queues of the connected WebSocket clients, keyed by client id
+pub struct WsConnections {
+    clients: RwLock<HashMap<String, WsClient>>,
+    queues: Arc<OutboundQueues>,
 }
 
-impl WebSocketTransport {
-    pub fn new(port: u16) -> Self {
-        Self {
-            port,
-            shutdown_signal: Mutex::new(None),
+impl WsConnections {
+    pub fn new(queues: Arc<OutboundQueues>) -> Self {
+        WsConnections { clients: RwLock::new(HashMap::new()), queues }
+    }
+
+    fn register(&self, client_id: &str, control: mpsc::UnboundedSender<Message>) -> (EventSender, OutboundReceiver<String>) {
+        let (tx, rx) = self.queues.open(client_id);
+        let now = chrono::Utc::now();
+        let client = WsClient { outbound: tx.clone(), control, connected_at: now, last_activity: Mutex::new(now), messages: AtomicU64::new(0) };
+        self.clients.write().unwrap().insert(client_id.to_string(), client);
+        (tx, rx)
+    }
+
+    fn outbound(&self, client_id: &str) -> Option<EventSender> {
+        self.clients.read().unwrap().get(client_id).map(|client| client.outbound.clone())
+    }
+
+    fn record_message(&self, client_id: &str) {
+        if let Some(client) = self.clients.read().unwrap().get(client_id) {
+            *client.last_activity.lock().unwrap() = chrono::Utc::now();
+            client.messages.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    fn unregister(&self, client_id: &str) {
+        self.clients.write().unwrap().remove(client_id);
+    }
+
+    /// Queue a serialized JSON-RPC message for a client, subject to the
+    /// backpressure policy. Returns false if it will not reach the client.
+    pub async fn send(&self, client_id: &str, message: String) -> bool {
+        match self.outbound(client_id) {
+            Some(outbound) => outbound.send(message).await,
+            None => false,
+        }
+    }
+
+    /// Push a notification to a client
+    #[allow(dead_code)] // Push API for subsystems outside request handling
+    pub async fn notify(&self, client_id: &str, method: &str, params: Value) -> bool {
+        self.send(client_id, json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string()).await
+    }
+
+    /// Queue a message for every connected client and return how many there were.
+    /// Each client gets it on its own task, so a full queue under the Block policy
+    /// holds up only that client.
+    pub async fn broadcast(&self, message: String) -> usize {
+        let outbound: Vec<EventSender> = self.clients.read().unwrap().values().map(|client| client.outbound.clone()).collect();
+        for client in &outbound {
+            let (client, message) = (client.clone(), message.clone());
+            tokio::spawn(async move { client.send(message).await });
         }
+        outbound.len()
+    }
+
+    /// Send every client a Going Away close frame after its queued messages and
+    /// return how many were reached
+    pub fn close_all(&self, reason: &str) -> usize {
+        self.clients
+            .read()
+            .unwrap()
+            .values()
+            .filter(|client| {
+                let frame = CloseFrame { code: close_code::AWAY, reason: reason.to_string().into() };
+                client.control.send(Message::Close(Some(frame))).is_ok()
+            })
+            .count()
+    }
+
+    /// Send one client a close frame after its queued messages. Returns false if
+    /// it is not connected.
+    pub fn close(&self, client_id: &str, reason: &str) -> bool {
+        let frame = CloseFrame { code: close_code::NORMAL, reason: reason.to_string().into() };
+        self.clients
+            .read()
+            .unwrap()
+            .get(client_id)
+            .is_some_and(|client| client.control.send(Message::Close(Some(frame))).is_ok())
+    }
+
+    /// Every connected client, oldest first
+    pub fn list(&self) -> Vec<SessionSummary> {
+        let mut clients: Vec<SessionSummary> = self
+            .clients
+            .read()
+            .unwrap()
+            .iter()
+            .map(|(id, client)| SessionSummary {
+                id: id.clone(),
+                transport: "websocket",
+                connected_at: client.connected_at,
+                last_activity: *client.last_activity.lock().unwrap(),
+                requests: client.messages.load(Ordering::Relaxed),
+                streaming: true,
+            })
+            .collect();
+        clients.sort_by_key(|client| client.connected_at);
+        clients
+    }
+
+    /// Number of connected clients
+    pub fn len(&self) -> usize {
+        self.clients.read().unwrap().len()
     }
 }
 
-impl McpTransport for WebSocketTransport {
-    fn start(&self, message_handler: Arc<dyn MessageHandler>) -> Result<(), TransportError> {
-        let port = self.port;
-        let (shutdown_tx, mut shutdown_rx) = broadcast::channel(1);
-        
-        // Clone for the closure
-        let message_handler = message_handler.clone();
-        
-        // Build application with WebSocket route
-        let app = Router::new()
-            .route("/mcpi", get(move |ws: WebSocketUpgrade| {
-                let handler = message_handler.clone();
-                async move {
-                    ws.on_upgrade(move |socket| handle_socket(socket, handler))
+pub async fn ws_handler(ws: WebSocketUpgrade, State(state): State<Arc<AppState>>, _headers: HeaderMap) -> Response {
+    if state.drain.is_draining() {
+        return (StatusCode::SERVICE_UNAVAILABLE, "Server is shutting down").into_response();
+    }
+    let client_id = format!("ws-{}", Uuid::new_v4());
+    info!("WebSocket upgrade request (/mcpi) from client: {}", client_id);
+    // Messages up to the limit are handled and somewhat larger ones get a JSON-RPC
+    // error; anything beyond that is refused before it is buffered
+    let max_message_bytes = state.message_handler.limits().config().max_message_bytes;
+    let ws = ws.max_message_size(max_message_bytes.saturating_mul(WS_MESSAGE_SLACK));
+    ws.on_upgrade(move |socket| handle_socket(socket, state, client_id))
+}
+
+async fn handle_socket(socket: WebSocket, state: Arc<AppState>, client_id: String) {
+    info!("WebSocket client connected: {}", client_id);
+    let (mut sink, mut stream) = socket.split();
+    let (control, mut control_rx) = mpsc::unbounded_channel::<Message>();
+    let (outbound, mut outbound_rx) = state.ws_connections.register(&client_id, control.clone());
+    // Requests of this connection that may run at the same time, and the tasks running them
+    let requests = Arc::new(Semaphore::new(state.message_handler.limits().config().batch_concurrency));
+    let mut tasks = JoinSet::new();
+
+    let writer_client_id = client_id.clone();
+    let mut writer = tokio::spawn(async move {
+        loop {
+            let message = tokio::select! {
+                text = outbound_rx.recv() => match text {
+                    Some(text) => Message::Text(text),
+                    None if outbound_rx.overflowed() => {
+                        warn!("WS client {} fell behind, disconnecting", writer_client_id);
+                        Message::Close(Some(CloseFrame { code: close_code::AGAIN, reason: "Outbound queue full".into() }))
+                    }
+                    None => break,
+                },
+                Some(frame) = control_rx.recv() => frame,
+                else => break,
+            };
+            let closing = matches!(message, Message::Close(_));
+            if closing {
+                // Deliver replies that are already queued before saying goodbye
+                while let Some(text) = outbound_rx.try_recv() {
+                    if sink.send(Message::Text(text)).await.is_err() {
+                        break;
+                    }
                 }
-            }));
-            
-        // Set up server address
-        let addr = SocketAddr::from(([0, 0, 0, 0], port));
-        
-        info!("Starting WebSocket transport on {}", addr);
-        
-        // Start the server
-        tokio::spawn(async move {
-            axum::Server::bind(&addr)
-                .serve(app.into_make_service())
-                .with_graceful_shutdown(async move {
-                    let _ = shutdown_rx.recv().await;
-                })
-                .await
-                .map_err(|e| error!("WebSocket server error: {}", e))
-                .ok();
-                
-            info!("WebSocket transport shut down");
-        });
-        
-        // Store shutdown sender using proper locking
-        if let Ok(mut guard) = self.shutdown_signal.lock() {
-            *guard = Some(shutdown_tx);
-        } else {
-            return Err(TransportError::StartupError("Failed to lock shutdown signal".to_string()));
+            }
+            if let Err(e) = sink.send(message).await {
+                warn!("WS send error to {}: {}", writer_client_id, e);
+                break;
+            }
+            if closing {
+                break;
+            }
         }
-        
-        Ok(())
-    }
-    
-    fn shutdown(&self) -> Result<(), TransportError> {
-        if let Ok(guard) = self.shutdown_signal.lock() {
-            if let Some(sender) = guard.as_ref() {
-                let _ = sender.send(());
-                info!("Shutdown signal sent to WebSocket transport");
+    });
+
+    loop {
+        let msg_result = tokio::select! {
+            msg_result = stream.next() => match msg_result {
+                Some(msg_result) => msg_result,
+                None => break,
+            },
+            // The writer stops after sending a close frame, e.g. when an admin
+            // terminates the connection; stop reading too
+            _ = &mut writer => break,
+        };
+        match msg_result {
+            Ok(msg) => {
+                if !process_ws_message(msg, &outbound, &control, &state, &client_id, &requests, &mut tasks).await {
+                    break;
+                }
+            }
+            Err(e) => {
+                warn!("WS recv error from {}: {}", client_id, e);
+                break;
             }
-        } else {
-            return Err(TransportError::ShutdownError("Failed to lock shutdown signal".to_string()));
         }
-        Ok(())
     }
+
+    info!("WebSocket client disconnected: {}", client_id);
+    state.ws_connections.unregister(&client_id);
+    // Nobody is left to answer, and a request still running could bring back the
+    // client state forgotten below
+    tasks.shutdown().await;
+    state.message_handler.forget_client(&client_id);
+    writer.abort();
 }
 
-async fn handle_socket(socket: axum::extract::ws::WebSocket, message_handler: Arc<dyn MessageHandler>) {
-    let (mut sender, mut receiver) = socket.split();
-    
-    // Generate client ID
-    let client_id = Uuid::new_v4().to_string();
-    info!("WebSocket connection established: {}", client_id);
-    
-    // Process messages
-    while let Some(Ok(message)) = receiver.next().await {
-        if let axum::extract::ws::Message::Text(text) = message {
-            // Use the future returned by handle_message
-            if let Some(response) = message_handler.handle_message(text, client_id.clone()).await {
-                if let Err(e) = sender.send(axum::extract::ws::Message::Text(response)).await {
-                    error!("Error sending message: {}", e);
-                    break;
+// Handle one incoming frame. Requests run on their own task so a slow tool call
+// does not hold up the rest of the connection, up to `limits.batch_concurrency` at
+// a time; beyond that, reading waits for one to finish. Returns false to close
+// the connection.
+async fn process_ws_message(
+    msg: Message,
+    outbound: &EventSender,
+    control: &mpsc::UnboundedSender<Message>,
+    state: &Arc<AppState>,
+    client_id: &str,
+    requests: &Arc<Semaphore>,
+    tasks: &mut JoinSet<()>,
+) -> bool {
+    match msg {
+        Message::Text(text) => {
+            info!("Received text from WS {}: {}", client_id, text.chars().take(100).collect::<String>());
+            state.ws_connections.record_message(client_id);
+            // The semaphore is never closed
+            let Ok(permit) = requests.clone().acquire_owned().await else { return false };
+            while tasks.try_join_next().is_some() {}
+            let state = state.clone();
+            let events = outbound.clone();
+            let client_id = client_id.to_string();
+            tasks.spawn(async move {
+                let _permit = permit;
+                // Shutdown waits until the response is queued, not just produced
+                let _in_flight = state.drain.track();
+                let handler = state.message_handler.clone();
+                if let Some(response) = handler.handle_message_with_events(text, client_id.clone(), events).await {
+                    if !state.ws_connections.send(&client_id, response).await {
+                        warn!("WS client {} went away before its response was sent", client_id);
+                    }
                 }
+            });
+        }
+        Message::Binary(_) => warn!("Unexpected binary msg from WS {}", client_id),
+        Message::Ping(data) => {
+            if control.send(Message::Pong(data)).is_err() {
+                return false;
             }
         }
+        Message::Pong(_) => info!("Received Pong from WS {}", client_id),
+        Message::Close(_) => {
+            info!("WS client {} sent close frame", client_id);
+            return false;
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::outbound::OutboundConfig;
+
+    #[tokio::test]
+    async fn test_messages_reach_registered_clients() {
+        let connections = WsConnections::new(Arc::new(OutboundQueues::new(OutboundConfig::from_config(None).unwrap())));
+        let (a_control, mut a_control_rx) = mpsc::unbounded_channel();
+        let (b_control, _b_control_rx) = mpsc::unbounded_channel();
+        let (_a_tx, mut a_rx) = connections.register("ws-a", a_control);
+        let (_b_tx, mut b_rx) = connections.register("ws-b", b_control);
+
+        assert!(connections.notify("ws-a", "notifications/tools/list_changed", json!({})).await);
+        assert_eq!(connections.broadcast("hello".to_string()).await, 2);
+        assert!(a_rx.try_recv().unwrap().contains("notifications/tools/list_changed"));
+        assert_eq!(a_rx.recv().await.unwrap(), "hello");
+        assert_eq!(b_rx.recv().await.unwrap(), "hello");
+
+        assert_eq!(connections.close_all("bye"), 2);
+        assert!(matches!(a_control_rx.try_recv().unwrap(), Message::Close(Some(frame)) if frame.code == close_code::AWAY));
+
+        connections.unregister("ws-a");
+        assert!(!connections.send("ws-a", "gone".to_string()).await);
+        assert_eq!(connections.len(), 1);
     }
-    
-    info!("WebSocket connection closed: {}", client_id);
-}
\ No newline at end of file
+}